@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::clock::Clock;
+use crate::error::Error;
+use crate::store::Store;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AccountsConfig {
+    pub secret: Option<String>,
+    pub session_ttl_secs: i64,
+}
+
+impl Default for AccountsConfig {
+    fn default() -> Self {
+        AccountsConfig {
+            secret: None,
+            session_ttl_secs: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Favorites {
+    #[serde(default)]
+    pub professors: Vec<u32>,
+    #[serde(default)]
+    pub sections: Vec<String>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub account_id: String,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub struct Accounts {
+    secret: Option<Vec<u8>>,
+    session_ttl: chrono::Duration,
+    store: Option<Arc<dyn Store>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Accounts {
+    pub fn new(config: &AccountsConfig, store: Option<Arc<dyn Store>>, clock: Arc<dyn Clock>) -> Self {
+        Accounts {
+            secret: config.secret.as_ref().map(|s| s.as_bytes().to_vec()),
+            session_ttl: chrono::Duration::seconds(config.session_ttl_secs),
+            store,
+            clock,
+        }
+    }
+
+    pub fn create(&self) -> Result<Session, Error> {
+        let account_id: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(22)
+            .collect();
+
+        self._session(account_id)
+    }
+
+    fn _session(&self, account_id: String) -> Result<Session, Error> {
+        let expires_at = self.clock.now() + self.session_ttl;
+        let payload = format!("{}:{}", account_id, expires_at.timestamp());
+        let signature = self._sign(payload.as_bytes())?.result().code();
+
+        Ok(Session {
+            token: format!("{}.{}",
+                base64::encode_config(payload.as_bytes(), base64::URL_SAFE_NO_PAD),
+                base64::encode_config(signature.as_slice(), base64::URL_SAFE_NO_PAD)),
+            account_id,
+            expires_at,
+        })
+    }
+
+    pub fn verify(&self, authorization: Option<&str>) -> Result<String, Error> {
+        let token = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or(Error::Unauthorized)?
+            .trim();
+
+        let (payload, signature) = token.split_once('.').ok_or(Error::Unauthorized)?;
+        let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD).map_err(|_| Error::Unauthorized)?;
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).map_err(|_| Error::Unauthorized)?;
+
+        self._sign(payload.as_slice())?
+            .verify(signature.as_slice())
+            .map_err(|_| Error::Unauthorized)?;
+
+        let payload = String::from_utf8(payload).map_err(|_| Error::Unauthorized)?;
+        let (account_id, expires_at) = payload.rsplit_once(':').ok_or(Error::Unauthorized)?;
+        let expires_at = expires_at.parse::<i64>().map_err(|_| Error::Unauthorized)?;
+
+        if expires_at < self.clock.now().timestamp() {
+            return Err(Error::Unauthorized);
+        }
+
+        Ok(account_id.to_owned())
+    }
+
+    pub async fn favorites(&self, account_id: &str) -> Result<Favorites, Error> {
+        Ok(self._store()?.load_favorites(account_id).await?.unwrap_or_default())
+    }
+
+    pub async fn save_favorites(&self, account_id: &str, mut favorites: Favorites) -> Result<Favorites, Error> {
+        favorites.professors.sort_unstable();
+        favorites.professors.dedup();
+        favorites.sections.sort();
+        favorites.sections.dedup();
+        favorites.updated_at = Some(self.clock.now());
+
+        self._store()?.save_favorites(account_id, &favorites).await?;
+
+        Ok(favorites)
+    }
+
+    fn _sign(&self, payload: &[u8]) -> Result<Hmac<Sha256>, Error> {
+        let secret = self.secret.as_ref()
+            .ok_or_else(|| Error::Config("no accounts secret configured".to_owned()))?;
+
+        let mut mac = Hmac::<Sha256>::new_varkey(secret.as_slice())
+            .map_err(|_| Error::Config("invalid accounts secret".to_owned()))?;
+        mac.input(payload);
+
+        Ok(mac)
+    }
+
+    fn _store(&self) -> Result<&Arc<dyn Store>, Error> {
+        self.store.as_ref()
+            .ok_or_else(|| Error::Config("no storage configured".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockClock;
+    use chrono::TimeZone;
+
+    fn with_secret(secret: Option<&str>) -> (Accounts, Arc<MockClock>) {
+        let clock = MockClock::at(Utc.ymd(2024, 1, 1).and_hms(12, 0, 0));
+        let config = AccountsConfig { secret: secret.map(|s| s.to_owned()), session_ttl_secs: 3600 };
+
+        (Accounts::new(&config, None, clock.clone()), clock)
+    }
+
+    fn bearer(token: &str) -> String {
+        format!("Bearer {}", token)
+    }
+
+    fn unauthorized(accounts: &Accounts, token: &str) -> bool {
+        matches!(accounts.verify(Some(bearer(token).as_str())), Err(Error::Unauthorized))
+    }
+
+    #[test]
+    fn sessions_round_trip() {
+        let (accounts, _) = with_secret(Some("s3cret"));
+        let session = accounts.create().unwrap();
+
+        assert_eq!(session.account_id.len(), 22);
+        assert_eq!(session.expires_at, Utc.ymd(2024, 1, 1).and_hms(13, 0, 0));
+        assert_eq!(accounts.verify(Some(bearer(session.token.as_str()).as_str())).unwrap(), session.account_id);
+        assert_eq!(accounts.verify(Some(format!("Bearer  {} ", session.token).as_str())).unwrap(), session.account_id);
+        assert_ne!(accounts.create().unwrap().account_id, session.account_id);
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let (accounts, _) = with_secret(Some("s3cret"));
+        let session = accounts.create().unwrap();
+        let (payload, signature) = session.token.split_once('.').unwrap();
+
+        let forged = format!("someone-else:{}", session.expires_at.timestamp() + 86400);
+        let forged = base64::encode_config(forged.as_bytes(), base64::URL_SAFE_NO_PAD);
+        assert!(unauthorized(&accounts, format!("{}.{}", forged, signature).as_str()));
+
+        let mut flipped = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).unwrap();
+        flipped[0] ^= 1;
+        let flipped = base64::encode_config(flipped.as_slice(), base64::URL_SAFE_NO_PAD);
+        assert!(unauthorized(&accounts, format!("{}.{}", payload, flipped).as_str()));
+
+        assert!(unauthorized(&accounts, payload));
+        assert!(unauthorized(&accounts, format!("{}.", payload).as_str()));
+        assert!(unauthorized(&accounts, format!("{}.{}!", payload, signature).as_str()));
+        assert!(matches!(accounts.verify(Some(session.token.as_str())), Err(Error::Unauthorized)));
+        assert!(matches!(accounts.verify(None), Err(Error::Unauthorized)));
+
+        let (other, _) = with_secret(Some("different"));
+        assert!(unauthorized(&other, session.token.as_str()));
+    }
+
+    #[test]
+    fn sessions_expire_on_the_clock() {
+        let (accounts, clock) = with_secret(Some("s3cret"));
+        let session = accounts.create().unwrap();
+
+        clock.advance(3600);
+        assert!(accounts.verify(Some(bearer(session.token.as_str()).as_str())).is_ok());
+
+        clock.advance(1);
+        assert!(unauthorized(&accounts, session.token.as_str()));
+    }
+
+    #[actix_rt::test]
+    async fn missing_secret_or_store_is_a_config_error() {
+        let (accounts, _) = with_secret(None);
+
+        assert!(matches!(accounts.create(), Err(Error::Config(_))));
+        assert!(matches!(accounts.verify(Some("Bearer YWJjOjE.c2ln")), Err(Error::Config(_))));
+
+        let (accounts, _) = with_secret(Some("s3cret"));
+        assert!(matches!(accounts.favorites("abc").await, Err(Error::Config(_))));
+        assert!(matches!(accounts.save_favorites("abc", Favorites::default()).await, Err(Error::Config(_))));
+    }
+}
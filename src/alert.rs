@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::config::AlertConfig;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaAlert {
+    pub alert: &'static str,
+    pub what: String,
+    pub failures: usize,
+    pub samples: usize,
+    pub rate: f64,
+    pub last_error: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Window {
+    samples: VecDeque<(DateTime<Utc>, bool)>,
+    last_error: String,
+    alerted_at: Option<DateTime<Utc>>,
+}
+
+pub struct SchemaMonitor {
+    config: AlertConfig,
+    client: reqwest::Client,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl SchemaMonitor {
+    pub fn new(config: AlertConfig) -> Self {
+        SchemaMonitor {
+            config,
+            client: reqwest::Client::new(),
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, what: &str, error: Option<&str>, now: DateTime<Utc>) -> Option<SchemaAlert> {
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(what.to_owned()).or_default();
+
+        let cutoff = now - chrono::Duration::seconds(self.config.window_secs);
+        while window.samples.front().map(|(at, _)| *at < cutoff).unwrap_or(false) {
+            window.samples.pop_front();
+        }
+
+        window.samples.push_back((now, error.is_none()));
+        if let Some(error) = error {
+            window.last_error = error.to_owned();
+        }
+
+        let samples = window.samples.len();
+        let failures = window.samples.iter().filter(|(_, ok)| !ok).count();
+        let rate = failures as f64 / samples as f64;
+
+        if error.is_none() || samples < self.config.min_samples || rate < self.config.schema_failure_rate {
+            return None;
+        }
+
+        if let Some(alerted_at) = window.alerted_at {
+            if (now - alerted_at).num_seconds() < self.config.cooldown_secs {
+                return None;
+            }
+        }
+        window.alerted_at = Some(now);
+
+        Some(SchemaAlert {
+            alert: "rmp_schema",
+            what: what.to_owned(),
+            failures,
+            samples,
+            rate,
+            last_error: window.last_error.clone(),
+            at: now,
+        })
+    }
+
+    pub async fn fire(&self, alert: SchemaAlert) {
+        println!("fire: ALERT {} failed schema checks for {}/{} responses ({:.0}%), last error: {}",
+            alert.what, alert.failures, alert.samples, alert.rate * 100.0, alert.last_error);
+
+        if let Some(webhook) = &self.config.webhook {
+            if let Err(e) = self.client.post(webhook.as_str()).json(&alert).send().await {
+                println!("fire: webhook {} error {}", webhook, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn monitor(min_samples: usize) -> SchemaMonitor {
+        SchemaMonitor::new(AlertConfig { min_samples, ..AlertConfig::default() })
+    }
+
+    #[test]
+    fn alerts_once_the_failure_rate_reaches_the_threshold() {
+        let monitor = monitor(4);
+        let now = Utc.ymd(2024, 1, 1).and_hms(12, 0, 0);
+
+        assert!(monitor.record("_professor_comments", None, now).is_none());
+        assert!(monitor.record("_professor_comments", Some("missing /data"), now).is_none());
+        assert!(monitor.record("_professor_comments", None, now).is_none());
+
+        let alert = monitor.record("_professor_comments", Some("missing /data"), now).unwrap();
+        assert_eq!((alert.failures, alert.samples, alert.rate), (2, 4, 0.5));
+        assert_eq!(alert.last_error, "missing /data");
+    }
+
+    #[test]
+    fn successes_never_alert_even_above_the_threshold() {
+        let monitor = monitor(2);
+        let now = Utc.ymd(2024, 1, 1).and_hms(12, 0, 0);
+
+        assert!(monitor.record("_search_professor", Some("invalid json"), now).is_none());
+        assert!(monitor.record("_search_professor", Some("invalid json"), now).is_some());
+        assert!(monitor.record("_search_professor", None, now + chrono::Duration::seconds(AlertConfig::default().cooldown_secs)).is_none());
+    }
+
+    #[test]
+    fn cooldown_suppresses_repeats_until_it_expires() {
+        let monitor = monitor(1);
+        let now = Utc.ymd(2024, 1, 1).and_hms(12, 0, 0);
+        let cooldown = chrono::Duration::seconds(AlertConfig::default().cooldown_secs);
+
+        assert!(monitor.record("_professor_comments", Some("missing /data"), now).is_some());
+        assert!(monitor.record("_professor_comments", Some("missing /data"), now + cooldown - chrono::Duration::seconds(1)).is_none());
+        assert!(monitor.record("_search_professor", Some("invalid json"), now).is_some());
+
+        let repeat = monitor.record("_professor_comments", Some("missing /edges"), now + cooldown).unwrap();
+        assert_eq!(repeat.last_error, "missing /edges");
+    }
+
+    #[test]
+    fn samples_outside_the_window_are_forgotten() {
+        let monitor = monitor(2);
+        let now = Utc.ymd(2024, 1, 1).and_hms(12, 0, 0);
+        let later = now + chrono::Duration::seconds(AlertConfig::default().window_secs + 1);
+
+        assert!(monitor.record("_professor_comments", Some("missing /data"), now).is_none());
+        assert!(monitor.record("_professor_comments", Some("missing /data"), later).is_none());
+
+        let alert = monitor.record("_professor_comments", Some("missing /data"), later).unwrap();
+        assert_eq!((alert.failures, alert.samples), (2, 2));
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use crate::rmp::NormalizedRating;
+
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn seed() -> u64 {
+        rand::random()
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 { 0 } else { self.next_u64() % n }
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+pub fn representative(ratings: &[NormalizedRating], n: usize, rng: &mut Rng) -> Vec<NormalizedRating> {
+    let mut buckets: BTreeMap<u32, Vec<&NormalizedRating>> = BTreeMap::new();
+    for r in ratings {
+        buckets.entry(r.quality().round() as u32).or_default().push(r);
+    }
+
+    let mut buckets: Vec<Vec<&NormalizedRating>> = buckets.into_values()
+        .map(|mut bucket| {
+            rng.shuffle(bucket.as_mut_slice());
+            bucket
+        })
+        .collect();
+
+    let mut picked = Vec::new();
+    while picked.len() < n && buckets.iter().any(|b| !b.is_empty()) {
+        for bucket in buckets.iter_mut() {
+            if picked.len() == n {
+                break;
+            }
+            if let Some(r) = bucket.pop() {
+                picked.push(r.clone());
+            }
+        }
+    }
+
+    picked
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::store::Store;
+
+const MAX_AUDIT_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub actor: String,
+    pub action: String,
+    pub target: String,
+    pub detail: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditQuery {
+    pub limit: Option<usize>,
+    pub action: Option<String>,
+}
+
+pub struct AuditLog {
+    store: Option<Arc<dyn Store>>,
+}
+
+impl AuditLog {
+    pub fn new(store: Option<Arc<dyn Store>>) -> Self {
+        AuditLog { store }
+    }
+
+    pub async fn record(&self, actor: &str, action: &str, target: &str, detail: Option<String>) {
+        println!("record: {} {} {} {:?}", actor, action, target, detail);
+
+        let store = match &self.store {
+            Some(store) => store,
+            None => return,
+        };
+
+        let entry = AuditEntry {
+            actor: actor.to_owned(),
+            action: action.to_owned(),
+            target: target.to_owned(),
+            detail,
+            at: Utc::now(),
+        };
+
+        if let Err(e) = store.save_audit(&entry).await {
+            println!("record: store error {}", e);
+        }
+    }
+
+    pub async fn entries(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, Error> {
+        let store = self.store.as_ref()
+            .ok_or_else(|| Error::Config("no storage configured".to_owned()))?;
+
+        let limit = query.limit.unwrap_or(100).min(MAX_AUDIT_LIMIT);
+
+        store.load_audit(limit, query.action.as_deref()).await
+    }
+}
@@ -0,0 +1,295 @@
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+
+use crate::clock::Clock;
+use crate::config::AuthConfig;
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Read,
+    Admin,
+    Internal,
+}
+
+impl Scope {
+    fn name(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Admin => "admin",
+            Scope::Internal => "internal",
+        }
+    }
+}
+
+pub fn is_probe(path: &str) -> bool {
+    path.trim_start_matches('/') == "readyz"
+}
+
+pub fn route_scope(path: &str) -> Option<Scope> {
+    if is_probe(path) {
+        return None;
+    }
+
+
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+    match segments.as_slice() {
+        ["admin", ..] => Some(Scope::Admin),
+        ["internal", ..] => Some(Scope::Internal),
+        ["r0", "dump", "ratings"] | ["r0", _, "dump", "ratings"] => Some(Scope::Internal),
+        ["r0", "me", ..] | ["r0", "accounts"] => None,
+        _ => Some(Scope::Read),
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+pub struct Claims {
+    pub sub: Option<String>,
+    iss: Option<String>,
+    aud: Option<Audience>,
+    exp: i64,
+    nbf: Option<i64>,
+    #[serde(default)]
+    scope: String,
+}
+
+impl Claims {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scope.split_whitespace().any(|s| s == scope.name())
+    }
+}
+
+pub struct Verifier {
+    config: AuthConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl Verifier {
+    pub fn new(config: &AuthConfig, clock: Arc<dyn Clock>) -> Self {
+        Verifier {
+            config: config.clone(),
+            clock,
+        }
+    }
+
+    pub fn check(&self, path: &str, authorization: Option<&str>) -> Result<(), Error> {
+        let scope = match route_scope(path) {
+            Some(Scope::Read) if !self.config.require_read => return Ok(()),
+            Some(scope) => scope,
+            None => return Ok(()),
+        };
+
+        if self.verify(authorization)?.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(Error::Forbidden)
+        }
+    }
+
+    pub fn verify(&self, authorization: Option<&str>) -> Result<Claims, Error> {
+        let token = authorization
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or(Error::Unauthorized)?
+            .trim();
+
+        let parts: Vec<&str> = token.split('.').collect();
+        let (header, claims, signature) = match parts.as_slice() {
+            [header, claims, signature] => (*header, *claims, *signature),
+            _ => return Err(Error::Unauthorized),
+        };
+
+        let header: Header = decode_part(header)?;
+        if header.alg != "HS256" {
+            return Err(Error::Unauthorized);
+        }
+
+        let signature = base64::decode_config(signature, base64::URL_SAFE_NO_PAD).map_err(|_| Error::Unauthorized)?;
+        let signed = format!("{}.{}", parts[0], claims);
+
+        let verified = self.config.keys.iter()
+            .filter(|(kid, _)| header.kid.as_ref().map(|k| k == *kid).unwrap_or(true))
+            .any(|(_, secret)| {
+                Hmac::<Sha256>::new_varkey(secret.as_bytes())
+                    .map(|mut mac| {
+                        mac.input(signed.as_bytes());
+                        mac.verify(signature.as_slice()).is_ok()
+                    })
+                    .unwrap_or(false)
+            });
+        if !verified {
+            return Err(Error::Unauthorized);
+        }
+
+        let claims: Claims = decode_part(claims)?;
+        let now = self.clock.now().timestamp();
+
+        if claims.exp + self.config.leeway_secs < now || claims.nbf.map(|n| n - self.config.leeway_secs > now).unwrap_or(false) {
+            return Err(Error::Unauthorized);
+        }
+
+        if let Some(issuer) = &self.config.issuer {
+            if claims.iss.as_ref() != Some(issuer) {
+                return Err(Error::Unauthorized);
+            }
+        }
+
+        if let Some(audience) = &self.config.audience {
+            let accepted = match &claims.aud {
+                Some(Audience::One(aud)) => aud == audience,
+                Some(Audience::Many(auds)) => auds.contains(audience),
+                None => false,
+            };
+            if !accepted {
+                return Err(Error::Unauthorized);
+            }
+        }
+
+        Ok(claims)
+    }
+}
+
+fn decode_part<T: serde::de::DeserializeOwned>(part: &str) -> Result<T, Error> {
+    let decoded = base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| Error::Unauthorized)?;
+
+    serde_json::from_slice(decoded.as_slice()).map_err(|_| Error::Unauthorized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockClock;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn encode(value: &serde_json::Value) -> String {
+        base64::encode_config(value.to_string().as_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    fn sign(header: serde_json::Value, claims: serde_json::Value, secret: &str) -> String {
+        let signed = format!("{}.{}", encode(&header), encode(&claims));
+        let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).unwrap();
+        mac.input(signed.as_bytes());
+
+        format!("Bearer {}.{}", signed, base64::encode_config(mac.result().code().as_slice(), base64::URL_SAFE_NO_PAD))
+    }
+
+    fn token(claims: serde_json::Value) -> String {
+        sign(json!({"alg": "HS256", "kid": "k1"}), claims, "secret-1")
+    }
+
+    fn verifier(config: AuthConfig) -> Verifier {
+        let keys = vec![("k1".to_owned(), "secret-1".to_owned()), ("k2".to_owned(), "secret-2".to_owned())];
+
+        Verifier::new(&AuthConfig { keys: keys.into_iter().collect(), ..config }, MockClock::at(Utc.timestamp(NOW, 0)))
+    }
+
+    fn rejected(verifier: &Verifier, authorization: &str) -> bool {
+        matches!(verifier.verify(Some(authorization)), Err(Error::Unauthorized))
+    }
+
+    #[test]
+    fn signatures_must_match_a_configured_key() {
+        let verifier = verifier(AuthConfig::default());
+        let claims = json!({"exp": NOW + 60, "scope": "read"});
+
+        assert!(verifier.verify(Some(token(claims.clone()).as_str())).is_ok());
+        assert!(verifier.verify(Some(sign(json!({"alg": "HS256"}), claims.clone(), "secret-2").as_str())).is_ok());
+
+        assert!(rejected(&verifier, sign(json!({"alg": "HS256", "kid": "k1"}), claims.clone(), "secret-2").as_str()));
+        assert!(rejected(&verifier, sign(json!({"alg": "HS256", "kid": "k3"}), claims.clone(), "secret-1").as_str()));
+        assert!(rejected(&verifier, sign(json!({"alg": "HS256"}), claims.clone(), "guessed").as_str()));
+
+        let valid = token(claims);
+        let parts: Vec<&str> = valid.trim_start_matches("Bearer ").split('.').collect();
+        let tampered = format!("Bearer {}.{}.{}", parts[0], encode(&json!({"exp": NOW + 60, "scope": "admin"})), parts[2]);
+        assert!(rejected(&verifier, tampered.as_str()));
+        assert!(rejected(&verifier, format!("Bearer {}.{}.", parts[0], parts[1]).as_str()));
+        assert!(rejected(&verifier, format!("Bearer {}.{}", parts[0], parts[1]).as_str()));
+        assert!(rejected(&verifier, valid.trim_start_matches("Bearer ")));
+        assert!(matches!(verifier.verify(None), Err(Error::Unauthorized)));
+    }
+
+    #[test]
+    fn only_hs256_is_accepted() {
+        let verifier = verifier(AuthConfig::default());
+        let claims = json!({"exp": NOW + 60, "scope": "admin"});
+
+        let none = format!("Bearer {}.{}.", encode(&json!({"alg": "none"})), encode(&claims));
+        assert!(rejected(&verifier, none.as_str()));
+        assert!(rejected(&verifier, sign(json!({"alg": "none", "kid": "k1"}), claims.clone(), "secret-1").as_str()));
+        assert!(rejected(&verifier, sign(json!({"alg": "RS256", "kid": "k1"}), claims.clone(), "secret-1").as_str()));
+        assert!(rejected(&verifier, sign(json!({"alg": "hs256", "kid": "k1"}), claims, "secret-1").as_str()));
+    }
+
+    #[test]
+    fn expiry_and_not_before_allow_the_leeway_and_no_more() {
+        let verifier = verifier(AuthConfig { leeway_secs: 30, ..AuthConfig::default() });
+
+        assert!(verifier.verify(Some(token(json!({"exp": NOW - 30})).as_str())).is_ok());
+        assert!(rejected(&verifier, token(json!({"exp": NOW - 31})).as_str()));
+
+        assert!(verifier.verify(Some(token(json!({"exp": NOW + 60, "nbf": NOW + 30})).as_str())).is_ok());
+        assert!(rejected(&verifier, token(json!({"exp": NOW + 60, "nbf": NOW + 31})).as_str()));
+
+        assert!(rejected(&verifier, token(json!({"scope": "read"})).as_str()));
+    }
+
+    #[test]
+    fn issuer_and_audience_must_match_when_configured() {
+        let verifier = verifier(AuthConfig {
+            issuer: Some("https://auth.example.com".to_owned()),
+            audience: Some("ucsb-courses".to_owned()),
+            ..AuthConfig::default()
+        });
+        let claims = |iss: serde_json::Value, aud: serde_json::Value| token(json!({"exp": NOW + 60, "iss": iss, "aud": aud}));
+
+        assert!(verifier.verify(Some(claims(json!("https://auth.example.com"), json!("ucsb-courses")).as_str())).is_ok());
+        assert!(verifier.verify(Some(claims(json!("https://auth.example.com"), json!(["other", "ucsb-courses"])).as_str())).is_ok());
+
+        assert!(rejected(&verifier, claims(json!("https://evil.example.com"), json!("ucsb-courses")).as_str()));
+        assert!(rejected(&verifier, claims(json!(null), json!("ucsb-courses")).as_str()));
+        assert!(rejected(&verifier, claims(json!("https://auth.example.com"), json!("other")).as_str()));
+        assert!(rejected(&verifier, claims(json!("https://auth.example.com"), json!(["other"])).as_str()));
+        assert!(rejected(&verifier, claims(json!("https://auth.example.com"), json!([])).as_str()));
+        assert!(rejected(&verifier, claims(json!("https://auth.example.com"), json!(null)).as_str()));
+    }
+
+    #[test]
+    fn missing_scopes_are_forbidden_not_unauthorized() {
+        let strict = verifier(AuthConfig { require_read: true, ..AuthConfig::default() });
+        let verifier = verifier(AuthConfig::default());
+        let read = token(json!({"exp": NOW + 60, "scope": "read"}));
+        let admin = token(json!({"exp": NOW + 60, "scope": "read admin"}));
+
+        assert!(matches!(verifier.check("/admin/audit", Some(read.as_str())), Err(Error::Forbidden)));
+        assert!(matches!(verifier.check("/admin/audit", Some("Bearer nope")), Err(Error::Unauthorized)));
+        assert!(matches!(verifier.check("/admin/audit", None), Err(Error::Unauthorized)));
+        assert!(verifier.check("/admin/audit", Some(admin.as_str())).is_ok());
+        assert!(matches!(verifier.check("/internal/metrics", Some(admin.as_str())), Err(Error::Forbidden)));
+        assert!(matches!(verifier.check("/r0/dump/ratings", Some(admin.as_str())), Err(Error::Forbidden)));
+
+        assert!(verifier.check("/r0/professor/ada/overview", None).is_ok());
+        assert!(verifier.check("/readyz", None).is_ok());
+        assert!(matches!(strict.check("/r0/professor/ada/overview", None), Err(Error::Unauthorized)));
+        assert!(strict.check("/r0/professor/ada/overview", Some(read.as_str())).is_ok());
+        assert!(strict.check("/r0/accounts", None).is_ok());
+    }
+}
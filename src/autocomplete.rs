@@ -0,0 +1,56 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+pub struct PrefixIndex<T: Ord + Clone> {
+    keys: BTreeMap<String, BTreeSet<T>>,
+}
+
+impl<T: Ord + Clone> PrefixIndex<T> {
+    pub fn new() -> Self {
+        PrefixIndex {
+            keys: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, key: &str, id: T) {
+        let key = key.trim().to_lowercase();
+        if key.is_empty() {
+            return;
+        }
+
+        self.keys.entry(key).or_default().insert(id);
+    }
+
+    pub fn get(&self, key: &str) -> Vec<T> {
+        self.keys.get(key.trim().to_lowercase().as_str())
+            .map(|ids| ids.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn search(&self, prefix: &str, limit: usize) -> Vec<T> {
+        let prefix = prefix.trim().to_lowercase();
+        let mut ids: Vec<T> = Vec::new();
+
+        if prefix.is_empty() {
+            return ids;
+        }
+
+        for (key, key_ids) in self.keys.range(prefix.clone()..) {
+            if !key.starts_with(prefix.as_str()) {
+                break;
+            }
+
+            for id in key_ids {
+                if !ids.contains(id) {
+                    ids.push(id.clone());
+                }
+            }
+
+            if ids.len() >= limit {
+                break;
+            }
+        }
+
+        ids.truncate(limit);
+        ids
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::error::Error;
+
+const KEEP_HOURS: i64 = 48;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Upstream {
+    Solr,
+    Graphql,
+    Token,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Counts {
+    pub solr: u64,
+    pub graphql: u64,
+    pub token: u64,
+}
+
+impl Counts {
+    pub fn total(&self) -> u64 {
+        self.solr + self.graphql + self.token
+    }
+
+    fn add(&mut self, upstream: Upstream) {
+        match upstream {
+            Upstream::Solr => self.solr += 1,
+            Upstream::Graphql => self.graphql += 1,
+            Upstream::Token => self.token += 1,
+        }
+    }
+
+    fn sum<'a, I: Iterator<Item = &'a Counts>>(counts: I) -> Counts {
+        counts.fold(Counts::default(), |acc, c| Counts {
+            solr: acc.solr + c.solr,
+            graphql: acc.graphql + c.graphql,
+            token: acc.token + c.token,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HourCounts {
+    pub hour: DateTime<Utc>,
+    #[serde(flatten)]
+    pub counts: Counts,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetReport {
+    pub daily_budget: Option<u64>,
+    pub remaining: Option<u64>,
+    pub exhausted: bool,
+    pub resets_at: DateTime<Utc>,
+    pub today: Counts,
+    pub since_start: Counts,
+    pub hours: Vec<HourCounts>,
+}
+
+#[derive(Default)]
+struct Ledger {
+    hours: BTreeMap<i64, Counts>,
+    since_start: Counts,
+}
+
+impl Ledger {
+    fn today(&self, now: DateTime<Utc>) -> Counts {
+        Counts::sum(self.hours.range(day_start(now).timestamp() / 3600..).map(|(_, c)| c))
+    }
+}
+
+#[derive(Default)]
+pub struct UpstreamBudget {
+    daily: u64,
+    ledger: Mutex<Ledger>,
+}
+
+fn day_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date().and_hms(0, 0, 0)
+}
+
+fn resets_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    day_start(now) + Duration::days(1)
+}
+
+impl UpstreamBudget {
+    pub fn new(daily: u64) -> Self {
+        UpstreamBudget {
+            daily,
+            ledger: Mutex::new(Ledger::default()),
+        }
+    }
+
+    pub fn exhausted(&self, now: DateTime<Utc>) -> bool {
+        self.daily > 0 && self.ledger.lock().unwrap().today(now).total() >= self.daily
+    }
+
+    pub fn spend(&self, upstream: Upstream, now: DateTime<Utc>) -> Result<(), Error> {
+        let mut ledger = self.ledger.lock().unwrap();
+
+        let used = ledger.today(now).total();
+        if self.daily > 0 && used >= self.daily {
+            return Err(Error::Shedding((resets_at(now) - now).num_seconds().max(1) as u64));
+        }
+
+        let hour = now.timestamp() / 3600;
+        ledger.hours.entry(hour).or_default().add(upstream);
+        ledger.since_start.add(upstream);
+        ledger.hours.retain(|h, _| *h > hour - KEEP_HOURS);
+
+        if self.daily > 0 && used + 1 == self.daily {
+            println!("spend: daily upstream budget of {} calls exhausted until {}", self.daily, resets_at(now));
+        }
+
+        Ok(())
+    }
+
+    pub fn report(&self, now: DateTime<Utc>) -> BudgetReport {
+        let ledger = self.ledger.lock().unwrap();
+        let today = ledger.today(now);
+        let daily_budget = Some(self.daily).filter(|d| *d > 0);
+
+        BudgetReport {
+            daily_budget,
+            remaining: daily_budget.map(|d| d.saturating_sub(today.total())),
+            exhausted: daily_budget.map(|d| today.total() >= d).unwrap_or(false),
+            resets_at: resets_at(now),
+            today,
+            since_start: ledger.since_start,
+            hours: ledger.hours.iter()
+                .map(|(hour, counts)| HourCounts { hour: Utc.timestamp(hour * 3600, 0), counts: *counts })
+                .collect(),
+        }
+    }
+
+    pub fn metrics(&self, now: DateTime<Utc>) -> String {
+        let report = self.report(now);
+
+        let mut lines = vec![
+            "# TYPE ucsb_courses_upstream_calls_total counter".to_owned(),
+            format!("ucsb_courses_upstream_calls_total{{upstream=\"solr\"}} {}", report.since_start.solr),
+            format!("ucsb_courses_upstream_calls_total{{upstream=\"graphql\"}} {}", report.since_start.graphql),
+            format!("ucsb_courses_upstream_calls_total{{upstream=\"token\"}} {}", report.since_start.token),
+            "# TYPE ucsb_courses_upstream_calls_today gauge".to_owned(),
+            format!("ucsb_courses_upstream_calls_today {}", report.today.total()),
+            "# TYPE ucsb_courses_upstream_budget_exhausted gauge".to_owned(),
+            format!("ucsb_courses_upstream_budget_exhausted {}", report.exhausted as u8),
+        ];
+        if let Some(remaining) = report.remaining {
+            lines.push("# TYPE ucsb_courses_upstream_budget_remaining gauge".to_owned());
+            lines.push(format!("ucsb_courses_upstream_budget_remaining {}", remaining));
+        }
+
+        lines.join("\n") + "\n"
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{BTreeMap, HashMap};
+
+pub struct Lru<V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<u32, (u64, V)>,
+    order: BTreeMap<u64, u32>,
+}
+
+impl<V> Lru<V> {
+    pub fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &u32) -> Option<&V> {
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &u32) -> Option<&mut V> {
+        self.entries.get_mut(key).map(|(_, value)| value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u32, &V)> {
+        self.entries.iter().map(|(key, (_, value))| (key, value))
+    }
+
+    pub fn touch(&mut self, key: &u32) {
+        let tick = self._next_tick();
+
+        if let Some((used, _)) = self.entries.get_mut(key) {
+            self.order.remove(used);
+            *used = tick;
+            self.order.insert(tick, *key);
+        }
+    }
+
+    pub fn insert(&mut self, key: u32, value: V) -> Vec<u32> {
+        let tick = self._next_tick();
+
+        if let Some((used, _)) = self.entries.insert(key, (tick, value)) {
+            self.order.remove(&used);
+        }
+        self.order.insert(tick, key);
+
+        let mut evicted = Vec::new();
+        while self.capacity > 0 && self.entries.len() > self.capacity {
+            match self.order.pop_first() {
+                Some((_, oldest)) => {
+                    self.entries.remove(&oldest);
+                    evicted.push(oldest);
+                }
+                None => break,
+            }
+        }
+
+        evicted
+    }
+
+    pub fn remove(&mut self, key: &u32) -> Option<V> {
+        let (used, value) = self.entries.remove(key)?;
+        self.order.remove(&used);
+
+        Some(value)
+    }
+
+    fn _next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_first() {
+        let mut lru = Lru::new(2);
+
+        assert!(lru.insert(1, "a").is_empty());
+        assert!(lru.insert(2, "b").is_empty());
+        lru.touch(&1);
+        assert_eq!(lru.insert(3, "c"), vec![2]);
+        assert_eq!(lru.get(&1), Some(&"a"));
+        assert_eq!(lru.get(&2), None);
+
+        assert!(lru.insert(3, "d").is_empty());
+        assert_eq!(lru.insert(4, "e"), vec![1]);
+        assert_eq!(lru.iter().count(), 2);
+    }
+
+    #[test]
+    fn removed_and_peeked_entries_do_not_change_the_order() {
+        let mut lru = Lru::new(2);
+
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        assert_eq!(lru.get(&1), Some(&"a"));
+        *lru.get_mut(&1).unwrap() = "z";
+        assert_eq!(lru.insert(3, "c"), vec![1]);
+
+        assert_eq!(lru.remove(&2), Some("b"));
+        assert_eq!(lru.remove(&2), None);
+        assert!(lru.insert(4, "d").is_empty());
+        assert_eq!(lru.iter().count(), 2);
+    }
+
+    #[test]
+    fn zero_capacity_never_evicts() {
+        let mut lru = Lru::new(0);
+
+        for key in 0..100 {
+            assert!(lru.insert(key, key).is_empty());
+        }
+        assert_eq!(lru.iter().count(), 100);
+    }
+}
@@ -0,0 +1,142 @@
+use reqwest::Url;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+use crate::models::{Comment, Page, ProfessorComments, ProfessorMatch, ProfessorResponse, ProfessorSuggestion};
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("invalid base url")]
+    InvalidUrl,
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("server returned {status} {code}")]
+    Api {
+        status: u16,
+        code: String,
+        message: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    error: String,
+    message: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct UcsbCoursesClient {
+    base: Url,
+    school: Option<String>,
+    client: reqwest::Client,
+}
+
+impl UcsbCoursesClient {
+    pub fn new(base_url: &str) -> Result<Self, ClientError> {
+        let base = Url::parse(base_url).map_err(|_| ClientError::InvalidUrl)?;
+
+        if base.cannot_be_a_base() {
+            return Err(ClientError::InvalidUrl);
+        }
+
+        Ok(UcsbCoursesClient {
+            base,
+            school: None,
+            client: reqwest::Client::default(),
+        })
+    }
+
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_school(mut self, school: &str) -> Self {
+        self.school = Some(school.to_owned());
+        self
+    }
+
+    pub async fn professor_overview(&self, name: &str) -> Result<ProfessorResponse, ClientError> {
+        self._get(self._url("r0", &["professor", name, "overview"]), &[]).await
+    }
+
+    pub async fn professor_comments(&self, name: &str) -> Result<Vec<Comment>, ClientError> {
+        self._get(self._url("r0", &["professor", name, "comments"]), &[]).await
+    }
+
+    pub async fn professor_course_comments(&self, name: &str, course: &str) -> Result<Vec<Comment>, ClientError> {
+        self._get(self._url("r0", &["professor", name, "course", course, "comments"]), &[]).await
+    }
+
+    pub async fn professors_by_last_name(&self, last_name: &str) -> Result<Vec<ProfessorMatch>, ClientError> {
+        self._get(self._url("r0", &["professor", "lastname", last_name]), &[]).await
+    }
+
+    pub async fn autocomplete_professor(&self, query: &str, limit: Option<usize>) -> Result<Vec<ProfessorSuggestion>, ClientError> {
+        self._get(self._url("r0", &["autocomplete", "professor"]), &Self::_autocomplete_query(query, limit)).await
+    }
+
+    pub async fn autocomplete_course(&self, query: &str, limit: Option<usize>) -> Result<Vec<String>, ClientError> {
+        self._get(self._url("r0", &["autocomplete", "course"]), &Self::_autocomplete_query(query, limit)).await
+    }
+
+    pub async fn comments_page(&self, name: &str, course: Option<&str>, page: usize, per_page: usize) -> Result<Page<ProfessorComments>, ClientError> {
+        let url = match course {
+            Some(course) => self._url("r1", &["professor", name, "course", course, "comments"]),
+            None => self._url("r1", &["professor", name, "comments"]),
+        };
+
+        self._get(url, &[("page", page.to_string()), ("per_page", per_page.to_string())]).await
+    }
+
+    fn _autocomplete_query(query: &str, limit: Option<usize>) -> Vec<(&'static str, String)> {
+        let mut params = vec![("q", query.to_owned())];
+
+        if let Some(limit) = limit {
+            params.push(("limit", limit.to_string()));
+        }
+
+        params
+    }
+
+    fn _url(&self, version: &str, segments: &[&str]) -> Url {
+        let mut url = self.base.clone();
+
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().push(version);
+
+            if let Some(school) = &self.school {
+                path.push(school.as_str());
+            }
+
+            path.extend(segments);
+        }
+
+        url
+    }
+
+    async fn _get<T: DeserializeOwned>(&self, url: Url, query: &[(&str, String)]) -> Result<T, ClientError> {
+        let resp = self.client.get(url)
+            .query(query)
+            .send()
+            .await?;
+
+        let status = resp.status();
+
+        if !status.is_success() {
+            let (code, message) = match resp.json::<ApiError>().await {
+                Ok(e) => (e.error, e.message),
+                Err(_) => (status.canonical_reason().unwrap_or("UNKNOWN").to_owned(), None),
+            };
+
+            return Err(ClientError::Api {
+                status: status.as_u16(),
+                code,
+                message,
+            });
+        }
+
+        Ok(resp.json::<T>().await?)
+    }
+}
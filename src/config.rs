@@ -0,0 +1,372 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::account::AccountsConfig;
+use crate::department;
+use crate::outbound::OutboundConfig;
+use crate::proxy::ProxyConfig;
+use crate::quota::QuotaConfig;
+use crate::rmp::{self, Freshness, GraphQLConfig, ScorerConfig, SolrConfig};
+use crate::version::VersionsConfig;
+#[cfg(feature = "scheduler")]
+use crate::scheduler::SchedulerConfig;
+#[cfg(feature = "scheduler")]
+use crate::mail::MailConfig;
+
+const CONFIG_ENV: &str = "UCSB_COURSES_CONFIG";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub mode: Mode,
+    pub public: PublicConfig,
+    pub scorer: ScorerConfig,
+    pub crawl: CrawlConfig,
+    pub storage: StorageConfig,
+    #[cfg(feature = "scheduler")]
+    pub scheduler: SchedulerConfig,
+    #[cfg(feature = "scheduler")]
+    pub mail: MailConfig,
+    pub stale_fallback: StaleFallbackConfig,
+    pub schools: SchoolsConfig,
+    pub rmp: RmpConfig,
+    pub access: AccessConfig,
+    pub auth: AuthConfig,
+    pub warmup: WarmupConfig,
+    pub limits: LimitsConfig,
+    pub accounts: AccountsConfig,
+    pub quotas: QuotaConfig,
+    pub shedding: SheddingConfig,
+    pub alerts: AlertConfig,
+    pub versions: VersionsConfig,
+    pub proxy: ProxyConfig,
+    pub format: FormatConfig,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    #[default]
+    Full,
+    Public,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FormatConfig {
+    pub precision: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PublicConfig {
+    pub concurrency: usize,
+}
+
+impl Default for PublicConfig {
+    fn default() -> Self {
+        PublicConfig {
+            concurrency: 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    pub webhook: Option<String>,
+    pub schema_failure_rate: f64,
+    pub min_samples: usize,
+    pub window_secs: i64,
+    pub cooldown_secs: i64,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        AlertConfig {
+            webhook: None,
+            schema_failure_rate: 0.5,
+            min_samples: 10,
+            window_secs: 900,
+            cooldown_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SheddingConfig {
+    pub enabled: bool,
+    pub retry_after_secs: u64,
+    pub refresh_slowdown: u32,
+}
+
+impl Default for SheddingConfig {
+    fn default() -> Self {
+        SheddingConfig {
+            enabled: false,
+            retry_after_secs: 300,
+            refresh_slowdown: 4,
+        }
+    }
+}
+
+impl SheddingConfig {
+    pub fn pace(&self, pace: std::time::Duration) -> std::time::Duration {
+        if self.enabled {
+            pace * self.refresh_slowdown.max(1)
+        } else {
+            pace
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub default: usize,
+    pub routes: HashMap<String, usize>,
+    pub retry_after_secs: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            default: 256,
+            routes: HashMap::new(),
+            retry_after_secs: 1,
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn limit(&self, route: &str) -> usize {
+        self.routes.get(route).cloned().unwrap_or(self.default)
+    }
+
+    pub fn capped(&self, max: usize) -> Self {
+        LimitsConfig {
+            default: self.default.min(max),
+            routes: self.routes.iter().map(|(route, limit)| (route.clone(), (*limit).min(max))).collect(),
+            retry_after_secs: self.retry_after_secs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WarmupConfig {
+    pub professors: Vec<String>,
+    pub file: Option<String>,
+    pub concurrency: usize,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        WarmupConfig {
+            professors: Vec::new(),
+            file: None,
+            concurrency: 4,
+        }
+    }
+}
+
+impl WarmupConfig {
+    pub fn entries(&self) -> std::io::Result<Vec<String>> {
+        let mut entries = self.professors.clone();
+
+        if let Some(file) = &self.file {
+            let content = std::fs::read_to_string(file)?;
+            entries.extend(content.lines()
+                .map(|l| l.trim())
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(|l| l.to_owned()));
+        }
+
+        Ok(entries)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+    pub keys: HashMap<String, String>,
+    pub require_read: bool,
+    pub leeway_secs: i64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            issuer: None,
+            audience: None,
+            keys: HashMap::new(),
+            require_read: false,
+            leeway_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AccessConfig {
+    pub allow_departments: Vec<String>,
+    pub block_departments: Vec<String>,
+    pub allow_professors: Vec<u32>,
+    pub block_professors: Vec<u32>,
+}
+
+impl AccessConfig {
+    pub fn permits(&self, rmp_id: u32, department: &str) -> bool {
+        if self.block_professors.contains(&rmp_id) {
+            return false;
+        }
+
+        if self.allow_professors.contains(&rmp_id) {
+            return true;
+        }
+
+        if self.block_departments.iter().any(|d| department::matches(department, d.as_str())) {
+            return false;
+        }
+
+        if !self.allow_departments.is_empty() {
+            return self.allow_departments.iter().any(|d| department::matches(department, d.as_str()));
+        }
+
+        self.allow_professors.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RmpConfig {
+    pub timeout_ms: u64,
+    pub refresh_min_interval: i64,
+    pub ratings_ttl: i64,
+    pub empty_ratings_ttl: i64,
+    pub ttl_jitter: i64,
+    pub score_ttl: i64,
+    pub identity_ttl: i64,
+    pub daily_budget: u64,
+    pub outbound: OutboundConfig,
+    pub solr: SolrConfig,
+    pub graphql: GraphQLConfig,
+}
+
+impl Default for RmpConfig {
+    fn default() -> Self {
+        RmpConfig {
+            timeout_ms: 10000,
+            refresh_min_interval: 300,
+            ratings_ttl: 21600,
+            empty_ratings_ttl: 3600,
+            ttl_jitter: 3600,
+            score_ttl: 300,
+            identity_ttl: 604800,
+            daily_budget: 0,
+            outbound: OutboundConfig::default(),
+            solr: SolrConfig::default(),
+            graphql: GraphQLConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchoolConfig {
+    pub slug: String,
+    pub rmp_id: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SchoolsConfig {
+    pub enabled: Vec<SchoolConfig>,
+    pub default: String,
+}
+
+impl Default for SchoolsConfig {
+    fn default() -> Self {
+        SchoolsConfig {
+            enabled: vec![SchoolConfig {
+                slug: "ucsb".to_owned(),
+                rmp_id: rmp::DEFAULT_SCHOOL_ID,
+            }],
+            default: "ucsb".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StaleFallbackConfig {
+    pub enabled: bool,
+    pub routes: HashMap<String, bool>,
+}
+
+impl Default for StaleFallbackConfig {
+    fn default() -> Self {
+        StaleFallbackConfig {
+            enabled: true,
+            routes: HashMap::new(),
+        }
+    }
+}
+
+impl StaleFallbackConfig {
+    pub fn freshness(&self, route: &str, freshness: Freshness) -> Freshness {
+        Freshness {
+            allow_stale: self.routes.get(route).cloned().unwrap_or(self.enabled),
+            ..freshness
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub url: Option<String>,
+    pub cache_size: usize,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            url: None,
+            cache_size: 256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CrawlConfig {
+    pub enabled: bool,
+    pub pace_ms: u64,
+    pub departments: Vec<String>,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        CrawlConfig {
+            enabled: false,
+            pace_ms: 1000,
+            departments: Vec::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    pub fn load() -> std::io::Result<Self> {
+        match std::env::var(CONFIG_ENV) {
+            Ok(path) => {
+                let content = std::fs::read_to_string(path)?;
+                serde_json::from_str(content.as_str())
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+            }
+            Err(_) => Ok(AppConfig::default()),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::OnceLock;
+
+use crate::department;
+use crate::ucsb::ClassResponse;
+
+const CATALOG_URL: &str = "https://catalog.ucsb.edu/courses";
+
+static COURSE_RE: OnceLock<Regex> = OnceLock::new();
+
+pub fn compact_course(course: &str) -> String {
+    course.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+pub fn normalize_course(course: &str) -> Option<String> {
+    let re = COURSE_RE.get_or_init(|| Regex::new("^([A-Z]+)([0-9]+)([A-Z]*)$").unwrap());
+
+    let compact = compact_course(course);
+    let cap = re.captures(compact.as_str())?;
+
+    Some(format!("{} {}{}", &cap[1], &cap[2], &cap[3]))
+}
+
+pub fn catalog_url(course: &str) -> Option<String> {
+    let normalized = normalize_course(course)?;
+    let (subject, number) = normalized.split_once(' ')?;
+    let subject = department::DEPARTMENTS.iter()
+        .flat_map(|d| d.ucsb_codes.iter())
+        .find(|code| compact_course(code) == subject)
+        .copied()
+        .unwrap_or(subject);
+
+    Some(format!("{}/{}%20{}", CATALOG_URL, subject.replace(' ', "%20"), number))
+}
+
+pub fn same_course(a: &str, b: &str) -> bool {
+    match (normalize_course(a), normalize_course(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => compact_course(a) == compact_course(b),
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CrossListings {
+    groups: HashMap<String, BTreeSet<String>>,
+}
+
+impl CrossListings {
+    pub fn from_classes(classes: &[ClassResponse]) -> Self {
+        let mut meetings: HashMap<String, BTreeSet<String>> = HashMap::new();
+
+        for class in classes {
+            let course = match normalize_course(class.course_id.as_str()) {
+                Some(course) => course,
+                None => continue,
+            };
+
+            for section in class.class_sections.iter().filter(|s| s.section.ends_with("00")) {
+                let mut instructors: Vec<&str> = section.instructors.iter().map(|i| i.instructor.trim()).collect();
+                instructors.sort_unstable();
+
+                let times: Vec<String> = section.time_locations.iter()
+                    .filter_map(|t| match (&t.days, &t.begin_time, &t.end_time) {
+                        (Some(days), Some(begin), Some(end)) => Some(format!("{}@{}-{}", days.trim(), begin, end)),
+                        _ => None,
+                    })
+                    .collect();
+
+                if instructors.is_empty() || times.is_empty() {
+                    continue;
+                }
+
+                meetings.entry(format!("{}|{}", instructors.join(","), times.join(","))).or_default()
+                    .insert(course.clone());
+            }
+        }
+
+        let mut cross_listings = CrossListings::default();
+        for courses in meetings.into_values().filter(|c| c.len() > 1) {
+            cross_listings._merge(courses);
+        }
+
+        cross_listings
+    }
+
+    fn _merge(&mut self, courses: BTreeSet<String>) {
+        let mut group = courses.clone();
+        for course in courses.iter() {
+            if let Some(existing) = self.groups.get(course) {
+                group.extend(existing.iter().cloned());
+            }
+        }
+
+        for course in group.iter() {
+            self.groups.insert(course.clone(), group.clone());
+        }
+    }
+
+    pub fn course_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn aliases(&self, course: &str) -> Vec<String> {
+        let normalized = match normalize_course(course) {
+            Some(normalized) => normalized,
+            None => return vec![course.to_owned()],
+        };
+
+        match self.groups.get(&normalized) {
+            Some(group) => group.iter().cloned().collect(),
+            None => vec![normalized],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn courses_normalize_regardless_of_spacing_and_case() {
+        assert_eq!(normalize_course("cmpsc130a").as_deref(), Some("CMPSC 130A"));
+        assert_eq!(normalize_course(" CMPSC 190dd ").as_deref(), Some("CMPSC 190DD"));
+        assert_eq!(normalize_course("pol s 7").as_deref(), Some("POLS 7"));
+        assert!(normalize_course("130A").is_none());
+        assert!(normalize_course("CMPSC").is_none());
+        assert!(same_course("CS-130A", "cs 130a"));
+        assert!(!same_course("CMPSC 130A", "CMPSC 130B"));
+    }
+
+    #[test]
+    fn catalog_links_restore_spaced_subject_codes() {
+        assert_eq!(catalog_url("cmpsc130a").unwrap(), "https://catalog.ucsb.edu/courses/CMPSC%20130A");
+        assert_eq!(catalog_url("POL S 7").unwrap(), "https://catalog.ucsb.edu/courses/POL%20S%207");
+        assert_eq!(catalog_url("che10").unwrap(), "https://catalog.ucsb.edu/courses/CH%20E%2010");
+        assert_eq!(catalog_url("xyz 1").unwrap(), "https://catalog.ucsb.edu/courses/XYZ%201");
+        assert!(catalog_url("not a course").is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Serialize, Deserialize};
+
+use crate::course::compact_course;
+use crate::name::fold;
+
+#[derive(Debug, Serialize)]
+pub struct Department {
+    pub rmp_name: &'static str,
+    pub ucsb_codes: &'static [&'static str],
+}
+
+pub const DEPARTMENTS: &[Department] = &[
+    Department { rmp_name: "Anthropology", ucsb_codes: &["ANTH"] },
+    Department { rmp_name: "Art", ucsb_codes: &["ART"] },
+    Department { rmp_name: "Art History", ucsb_codes: &["ARTHI"] },
+    Department { rmp_name: "Asian American Studies", ucsb_codes: &["AS AM"] },
+    Department { rmp_name: "Biology", ucsb_codes: &["MCDB", "EEMB"] },
+    Department { rmp_name: "Black Studies", ucsb_codes: &["BL ST"] },
+    Department { rmp_name: "Chemical Engineering", ucsb_codes: &["CH E"] },
+    Department { rmp_name: "Chemistry", ucsb_codes: &["CHEM"] },
+    Department { rmp_name: "Chicano Studies", ucsb_codes: &["CH ST"] },
+    Department { rmp_name: "Classics", ucsb_codes: &["CLASS"] },
+    Department { rmp_name: "Communication", ucsb_codes: &["COMM"] },
+    Department { rmp_name: "Comparative Literature", ucsb_codes: &["C LIT"] },
+    Department { rmp_name: "Computer Science", ucsb_codes: &["CMPSC"] },
+    Department { rmp_name: "Dance", ucsb_codes: &["DANCE"] },
+    Department { rmp_name: "Earth Science", ucsb_codes: &["EARTH"] },
+    Department { rmp_name: "East Asian Languages", ucsb_codes: &["EACS", "CHIN", "JAPAN", "KOR"] },
+    Department { rmp_name: "Economics", ucsb_codes: &["ECON"] },
+    Department { rmp_name: "Education", ucsb_codes: &["ED"] },
+    Department { rmp_name: "Electrical Engineering", ucsb_codes: &["ECE"] },
+    Department { rmp_name: "English", ucsb_codes: &["ENGL"] },
+    Department { rmp_name: "Environmental Studies", ucsb_codes: &["ENV S"] },
+    Department { rmp_name: "Exercise & Sport Studies", ucsb_codes: &["ES"] },
+    Department { rmp_name: "Feminist Studies", ucsb_codes: &["FEMST"] },
+    Department { rmp_name: "Film", ucsb_codes: &["FAMST"] },
+    Department { rmp_name: "French", ucsb_codes: &["FR"] },
+    Department { rmp_name: "Geography", ucsb_codes: &["GEOG"] },
+    Department { rmp_name: "German", ucsb_codes: &["GER"] },
+    Department { rmp_name: "Global Studies", ucsb_codes: &["GLOBL"] },
+    Department { rmp_name: "History", ucsb_codes: &["HIST"] },
+    Department { rmp_name: "Italian", ucsb_codes: &["ITAL"] },
+    Department { rmp_name: "Linguistics", ucsb_codes: &["LING"] },
+    Department { rmp_name: "Materials Science", ucsb_codes: &["MATRL"] },
+    Department { rmp_name: "Mathematics", ucsb_codes: &["MATH"] },
+    Department { rmp_name: "Mechanical Engineering", ucsb_codes: &["ME"] },
+    Department { rmp_name: "Music", ucsb_codes: &["MUS"] },
+    Department { rmp_name: "Philosophy", ucsb_codes: &["PHIL"] },
+    Department { rmp_name: "Physics", ucsb_codes: &["PHYS"] },
+    Department { rmp_name: "Political Science", ucsb_codes: &["POL S"] },
+    Department { rmp_name: "Psychology", ucsb_codes: &["PSY"] },
+    Department { rmp_name: "Religion", ucsb_codes: &["RG ST"] },
+    Department { rmp_name: "Sociology", ucsb_codes: &["SOC"] },
+    Department { rmp_name: "Spanish", ucsb_codes: &["SPAN", "PORT"] },
+    Department { rmp_name: "Statistics", ucsb_codes: &["PSTAT"] },
+    Department { rmp_name: "Technology Management", ucsb_codes: &["TMP"] },
+    Department { rmp_name: "Theater", ucsb_codes: &["THTR"] },
+    Department { rmp_name: "Writing", ucsb_codes: &["WRIT"] },
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DepartmentQuery {
+    pub department: Option<String>,
+}
+
+impl DepartmentQuery {
+    pub fn accepts(&self, department: &str) -> bool {
+        self.department.as_ref()
+            .map(|q| matches(department, q.as_str()))
+            .unwrap_or(true)
+    }
+}
+
+pub fn resolve(department: &str) -> Option<&'static Department> {
+    let folded = fold(department.trim());
+    let compact = compact_course(department);
+
+    DEPARTMENTS.iter().find(|d| {
+        fold(d.rmp_name) == folded || d.ucsb_codes.iter().any(|c| compact_course(c) == compact)
+    })
+}
+
+pub fn matches(rmp_department: &str, query: &str) -> bool {
+    if fold(rmp_department.trim()) == fold(query.trim()) {
+        return true;
+    }
+
+    match (resolve(rmp_department), resolve(query)) {
+        (Some(a), Some(b)) => std::ptr::eq(a, b),
+        _ => false,
+    }
+}
+
+pub fn canonical(department: &str) -> String {
+    match resolve(department) {
+        Some(d) => d.rmp_name.to_owned(),
+        None => fold(department.trim()),
+    }
+}
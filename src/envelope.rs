@@ -0,0 +1,259 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+
+use crate::error::Error;
+use crate::fields;
+use crate::rmp::{Fetched, NormalizedRating};
+use ucsb_courses::models::Meta;
+
+const DEFAULT_PER_PAGE: usize = 20;
+const MAX_PER_PAGE: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ListQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub cursor: Option<String>,
+    pub fields: Option<String>,
+}
+
+impl ListQuery {
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub date: i64,
+    pub id: u32,
+}
+
+impl Cursor {
+    pub fn rating(r: &NormalizedRating) -> Self {
+        Cursor {
+            date: r.date.timestamp(),
+            id: r.legacy_id.unwrap_or(0),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        base64::encode_config(format!("{}:{}", self.date, self.id).as_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let invalid = || Error::BadRequest("invalid cursor".to_owned());
+
+        let decoded = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+        let (date, id) = decoded.split_once(':').ok_or_else(invalid)?;
+
+        Ok(Cursor {
+            date: date.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct Envelope {
+    pub data: Value,
+    pub meta: Meta,
+}
+
+impl Envelope {
+    pub fn paginate<T: Serialize>(mut fetched: Fetched<Vec<T>>, query: &ListQuery, now: DateTime<Utc>) -> Self {
+        let page = query.page();
+        let per_page = query.per_page();
+
+        let total = fetched.value.len();
+        let items: Vec<T> = std::mem::take(&mut fetched.value).into_iter()
+            .skip((page - 1) * per_page)
+            .take(per_page)
+            .collect();
+
+        Envelope {
+            meta: Self::_meta(items.len(), page, total, None, &fetched, now),
+            data: fields::filter_fields(&items, query.fields.as_deref()),
+        }
+    }
+
+    pub fn paginate_cursor<R, T, K, M>(mut fetched: Fetched<Vec<R>>, query: &ListQuery, key: K, map: M, now: DateTime<Utc>) -> Result<Self, Error>
+        where T: Serialize, K: Fn(&R) -> Cursor, M: Fn(&R) -> T {
+        let page = query.page();
+        let per_page = query.per_page();
+
+        let mut sorted = std::mem::take(&mut fetched.value);
+        sorted.sort_by_key(|r| Reverse(key(r)));
+
+        let total = sorted.len();
+        let start = match &query.cursor {
+            Some(cursor) => {
+                let cursor = Cursor::decode(cursor.as_str())?;
+                sorted.iter().position(|r| key(r) < cursor).unwrap_or(total)
+            }
+            None => ((page - 1) * per_page).min(total),
+        };
+        let end = (start + per_page).min(total);
+
+        let window = &sorted[start..end];
+        let next_cursor = if end < total {
+            window.last().map(|r| key(r).encode())
+        } else {
+            None
+        };
+
+        let items: Vec<T> = window.iter().map(map).collect();
+
+        Ok(Envelope {
+            meta: Self::_meta(items.len(), page, total, next_cursor, &fetched, now),
+            data: fields::filter_fields(&items, query.fields.as_deref()),
+        })
+    }
+
+    pub fn from_rows<R, T, K, M>(mut fetched: Fetched<Vec<R>>, total: usize, query: &ListQuery, key: Option<K>, map: M, now: DateTime<Utc>) -> Self
+        where T: Serialize, K: Fn(&R) -> Cursor, M: Fn(&R) -> T {
+        let per_page = query.per_page();
+
+        let mut rows = std::mem::take(&mut fetched.value);
+        let more = rows.len() > per_page;
+        rows.truncate(per_page);
+
+        let next_cursor = match (more, key) {
+            (true, Some(key)) => rows.last().map(|r| key(r).encode()),
+            _ => None,
+        };
+
+        let items: Vec<T> = rows.iter().map(map).collect();
+
+        Envelope {
+            meta: Self::_meta(items.len(), query.page(), total, next_cursor, &fetched, now),
+            data: fields::filter_fields(&items, query.fields.as_deref()),
+        }
+    }
+
+    fn _meta<T>(count: usize, page: usize, total: usize, next_cursor: Option<String>, fetched: &Fetched<T>, now: DateTime<Utc>) -> Meta {
+        Meta {
+            count,
+            page,
+            total,
+            next_cursor,
+            cache_age: (now - fetched.fetched_at).num_seconds().max(0),
+            source: fetched.source.to_string(),
+            stale: fetched.stale,
+            fetched_at: fetched.fetched_at,
+            sources: fetched.sources.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rmp::Source;
+    use crate::sim;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.ymd(2024, 1, day).and_hms(12, 0, 0)
+    }
+
+    fn rating(day: u32, legacy_id: Option<u32>) -> NormalizedRating {
+        NormalizedRating { legacy_id, ..sim::rating(at(day), 4, 4, 0, 0) }
+    }
+
+    fn fetched<T>(value: T) -> Fetched<T> {
+        Fetched { value, source: Source::Store, fetched_at: at(20), stale: false, sources: Vec::new() }
+    }
+
+    fn page(cursor: Option<String>) -> ListQuery {
+        ListQuery { page: None, per_page: Some(2), cursor, fields: None }
+    }
+
+    // Newest first: (4, 5), (3, 7), (3, NULL -> 0), (2, 3), (1, 2).
+    fn originals() -> Vec<NormalizedRating> {
+        vec![rating(1, Some(2)), rating(3, None), rating(4, Some(5)), rating(2, Some(3)), rating(3, Some(7))]
+    }
+
+    // One rating newer than the first page and one sharing the cursor's date below its id.
+    fn inserted() -> Vec<NormalizedRating> {
+        vec![rating(5, Some(9)), rating(3, Some(6))]
+    }
+
+    fn in_memory(ratings: Vec<NormalizedRating>, cursor: Option<String>) -> Envelope {
+        Envelope::paginate_cursor(fetched(ratings), &page(cursor), Cursor::rating, |r| r.legacy_id, at(20)).unwrap()
+    }
+
+    #[test]
+    fn cursor_encoding_round_trips_and_rejects_garbage() {
+        let cursor = Cursor { date: 1_700_000_000, id: 42 };
+        assert_eq!(Cursor::decode(cursor.encode().as_str()).unwrap(), cursor);
+        assert_eq!(Cursor::rating(&rating(3, None)), Cursor { date: at(3).timestamp(), id: 0 });
+
+        for garbage in &["not base64!", "MTIz", "YTpi"] {
+            assert!(matches!(Cursor::decode(garbage), Err(Error::BadRequest(_))), "{}", garbage);
+        }
+    }
+
+    #[test]
+    fn in_memory_cursor_is_stable_when_ratings_are_inserted_between_pages() {
+        let first = in_memory(originals(), None);
+        assert_eq!(first.data, json!([5, 7]));
+        assert_eq!(first.meta.total, 5);
+
+        let mut ratings = originals();
+        ratings.extend(inserted());
+
+        let second = in_memory(ratings.clone(), first.meta.next_cursor.clone());
+        assert_eq!(second.data, json!([6, null]));
+
+        let third = in_memory(ratings, second.meta.next_cursor.clone());
+        assert_eq!(third.data, json!([3, 2]));
+        assert_eq!(third.meta.next_cursor, None);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[actix_rt::test]
+    async fn keyset_cursor_is_stable_when_ratings_are_inserted_between_pages() {
+        use crate::filter::CommentFilter;
+
+        let path = std::env::temp_dir().join(format!("ucsb-courses-keyset-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = crate::store::connect(format!("sqlite:{}", path.display()).as_str()).await.unwrap();
+
+        let sql = |cursor: Option<String>| {
+            let store = store.clone();
+            async move {
+                let query = page(cursor);
+                let ratings_query = CommentFilter::default().ratings_query(&query).unwrap().unwrap();
+                let rows = store.query_ratings(1, &ratings_query).await.unwrap();
+                Envelope::from_rows(fetched(rows.ratings), rows.total, &query, Some(Cursor::rating), |r| r.legacy_id, at(20))
+            }
+        };
+
+        store.save_ratings(1, &originals(), at(20)).await.unwrap();
+        let first = sql(None).await;
+        assert_eq!(first.data, json!([5, 7]));
+        assert_eq!(first.meta.total, 5);
+
+        let mut ratings = originals();
+        ratings.extend(inserted());
+        store.save_ratings(1, &ratings, at(20)).await.unwrap();
+
+        let second = sql(first.meta.next_cursor.clone()).await;
+        assert_eq!(second.data, json!([6, null]));
+
+        let third = sql(second.meta.next_cursor.clone()).await;
+        assert_eq!(third.data, json!([3, 2]));
+        assert_eq!(third.meta.next_cursor, None);
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
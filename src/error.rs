@@ -0,0 +1,110 @@
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::http::StatusCode;
+use serde_json::json;
+use thiserror::Error;
+use ucsb_courses::models::ProfessorSuggestion;
+
+use crate::validate::FieldError;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("no matching professor")]
+    NotFound,
+    #[error("no confident professor match")]
+    LowConfidence(Vec<ProfessorSuggestion>),
+    #[error("ratemyprofessor.com broken")]
+    Rmp,
+    #[error("ratemyprofessor.com schema changed: {0}")]
+    RmpSchema(String),
+    #[error("ratemyprofessor.com response too large")]
+    RmpTooLarge,
+    #[error("UCSB API broken")]
+    Ucsb,
+    #[error("missing or invalid token")]
+    Unauthorized,
+    #[error("token lacks the required scope")]
+    Forbidden,
+    #[error("API key quota exceeded")]
+    QuotaExceeded,
+    #[error("too many requests in flight")]
+    Overloaded(u64),
+    #[error("serving cached data only")]
+    Shedding(u64),
+    #[error("invalid request parameters")]
+    Validation(Vec<FieldError>),
+    #[error("bad request: {0}")]
+    BadRequest(String),
+    #[error("storage error: {0}")]
+    Store(String),
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    #[cfg(feature = "scheduler")]
+    #[error("mail delivery failed: {0}")]
+    Mail(String),
+}
+
+impl Error {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound => "NOT_FOUND",
+            Error::LowConfidence(_) => "LOW_CONFIDENCE",
+            Error::Rmp => "RMP",
+            Error::RmpSchema(_) => "RMP_SCHEMA",
+            Error::RmpTooLarge => "RMP_TOO_LARGE",
+            Error::Ucsb => "UCSB",
+            Error::Unauthorized => "UNAUTHORIZED",
+            Error::Forbidden => "FORBIDDEN",
+            Error::QuotaExceeded => "QUOTA_EXCEEDED",
+            Error::Overloaded(_) => "OVERLOADED",
+            Error::Shedding(_) => "SHEDDING",
+            Error::Validation(_) => "VALIDATION",
+            Error::BadRequest(_) => "BAD_REQUEST",
+            Error::Store(_) => "STORE",
+            Error::Config(_) => "CONFIG",
+            #[cfg(feature = "scheduler")]
+            Error::Mail(_) => "MAIL",
+        }
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Error::NotFound | Error::LowConfidence(_) => StatusCode::NOT_FOUND,
+            Error::Rmp | Error::RmpSchema(_) | Error::RmpTooLarge | Error::Ucsb => StatusCode::BAD_GATEWAY,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Forbidden => StatusCode::FORBIDDEN,
+            Error::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+            Error::Overloaded(_) | Error::Shedding(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Validation(_) | Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::Store(_) | Error::Config(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            #[cfg(feature = "scheduler")]
+            Error::Mail(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            Error::BadRequest(message) => HttpResponse::build(self.status_code())
+                .json(json!({"error": self.code(), "message": message})),
+            Error::Overloaded(retry_after) => HttpResponse::build(self.status_code())
+                .header("Retry-After", retry_after.to_string())
+                .json(json!({"error": self.code()})),
+            Error::Shedding(retry_after) => HttpResponse::build(self.status_code())
+                .header("Retry-After", retry_after.to_string())
+                .json(json!({
+                    "error": self.code(),
+                    "hint": "this professor is not cached and upstream lookups are paused during peak traffic; retry later",
+                })),
+            Error::QuotaExceeded => HttpResponse::build(self.status_code())
+                .header("X-RateLimit-Remaining", "0")
+                .json(json!({"error": self.code()})),
+            Error::Validation(fields) => HttpResponse::build(self.status_code())
+                .json(json!({"error": self.code(), "fields": fields})),
+            Error::LowConfidence(candidates) => HttpResponse::build(self.status_code())
+                .json(json!({"error": self.code(), "candidates": candidates})),
+            _ => HttpResponse::build(self.status_code())
+                .json(json!({"error": self.code()})),
+        }
+    }
+}
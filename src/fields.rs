@@ -0,0 +1,97 @@
+use serde::Serialize;
+use serde_json::Value;
+
+fn retain_fields(value: Value, fields: &[&str]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter(|(k, _)| fields.contains(&k.as_str()))
+                .collect()
+        ),
+        Value::Array(items) => Value::Array(
+            items.into_iter()
+                .map(|v| retain_fields(v, fields))
+                .collect()
+        ),
+        other => other,
+    }
+}
+
+pub fn filter_fields<T: Serialize>(value: &T, fields: Option<&str>) -> Value {
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+
+    match fields {
+        Some(fields) => {
+            let fields: Vec<&str> = fields.split(',')
+                .map(|f| f.trim())
+                .filter(|f| !f.is_empty())
+                .collect();
+
+            if fields.is_empty() {
+                return value;
+            }
+
+            retain_fields(value, fields.as_slice())
+        }
+        None => value,
+    }
+}
+
+pub fn round_numbers(value: Value, precision: u32) -> Value {
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let scale = 10f64.powi(precision as i32);
+            n.as_f64()
+                .map(|f| match (f * scale).round() / scale {
+                    rounded if rounded.is_finite() => rounded,
+                    _ => f,
+                })
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .unwrap_or(Value::Null)
+        }
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, round_numbers(v, precision)))
+                .collect()
+        ),
+        Value::Array(items) => Value::Array(
+            items.into_iter()
+                .map(|v| round_numbers(v, precision))
+                .collect()
+        ),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn numbers_round_to_precision() {
+        let value = json!({"quality": 4.199999809, "num_ratings": 20, "points": [{"gpa": 3.456}]});
+        assert_eq!(round_numbers(value, 2), json!({"quality": 4.2, "num_ratings": 20, "points": [{"gpa": 3.46}]}));
+
+        assert_eq!(round_numbers(json!([-2.345, 2.5, 7]), 0), json!([-2.0, 3.0, 7]));
+        assert_eq!(round_numbers(json!({"name": "4.5", "ok": true, "none": null}), 0), json!({"name": "4.5", "ok": true, "none": null}));
+    }
+
+    #[test]
+    fn extreme_precision_keeps_the_original_value() {
+        assert_eq!(round_numbers(json!(1e300), 20), json!(1e300));
+        assert_eq!(round_numbers(json!(0.1), 400), json!(0.1));
+        assert_eq!(round_numbers(json!(u64::MAX), 2), json!(u64::MAX));
+    }
+
+    #[test]
+    fn fields_keep_only_the_named_keys() {
+        let value = json!([{"a": 1, "b": 2, "c": 3}, {"a": 4}]);
+
+        assert_eq!(filter_fields(&value, Some(" a , c ")), json!([{"a": 1, "c": 3}, {"a": 4}]));
+        assert_eq!(filter_fields(&value, Some(",,")), value);
+        assert_eq!(filter_fields(&value, None), value);
+        assert_eq!(filter_fields(&json!({"a": {"b": 1}}), Some("b")), json!({}));
+    }
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use serde::Deserialize;
+use std::cmp::Reverse;
+
+use crate::envelope::{Cursor, ListQuery};
+use crate::error::Error;
+use crate::quarter::Quarter;
+use crate::rmp::NormalizedRating;
+use crate::store::{RatingsQuery, RatingsSort};
+use crate::text::{self, TextProfile};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CommentFilter {
+    pub quarter: Option<String>,
+    pub min_thumb_score: Option<i64>,
+    pub min_words: Option<u32>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub within: Option<String>,
+    pub grade: Option<String>,
+    pub online: Option<bool>,
+    pub sort: Option<RatingsSort>,
+    pub include_hidden: Option<bool>,
+    pub text: Option<TextProfile>,
+}
+
+type DateRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+impl CommentFilter {
+    pub fn apply(&self, ratings: Vec<NormalizedRating>, now: DateTime<Utc>) -> Result<Vec<NormalizedRating>, Error> {
+        let quarter = match &self.quarter {
+            Some(q) => Some(q.parse::<Quarter>()
+                .map_err(|_| Error::BadRequest(format!("invalid quarter {}", q)))?),
+            None => None,
+        };
+
+        let (since, until) = self._range()?;
+
+        let within = match &self.within {
+            Some(w) => Some(parse_within(w.as_str(), Quarter::from_date(now))
+                .ok_or_else(|| Error::BadRequest(format!("invalid within {}", w)))?),
+            None => None,
+        };
+
+        if since.is_some() && within.is_some() {
+            return Err(Error::BadRequest("since and within are mutually exclusive".to_owned()));
+        }
+
+        let mut ratings: Vec<NormalizedRating> = ratings.into_iter()
+            .filter(|r| quarter.map(|q| r.quarter == q).unwrap_or(true))
+            .filter(|r| since.map(|s| r.date >= s).unwrap_or(true))
+            .filter(|r| until.map(|u| r.date <= u).unwrap_or(true))
+            .filter(|r| within.map(|q| r.quarter >= q).unwrap_or(true))
+            .filter(|r| self.grade.as_ref().map(|g| r.grade.eq_ignore_ascii_case(g)).unwrap_or(true))
+            .filter(|r| self.online.map(|online| r.online().unwrap_or(false) == online).unwrap_or(true))
+            .filter(|r| self.min_thumb_score.map(|min| r.net_thumbs() >= min).unwrap_or(true))
+            .filter(|r| self.min_words.map(|min| r.word_count >= min).unwrap_or(true))
+            .map(|r| self.present(r))
+            .collect();
+
+        match self.sort {
+            Some(RatingsSort::Newest) => ratings.sort_by_key(|r| Reverse((r.date, r.legacy_id))),
+            Some(RatingsSort::Oldest) => ratings.sort_by_key(|r| (r.date, r.legacy_id)),
+            Some(RatingsSort::Helpful) => ratings.sort_by_key(|r| Reverse((r.net_thumbs(), r.date))),
+            None => {}
+        }
+
+        Ok(ratings)
+    }
+
+    pub fn present(&self, r: NormalizedRating) -> NormalizedRating {
+        match self.text {
+            Some(profile) if profile != TextProfile::Raw => NormalizedRating {
+                comment: text::normalize(r.comment.as_str(), profile),
+                ..r
+            },
+            _ => r,
+        }
+    }
+
+    pub fn sort(&self, query: &ListQuery) -> Result<RatingsSort, Error> {
+        let sort = self.sort.unwrap_or_default();
+
+        if query.cursor.is_some() && sort != RatingsSort::Newest {
+            return Err(Error::BadRequest("cursor pagination requires sort=newest".to_owned()));
+        }
+
+        Ok(sort)
+    }
+
+    pub fn ratings_query(&self, query: &ListQuery) -> Result<Option<RatingsQuery>, Error> {
+        let sort = self.sort(query)?;
+
+        if self.quarter.is_some() || self.within.is_some() || self.min_words.is_some() || self.online.is_some() {
+            return Ok(None);
+        }
+
+        let (since, until) = self._range()?;
+        let after = query.cursor.as_deref().map(Cursor::decode).transpose()?;
+
+        Ok(Some(RatingsQuery {
+            courses: Vec::new(),
+            since,
+            until,
+            grade: self.grade.clone(),
+            min_thumb_score: self.min_thumb_score,
+            exclude: Vec::new(),
+            sort,
+            offset: if after.is_some() { 0 } else { (query.page() - 1) * query.per_page() },
+            after,
+            limit: query.per_page() + 1,
+        }))
+    }
+
+    fn _range(&self) -> Result<DateRange, Error> {
+        let since = match &self.since {
+            Some(s) => Some(parse_since(s.as_str())
+                .ok_or_else(|| Error::BadRequest(format!("invalid since {}", s)))?),
+            None => None,
+        };
+
+        let until = match &self.until {
+            Some(u) => Some(parse_until(u.as_str())
+                .ok_or_else(|| Error::BadRequest(format!("invalid until {}", u)))?),
+            None => None,
+        };
+
+        Ok((since, until))
+    }
+}
+
+fn parse_since(since: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(since) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(since, "%Y-%m-%d").ok()
+        .map(|d| Utc.from_utc_datetime(&d.and_hms(0, 0, 0)))
+}
+
+fn parse_until(until: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(until) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(until, "%Y-%m-%d").ok()
+        .map(|d| Utc.from_utc_datetime(&d.and_hms(23, 59, 59)))
+}
+
+fn parse_within(within: &str, current: Quarter) -> Option<Quarter> {
+    let within = within.trim().to_lowercase();
+    let (count, unit) = within.split_at(within.char_indices().last()?.0);
+    let count = count.parse::<u32>().ok().filter(|c| *c > 0 && *c <= 100)?;
+
+    let quarters = match unit {
+        "q" => count,
+        "y" => count * 4,
+        _ => return None,
+    };
+
+    Some(current.back(quarters - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim;
+
+    fn page(page: Option<usize>, per_page: Option<usize>) -> ListQuery {
+        ListQuery { page, per_page, cursor: None, fields: None }
+    }
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.ymd(year, month, day).and_hms(12, 0, 0)
+    }
+
+    #[test]
+    fn comment_filters_push_down_when_sql_can_express_them() {
+        let page = page(Some(3), Some(10));
+        let filter = CommentFilter { grade: Some("A".to_owned()), until: Some("2023-12-31".to_owned()), sort: Some(RatingsSort::Helpful), ..CommentFilter::default() };
+
+        let query = filter.ratings_query(&page).unwrap().unwrap();
+        assert_eq!((query.offset, query.limit, query.sort), (20, 11, RatingsSort::Helpful));
+        assert_eq!(query.until.unwrap().to_rfc3339(), "2023-12-31T23:59:59+00:00");
+
+        let words = CommentFilter { min_words: Some(20), ..CommentFilter::default() };
+        assert!(words.ratings_query(&page).unwrap().is_none());
+        let online = CommentFilter { online: Some(true), ..CommentFilter::default() };
+        assert!(online.ratings_query(&page).unwrap().is_none());
+        let within = CommentFilter { within: Some("2q".to_owned()), ..CommentFilter::default() };
+        assert!(within.ratings_query(&page).unwrap().is_none());
+
+        let cursor = ListQuery { cursor: Some(Cursor { date: 1, id: 2 }.encode()), ..page };
+        assert!(filter.ratings_query(&cursor).is_err());
+        let keyset = CommentFilter::default().ratings_query(&cursor).unwrap().unwrap();
+        assert_eq!((keyset.offset, keyset.after), (0, Some(Cursor { date: 1, id: 2 })));
+    }
+
+    #[test]
+    fn paging_is_clamped_before_it_reaches_sql() {
+        let window = |p, pp| {
+            let query = CommentFilter::default().ratings_query(&page(p, pp)).unwrap().unwrap();
+            (query.offset, query.limit)
+        };
+
+        assert_eq!(window(Some(0), Some(0)), (0, 2));
+        assert_eq!(window(Some(2), Some(500)), (100, 101));
+        assert_eq!(window(None, None), (0, 21));
+
+        let garbage = ListQuery { cursor: Some("not-a-cursor".to_owned()), ..page(None, None) };
+        assert!(CommentFilter::default().ratings_query(&garbage).is_err());
+    }
+
+    #[test]
+    fn date_bounds_are_inclusive_and_normalized_to_utc() {
+        assert_eq!(parse_since("2023-01-01").unwrap().to_rfc3339(), "2023-01-01T00:00:00+00:00");
+        assert_eq!(parse_until("2023-01-01").unwrap().to_rfc3339(), "2023-01-01T23:59:59+00:00");
+        assert_eq!(parse_since("2023-01-01T00:30:00-08:00").unwrap().to_rfc3339(), "2023-01-01T08:30:00+00:00");
+        assert!(parse_since("2023-02-30").is_none());
+        assert!(parse_until("yesterday").is_none());
+
+        let ratings = vec![sim::rating(Utc.ymd(2023, 1, 1).and_hms(23, 59, 59), 4, 4, 0, 0), sim::rating(Utc.ymd(2023, 1, 2).and_hms(0, 0, 0), 4, 4, 0, 0)];
+        let day = CommentFilter { since: Some("2023-01-01".to_owned()), until: Some("2023-01-01".to_owned()), ..CommentFilter::default() };
+        assert_eq!(day.apply(ratings, at(2024, 1, 1)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn within_counts_back_whole_quarters_from_now() {
+        let fall = Quarter::from_date(at(2024, 10, 1));
+
+        assert_eq!(parse_within("1q", fall), Some(fall));
+        assert_eq!(parse_within(" 2Q ", fall), Some(fall.back(1)));
+        assert_eq!(parse_within("1y", fall), Some(fall.back(3)));
+        assert_eq!(parse_within("0q", fall), None);
+        assert_eq!(parse_within("101q", fall), None);
+        assert_eq!(parse_within("q", fall), None);
+        assert_eq!(parse_within("", fall), None);
+        assert_eq!(parse_within("3w", fall), None);
+
+        let ratings = vec![sim::rating(at(2024, 11, 1), 4, 4, 0, 0), sim::rating(at(2024, 5, 1), 4, 4, 0, 0), sim::rating(at(2024, 1, 15), 4, 4, 0, 0)];
+        let recent = CommentFilter { within: Some("3q".to_owned()), ..CommentFilter::default() };
+        assert_eq!(recent.apply(ratings.clone(), at(2024, 12, 1)).unwrap().len(), 2);
+        assert_eq!(recent.apply(ratings.clone(), at(2025, 2, 1)).unwrap().len(), 1);
+
+        let both = CommentFilter { since: Some("2024-01-01".to_owned()), ..recent };
+        assert!(both.apply(ratings, at(2024, 12, 1)).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::course::normalize_course;
+use crate::ucsb::ClassResponse;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeCourse {
+    pub course: String,
+    pub title: String,
+    pub instructors: BTreeSet<String>,
+}
+
+pub struct GeIndex {
+    areas: BTreeMap<String, BTreeMap<String, GeCourse>>,
+}
+
+fn area_key(area: &str) -> String {
+    area.trim().to_uppercase()
+}
+
+impl GeIndex {
+    pub fn from_classes(classes: &[ClassResponse]) -> Self {
+        let mut areas: BTreeMap<String, BTreeMap<String, GeCourse>> = BTreeMap::new();
+
+        for class in classes {
+            let course = match normalize_course(class.course_id.as_str()) {
+                Some(course) => course,
+                None => continue,
+            };
+
+            let instructors: BTreeSet<String> = class.class_sections.iter()
+                .flat_map(|s| s.instructors.iter().map(|i| i.instructor.clone()))
+                .collect();
+
+            for ge in class.general_education.iter() {
+                let entry = areas.entry(area_key(ge.ge_code.as_str())).or_default()
+                    .entry(course.clone())
+                    .or_insert_with(|| GeCourse {
+                        course: course.clone(),
+                        title: class.title.clone(),
+                        instructors: BTreeSet::new(),
+                    });
+
+                entry.instructors.extend(instructors.iter().cloned());
+            }
+        }
+
+        GeIndex { areas }
+    }
+
+    pub fn courses(&self, area: &str) -> Option<Vec<GeCourse>> {
+        self.areas.get(area_key(area).as_str())
+            .map(|courses| courses.values().cloned().collect())
+    }
+}
@@ -0,0 +1,17 @@
+pub fn gpa(grade: &str) -> Option<f32> {
+    match grade.trim().to_uppercase().as_str() {
+        "A+" | "A" => Some(4.0),
+        "A-" => Some(3.7),
+        "B+" => Some(3.3),
+        "B" => Some(3.0),
+        "B-" => Some(2.7),
+        "C+" => Some(2.3),
+        "C" => Some(2.0),
+        "C-" => Some(1.7),
+        "D+" => Some(1.3),
+        "D" => Some(1.0),
+        "D-" => Some(0.7),
+        "F" => Some(0.0),
+        _ => None,
+    }
+}
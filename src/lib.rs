@@ -0,0 +1,4 @@
+pub mod models;
+
+#[cfg(feature = "client")]
+pub mod client;
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::LimitsConfig;
+use crate::error::Error;
+
+pub struct ConcurrencyLimits {
+    config: LimitsConfig,
+    in_flight: Mutex<HashMap<&'static str, usize>>,
+}
+
+pub struct Permit<'a> {
+    limits: &'a ConcurrencyLimits,
+    route: &'static str,
+}
+
+impl ConcurrencyLimits {
+    pub fn new(config: LimitsConfig) -> Self {
+        ConcurrencyLimits {
+            config,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn acquire(&self, route: &'static str) -> Result<Permit<'_>, Error> {
+        let limit = self.config.limit(route);
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let count = in_flight.entry(route).or_insert(0);
+
+        if limit > 0 && *count >= limit {
+            println!("acquire: {} over limit of {}", route, limit);
+            return Err(Error::Overloaded(self.config.retry_after_secs));
+        }
+
+        *count += 1;
+
+        Ok(Permit {
+            limits: self,
+            route,
+        })
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if let Some(count) = self.limits.in_flight.lock().unwrap().get_mut(self.route) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Security {
+    None,
+    StartTls,
+    Tls,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub host: Option<String>,
+    pub port: u16,
+    pub security: Security,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub subject: String,
+    pub body: String,
+    pub rate_limit_secs: i64,
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        MailConfig {
+            host: None,
+            port: 587,
+            security: Security::StartTls,
+            username: None,
+            password: None,
+            from: "ucsb-courses@localhost".to_owned(),
+            subject: "Seats open in {enroll_code} ({quarter})".to_owned(),
+            body: "Section {enroll_code} for quarter {quarter} has {enrolled}/{capacity} seats filled.\n\nWatch #{watch_id}".to_owned(),
+            rate_limit_secs: 900,
+        }
+    }
+}
+
+pub fn render(template: &str, values: &[(&str, String)]) -> String {
+    values.iter().fold(template.to_owned(), |acc, (key, value)| {
+        acc.replace(format!("{{{}}}", key).as_str(), value.as_str())
+    })
+}
+
+pub fn valid_address(address: &str) -> bool {
+    match address.split_once('@') {
+        Some((local, domain)) => !local.is_empty()
+            && domain.contains('.')
+            && !address.chars().any(|c| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | ',')),
+        None => false,
+    }
+}
+
+pub struct Mailer {
+    config: MailConfig,
+    host: String,
+    sent: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl Mailer {
+    pub fn new(config: &MailConfig) -> Option<Self> {
+        Some(Mailer {
+            host: config.host.clone()?,
+            config: config.clone(),
+            sent: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub async fn notify(&self, to: &str, values: &[(&str, String)]) -> Result<bool, Error> {
+        {
+            let mut sent = self.sent.lock().await;
+            let now = Utc::now();
+
+            if let Some(last) = sent.get(to) {
+                if (now - *last).num_seconds() < self.config.rate_limit_secs {
+                    println!("notify: rate limited {}", to);
+                    return Ok(false);
+                }
+            }
+
+            sent.insert(to.to_owned(), now);
+        }
+
+        let subject = render(self.config.subject.as_str(), values);
+        let body = render(self.config.body.as_str(), values);
+
+        self.send(to, subject.as_str(), body.as_str()).await?;
+
+        Ok(true)
+    }
+
+    async fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), Error> {
+        let stream = TcpStream::connect((self.host.as_str(), self.config.port)).await
+            .map_err(|e| Error::Mail(format!("smtp connect: {}", e)))?;
+
+        match self.config.security {
+            Security::None => self._session(stream, to, subject, body).await,
+            Security::Tls => {
+                let stream = self._tls(stream).await?;
+                self._session(stream, to, subject, body).await
+            }
+            Security::StartTls => {
+                let mut stream = stream;
+                expect_reply(&mut stream, 220).await?;
+                command(&mut stream, format!("EHLO {}", self._domain()).as_str(), 250).await?;
+                command(&mut stream, "STARTTLS", 220).await?;
+
+                let mut stream = self._tls(stream).await?;
+                command(&mut stream, format!("EHLO {}", self._domain()).as_str(), 250).await?;
+                self._transaction(&mut stream, to, subject, body).await
+            }
+        }
+    }
+
+    async fn _tls(&self, stream: TcpStream) -> Result<async_native_tls::TlsStream<TcpStream>, Error> {
+        async_native_tls::connect(self.host.as_str(), stream).await
+            .map_err(|e| Error::Mail(format!("smtp tls: {}", e)))
+    }
+
+    async fn _session<S: AsyncRead + AsyncWrite + Unpin>(&self, mut stream: S, to: &str, subject: &str, body: &str) -> Result<(), Error> {
+        expect_reply(&mut stream, 220).await?;
+        command(&mut stream, format!("EHLO {}", self._domain()).as_str(), 250).await?;
+
+        self._transaction(&mut stream, to, subject, body).await
+    }
+
+    async fn _transaction<S: AsyncRead + AsyncWrite + Unpin>(&self, stream: &mut S, to: &str, subject: &str, body: &str) -> Result<(), Error> {
+        if let (Some(username), Some(password)) = (&self.config.username, &self.config.password) {
+            let credentials = base64::encode(format!("\0{}\0{}", username, password).as_bytes());
+            command(stream, format!("AUTH PLAIN {}", credentials).as_str(), 235).await?;
+        }
+
+        command(stream, format!("MAIL FROM:<{}>", self.config.from).as_str(), 250).await?;
+        command(stream, format!("RCPT TO:<{}>", to).as_str(), 250).await?;
+        command(stream, "DATA", 354).await?;
+
+        let mut message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+            self.config.from, to, subject.replace(['\r', '\n'], " "), Utc::now().to_rfc2822(),
+        );
+        for line in body.lines() {
+            if line.starts_with('.') {
+                message.push('.');
+            }
+            message.push_str(line);
+            message.push_str("\r\n");
+        }
+
+        command(stream, format!("{}.", message).as_str(), 250).await?;
+        command(stream, "QUIT", 221).await?;
+
+        Ok(())
+    }
+
+    fn _domain(&self) -> &str {
+        self.config.from.split_once('@').map(|(_, d)| d).unwrap_or("localhost")
+    }
+}
+
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, line: &str, code: u16) -> Result<(), Error> {
+    stream.write_all(format!("{}\r\n", line).as_bytes()).await
+        .map_err(|e| Error::Mail(format!("smtp write: {}", e)))?;
+
+    expect_reply(stream, code).await
+}
+
+async fn expect_reply<S: AsyncRead + Unpin>(stream: &mut S, code: u16) -> Result<(), Error> {
+    loop {
+        let line = read_line(stream).await?;
+
+        let status = line.get(..3).and_then(|s| s.parse::<u16>().ok())
+            .ok_or_else(|| Error::Mail(format!("smtp reply: {}", line)))?;
+        if status != code {
+            return Err(Error::Mail(format!("smtp reply: {}", line)));
+        }
+
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+async fn read_line<S: AsyncRead + Unpin>(stream: &mut S) -> Result<String, Error> {
+    let mut line = Vec::new();
+    let mut byte = [0u8; 1];
+
+    loop {
+        let n = stream.read(&mut byte).await
+            .map_err(|e| Error::Mail(format!("smtp read: {}", e)))?;
+        if n == 0 || byte[0] == b'\n' {
+            break;
+        }
+        line.push(byte[0]);
+    }
+
+    Ok(String::from_utf8_lossy(line.as_slice()).trim_end().to_owned())
+}
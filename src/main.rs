@@ -1,133 +1,1609 @@
-use actix_web::{web, App, HttpRequest, HttpServer, Responder};
+use actix_web::{middleware, web, App, HttpRequest, HttpServer, Responder};
+use actix_web::dev::{Body, ResponseBody, ResponseHead, Service};
+use actix_web::http::header::{HeaderName, HeaderValue};
 
 use serde::{Serialize, Deserialize};
-use tokio::sync::Mutex;
 use serde_json::json;
-use std::time::{SystemTime, UNIX_EPOCH};
+use futures::StreamExt;
 use std::collections::HashMap;
-use std::sync::Arc;
+
+use validate::Validate;
+use ucsb_courses::models::{Comment, CourseCount, DepartmentGroup, NameMatch, ProfessorCard, ProfessorMatch, ProfessorResponse, ProfessorSuggestion, ShieldsBadge};
 
 mod rmp;
+mod error;
+mod autocomplete;
+mod course;
+mod ucsb;
+mod fields;
+mod envelope;
+mod name;
+mod quarter;
+mod filter;
+mod config;
+mod department;
+mod stats;
+mod text;
+mod grade;
+mod store;
+mod limit;
+mod validate;
+mod prereq;
+mod schedule;
+mod ge;
+mod account;
+mod auth;
+mod quota;
+mod audit;
+mod moderation;
+mod startup;
+mod supervisor;
+mod alert;
+mod selftest;
+mod version;
+mod proxy;
+mod svg;
+mod clock;
+mod analysis;
+mod budget;
+mod outbound;
+mod cache;
+#[cfg(test)]
+mod sim;
+#[cfg(test)]
+mod mock;
+#[cfg(feature = "scheduler")]
+mod watch;
+#[cfg(feature = "scheduler")]
+mod mail;
+#[cfg(feature = "scheduler")]
+mod scheduler;
 
 struct AppState {
-    rmp_controller: rmp::Controller,
+    schools: HashMap<String, rmp::Controller>,
+    default_school: String,
+    stale_fallback: config::StaleFallbackConfig,
+    mode: config::Mode,
+    shedding: config::SheddingConfig,
+    versions: version::VersionsConfig,
+    proxy: proxy::ProxyConfig,
+    auth: auth::Verifier,
+    limits: limit::ConcurrencyLimits,
+    prereqs: tokio::sync::RwLock<prereq::PrereqGraph>,
+    sections: tokio::sync::RwLock<schedule::SectionIndex>,
+    catalog: Option<ucsb::Catalog>,
+    quarter: String,
+    ge: tokio::sync::RwLock<HashMap<String, ge::GeIndex>>,
+    accounts: account::Accounts,
+    quotas: quota::Quotas,
+    audit: audit::AuditLog,
+    hidden: std::sync::Arc<moderation::HiddenRatings>,
+    budget: std::sync::Arc<budget::UpstreamBudget>,
+    clock: std::sync::Arc<dyn clock::Clock>,
+    readiness: std::sync::Arc<startup::Readiness>,
+    supervisor: std::sync::Arc<supervisor::Supervisor>,
+    #[cfg(feature = "scheduler")]
+    watchlist: std::sync::Arc<watch::Watchlist>,
+    #[cfg(feature = "scheduler")]
+    scheduler: scheduler::Scheduler,
+}
+
+impl AppState {
+    fn controller(&self, school: Option<&str>) -> Result<&rmp::Controller, error::Error> {
+        self.schools.get(school.unwrap_or(self.default_school.as_str()))
+            .ok_or(error::Error::NotFound)
+    }
+
+    fn freshness(&self, route: &str, freshness: rmp::Freshness) -> rmp::Freshness {
+        rmp::Freshness {
+            refresh: freshness.refresh && self.mode == config::Mode::Full,
+            cache_only: self.shedding.enabled,
+            ..self.stale_fallback.freshness(route, freshness)
+        }
+    }
+
+    fn account(&self, req: &HttpRequest) -> Result<String, error::Error> {
+        let header = req.headers().get("Authorization").and_then(|h| h.to_str().ok());
+
+        if let Ok(account_id) = self.accounts.verify(header) {
+            return Ok(account_id);
+        }
+
+        let claims = self.auth.verify(header)?;
+        match claims.sub {
+            Some(sub) if claims.has_scope(auth::Scope::Read) => Ok(format!("jwt:{}", sub)),
+            _ => Err(error::Error::Forbidden),
+        }
+    }
+
+    fn actor(&self, req: &HttpRequest) -> String {
+        let header = req.headers().get("Authorization").and_then(|h| h.to_str().ok());
+
+        self.auth.verify(header).ok()
+            .and_then(|c| c.sub)
+            .or_else(|| self.proxy.client_ip(req.peer_addr().map(|a| a.ip()), req.headers()).map(|ip| format!("ip:{}", ip)))
+            .unwrap_or_else(|| "unknown".to_owned())
+    }
+
+    fn include_hidden(&self, req: &HttpRequest, filter: &filter::CommentFilter) -> Result<bool, error::Error> {
+        if filter.include_hidden != Some(true) {
+            return Ok(false);
+        }
+
+        let header = req.headers().get("Authorization").and_then(|h| h.to_str().ok());
+        if self.auth.verify(header)?.has_scope(auth::Scope::Admin) {
+            Ok(true)
+        } else {
+            Err(error::Error::Forbidden)
+        }
+    }
+
+    fn controllers(&self) -> Vec<&rmp::Controller> {
+        self.schools.values().collect()
+    }
+}
+
+impl From<rmp::Professor> for ProfessorResponse {
+    fn from(p: rmp::Professor) -> Self {
+        ProfessorResponse {
+            ids: rmp::rmp_ids(p.rmp_id),
+            quality: p.score.as_ref().and_then(|e| e.quality),
+            quality_yr: p.score.as_ref().and_then(|e| e.quality_yr),
+            num_ratings: p.score.as_ref().map(|e| e.num_ratings).or(p.num_ratings),
+            insufficient_data: p.score.as_ref().map(|e| e.insufficient_data).unwrap_or(false),
+            stale: p.score.as_ref().map(|e| e.stale).unwrap_or(false),
+            fetched_at: p.score.as_ref().map(|e| e.fetched_at),
+            sources: p.score.as_ref().map(|e| rmp::data_source(rmp::RMP_SOURCE, e.fetched_at)).into_iter().collect(),
+            first_name: p.first_name,
+            last_name: p.last_name,
+            full_name: p.full_name,
+            department: p.department,
+            rmp_department: p.rmp_department,
+            department_percentile: None,
+            school_percentile: None,
+            metadata: None,
+            name_match: None,
+        }
+    }
+}
+
+impl From<rmp::Professor> for ProfessorMatch {
+    fn from(p: rmp::Professor) -> Self {
+        ProfessorMatch {
+            rmp_quality: p.rmp_quality,
+            top_courses: Vec::new(),
+            professor: ProfessorResponse::from(p),
+        }
+    }
+}
+
+fn to_comment(r: &rmp::NormalizedRating, now: chrono::DateTime<chrono::Utc>) -> Comment {
+    Comment {
+        class: r.class.clone(),
+        comment: r.comment.clone(),
+        word_count: r.word_count,
+        reading_seconds: r.reading_seconds,
+        grade: r.grade.clone(),
+        attendance_mandatory: r.attendance_mandatory,
+        course_type: r.course_type(),
+        online: r.online(),
+        quality: r.quality(),
+        difficulty: r.difficulty as f32,
+        date: r.date,
+        quarter: r.quarter.to_string(),
+        quarter_code: r.quarter.code(),
+        terms_ago: quarter::Quarter::from_date(now).terms_since(&r.quarter),
+        session: quarter::Session::from_date(r.date).map(|s| s.name().to_owned()),
+    }
+}
+
+#[derive(Deserialize)]
+struct SchoolPath {
+    pub school: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProfessorPath {
+    pub school: Option<String>,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct CoursePath {
+    pub school: Option<String>,
+    pub name: String,
+    pub course: String,
+}
+
+#[derive(Deserialize)]
+struct LastNamePath {
+    pub school: Option<String>,
+    pub last: String,
+}
+
+#[derive(Deserialize)]
+struct MappingPath {
+    pub school: Option<String>,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct MergePath {
+    pub school: Option<String>,
+    pub rmp_id: u32,
+}
+
+#[derive(Deserialize)]
+struct CourseIdPath {
+    pub course: String,
+}
+
+#[derive(Deserialize)]
+struct TagPath {
+    pub school: Option<String>,
+    pub tag: String,
+}
+
+#[derive(Deserialize)]
+struct LimitQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct GePath {
+    pub area: String,
+}
+
+#[derive(Deserialize)]
+struct GeQuery {
+    pub quarter: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct ProfessorResponse {
+#[derive(Serialize)]
+struct GeInstructor {
+    pub name: String,
+    pub professor: Option<ProfessorResponse>,
+}
+
+#[derive(Serialize)]
+struct CourseLinks {
+    pub rmp_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub catalog_url: Option<String>,
+}
+
+impl CourseLinks {
+    fn new(rmp_id: u32, course: &str) -> Self {
+        CourseLinks {
+            rmp_url: rmp::rmp_course_url(rmp_id, course),
+            catalog_url: course::catalog_url(course),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct GeCourseResponse {
+    pub course: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub catalog_url: Option<String>,
+    pub instructors: Vec<GeInstructor>,
+}
+
+impl GeCourseResponse {
+    fn best_quality(&self) -> f32 {
+        self.instructors.iter()
+            .filter_map(|i| i.professor.as_ref().and_then(|p| p.quality))
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(feature = "scheduler")]
+impl Validate for watch::WatchRequest {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .check("enroll_code", self.enroll_code.len() == 5 && self.enroll_code.chars().all(|c| c.is_ascii_digit()), "must be a 5-digit enroll code")
+            .check("quarter", self.quarter.as_ref().map(|q| quarter::valid_code(q)).unwrap_or(true), "must be a quarter code such as 20241 or 20243A")
+            .check("webhook", self.webhook.as_ref().map(|w| w.starts_with("https://") || w.starts_with("http://")).unwrap_or(true), "must be an http(s) URL")
+            .check("email", self.email.as_ref().map(|e| mail::valid_address(e)).unwrap_or(true), "must be an email address")
+            .check("webhook", self.webhook.is_some() || self.email.is_some(), "a webhook or an email is required")
+            .finish()
+    }
+}
+
+impl Validate for quota::KeyRequest {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .check("name", !self.name.trim().is_empty() && self.name.len() <= 100, "must be 1-100 characters")
+            .check("daily_quota", self.daily_quota.map(|q| q > 0).unwrap_or(true), "must be positive")
+            .check("monthly_quota", self.monthly_quota.map(|q| q > 0).unwrap_or(true), "must be positive")
+            .finish()
+    }
+}
+
+impl Validate for moderation::HideRequest {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .check("reason", !self.reason.trim().is_empty() && self.reason.len() <= 500, "must be 1-500 characters")
+            .finish()
+    }
+}
+
+impl Validate for account::Favorites {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .check("professors", self.professors.len() <= MAX_FAVORITES, "too many professors")
+            .check("sections", self.sections.len() <= MAX_FAVORITES, "too many sections")
+            .check("sections", self.sections.iter().all(|s| s.len() == 5 && s.chars().all(|c| c.is_ascii_digit())), "must be 5-digit enroll codes")
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct MappingBody {
     pub rmp_id: u32,
+}
+
+#[derive(Deserialize)]
+struct MergeBody {
+    pub duplicate_id: u32,
+}
 
-    pub quality: Option<f32>,
-    pub quality_yr: Option<f32>,
+const MAX_BATCH_SIZE: usize = 20;
 
-    pub first_name: String,
-    pub last_name: String,
-    pub full_name: String,
+const BATCH_CONCURRENCY: usize = 4;
 
-    pub department: String,
+const MAX_SCHEDULE_COURSES: usize = 8;
+
+const MAX_FAVORITES: usize = 500;
+
+#[derive(Deserialize)]
+struct BatchItem {
+    pub name: String,
+    pub course: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct TopQuery {
+    pub n: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SampleQuery {
+    pub n: Option<usize>,
+    pub seed: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Comment {
-    pub class: String,
+const STRICT_MIN_CONFIDENCE: f32 = 0.75;
+
+const MIN_SCORE_WINDOW: f64 = 86400.0;
 
-    pub comment: String,
+const MAX_SCORE_WINDOW: f64 = 315360000.0;
 
-    pub grade: String,
+const MAX_MIN_WEIGHT: f64 = 100.0;
 
-    pub attendance_mandatory: Option<bool>,
+#[derive(Deserialize)]
+struct StrictQuery {
+    pub strict: Option<bool>,
+}
 
-    pub quality: f32,
-    pub difficulty: f32,
+#[derive(Deserialize)]
+struct TokenQuery {
+    pub refresh: Option<bool>,
+}
 
-    pub date: chrono::DateTime<chrono::Utc>,
+#[derive(Deserialize)]
+struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AutocompleteQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+impl Validate for SchoolPath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .finish()
+    }
+}
+
+impl Validate for ProfessorPath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .professor("name", self.name.as_str())
+            .finish()
+    }
+}
+
+impl Validate for CoursePath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .professor("name", self.name.as_str())
+            .course("course", self.course.as_str())
+            .finish()
+    }
+}
+
+impl Validate for LastNamePath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .professor("last", self.last.as_str())
+            .finish()
+    }
+}
+
+impl Validate for MappingPath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .professor("name", self.name.as_str())
+            .finish()
+    }
+}
+
+impl Validate for MergePath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .finish()
+    }
+}
+
+impl Validate for CourseIdPath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .course("course", self.course.as_str())
+            .finish()
+    }
+}
+
+impl Validate for schedule::SuggestRequest {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        let fields = validate::Fields::new()
+            .check("courses", !self.courses.is_empty() && self.courses.len() <= MAX_SCHEDULE_COURSES, "must list between 1 and 8 courses")
+            .check("constraints", self.constraints.is_valid(), "times must be formatted as HH:MM");
+
+        self.courses.iter()
+            .fold(fields, |fields, course| fields.course("courses", course.as_str()))
+            .finish()
+    }
+}
+
+impl Validate for TagPath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .school("school", self.school.as_deref())
+            .check("tag", !self.tag.trim().is_empty() && self.tag.len() <= 64, "must be 1-64 characters")
+            .finish()
+    }
+}
+
+impl Validate for GePath {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .check("area", !self.area.is_empty() && self.area.len() <= 8 && self.area.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'), "must be a GE area code")
+            .finish()
+    }
+}
+
+impl Validate for GeQuery {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .check("quarter", self.quarter.as_ref().map(|q| quarter::valid_code(q)).unwrap_or(true), "must be a quarter code such as 20241 or 20243A")
+            .finish()
+    }
+}
+
+impl Validate for BatchItem {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        let fields = validate::Fields::new().professor("name", self.name.as_str());
+
+        match &self.course {
+            Some(course) => fields.course("course", course.as_str()).finish(),
+            None => fields.finish(),
+        }
+    }
+}
+
+impl Validate for rmp::ScoreParams {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .range("window_secs", self.window_secs.map(|w| w as f64), MIN_SCORE_WINDOW, MAX_SCORE_WINDOW)
+            .range("min_weight", self.min_weight.map(|w| w as f64), 0.0, MAX_MIN_WEIGHT)
+            .finish()
+    }
+}
+
+impl Validate for AutocompleteQuery {
+    fn errors(&self) -> Vec<validate::FieldError> {
+        validate::Fields::new()
+            .name("q", self.q.as_str())
+            .finish()
+    }
+}
+
+async fn name_match(controller: &rmp::Controller, query: &str, professor: &rmp::Professor, strict: &StrictQuery) -> Result<NameMatch, error::Error> {
+    let confidence = controller.confidence(query, professor).await;
+
+    if strict.strict.unwrap_or(false) && confidence < STRICT_MIN_CONFIDENCE {
+        let candidates = controller.candidates(query).await
+            .into_iter()
+            .map(ProfessorSuggestion::from)
+            .collect();
+
+        return Err(error::Error::LowConfidence(candidates));
+    }
+
+    Ok(NameMatch {
+        query: query.to_owned(),
+        resolved_name: professor.full_name.clone(),
+        confidence,
+    })
+}
+
+const LANDING_PAGE: &str = include_str!("landing.html");
+const CARD_TAGS: usize = 3;
+
+async fn landing() -> actix_web::HttpResponse {
+    actix_web::HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(LANDING_PAGE)
 }
 
 async fn version() -> impl Responder {
     web::Json(json!({"version": "0.0.1"}))
 }
 
-async fn professor_overview(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
-    if let Some(pr) = data.rmp_controller.professor_overview(path.clone()).await {
-        let professor = pr.lock().await;
-        let p: rmp::Professor = professor.clone();
+async fn readyz(data: web::Data<AppState>) -> actix_web::HttpResponse {
+    let status = data.readiness.status(data.supervisor.unhealthy());
 
-        return actix_web::Either::A(web::Json(ProfessorResponse {
-            rmp_id: p.rmp_id,
-            quality: p.score.as_ref().map(|e| e.quality).flatten(),
-            quality_yr: p.score.as_ref().map(|e| e.quality_yr).flatten(),
-            first_name: p.first_name,
-            last_name: p.last_name,
-            full_name: p.full_name,
-            department: p.department,
-        }));
+    if status.ready {
+        actix_web::HttpResponse::Ok().json(status)
+    } else {
+        actix_web::HttpResponse::ServiceUnavailable().json(status)
     }
+}
+
+async fn professor_overview(path: web::Path<ProfessorPath>, query: web::Query<FieldsQuery>, strict: web::Query<StrictQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<serde_json::Value>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/overview")?;
+    let freshness = data.freshness("/r0/professor/{name}/overview", *freshness);
+    let controller = data.controller(path.school.as_deref())?;
+    let pr = controller.professor_overview(path.name.clone(), freshness).await?;
+    let p: rmp::Professor = pr.lock().await.clone();
+
+    let name_match = name_match(controller, path.name.as_str(), &p, &strict).await?;
+    let (department_percentile, school_percentile) = controller.professor_percentiles(p.rmp_id).await;
+    let catalog_fetched_at = p.rmp_department.as_ref().and(controller.catalog_fetched_at().await);
+    let metadata = controller.teacher_metadata(p.rmp_id).await;
 
-    actix_web::Either::B(web::Json(json!({"error": "RMP"})))
+    let mut resp = ProfessorResponse {
+        department_percentile,
+        school_percentile,
+        metadata,
+        name_match: Some(name_match),
+        ..ProfessorResponse::from(p)
+    };
+    if let Some(at) = catalog_fetched_at {
+        resp.sources.push(rmp::data_source(rmp::UCSB_SOURCE, at));
+    }
+
+    Ok(web::Json(fields::filter_fields(&resp, query.fields.as_deref())))
 }
 
-async fn professor_comments(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
-    let comments: Vec<Comment> = data.rmp_controller.professor_comments(path.clone(), None).await
+async fn card(data: &AppState, path: &ProfessorPath, route: &'static str, freshness: rmp::Freshness) -> Result<ProfessorCard, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire(route)?;
+    let freshness = data.freshness(route, freshness);
+    let (p, ratings) = data.controller(path.school.as_deref())?
+        .professor_comments(path.name.clone(), None, freshness).await?;
+
+    let score = p.score.as_ref().and_then(|e| e.quality);
+    let score_label = match score {
+        Some(quality) => format!("{:.1} / 5 from {} ratings", quality, ratings.value.len()),
+        None => "No ratings yet".to_owned(),
+    };
+
+    Ok(ProfessorCard {
+        title: p.full_name.clone(),
+        subtitle: p.rmp_department.clone().unwrap_or_else(|| p.department.clone()),
+        score,
+        score_label,
+        difficulty: stats::mean_difficulty(ratings.value.as_slice()),
+        num_ratings: ratings.value.len(),
+        top_tags: stats::top_tags(ratings.value.as_slice(), CARD_TAGS),
+        url: rmp::rmp_ids(p.rmp_id).rmp_url,
+        fetched_at: p.score.as_ref().map(|e| e.fetched_at),
+    })
+}
+
+async fn professor_card(path: web::Path<ProfessorPath>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<ProfessorCard>, error::Error> {
+    Ok(web::Json(card(&data, &path, "/r0/professor/{name}/card", *freshness).await?))
+}
+
+async fn professor_card_svg(path: web::Path<ProfessorPath>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<actix_web::HttpResponse, error::Error> {
+    let card = card(&data, &path, "/r0/professor/{name}/card.svg", *freshness).await?;
+
+    Ok(actix_web::HttpResponse::Ok()
+        .content_type("image/svg+xml")
+        .body(svg::render_card(&card)))
+}
+
+async fn professor_badge(path: web::Path<ProfessorPath>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<ShieldsBadge>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/badge.json")?;
+    let freshness = data.freshness("/r0/professor/{name}/badge.json", *freshness);
+    let pr = data.controller(path.school.as_deref())?
+        .professor_overview(path.name.clone(), freshness).await?;
+    let quality = pr.lock().await.score.as_ref().and_then(|e| e.quality);
+
+    Ok(web::Json(ShieldsBadge::quality(quality)))
+}
+
+async fn moderated_comments(controller: &rmp::Controller, name: String, course: Option<String>, freshness: rmp::Freshness, include_hidden: bool) -> Result<(rmp::Professor, rmp::Fetched<Vec<rmp::NormalizedRating>>), error::Error> {
+    if include_hidden {
+        controller.unmoderated_comments(name, course, freshness).await
+    } else {
+        controller.professor_comments(name, course, freshness).await
+    }
+}
+
+async fn professor_comments(req: HttpRequest, path: web::Path<ProfessorPath>, query: web::Query<FieldsQuery>, filter: web::Query<filter::CommentFilter>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<serde_json::Value>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/comments")?;
+    let freshness = data.freshness("/r0/professor/{name}/comments", *freshness);
+    let include_hidden = data.include_hidden(&req, &filter)?;
+    let ratings = moderated_comments(data.controller(path.school.as_deref())?, path.name.clone(), None, freshness, include_hidden).await
+        .map(|(_, f)| f.value)
+        .unwrap_or_default();
+
+    let now = data.clock.now();
+    let comments: Vec<Comment> = filter.apply(ratings, now)?
         .iter()
-        .map(|r| Comment {
-            class: r.class.clone(),
-            comment: r.comment.replace("&quot;", "\""),
-            grade: r.grade.clone(),
-            attendance_mandatory: r.attendance_mandatory.clone(),
-            quality: (r.clarity + r.helpful) as f32 / 2.0,
-            difficulty: r.difficulty as f32,
-            date: r.date.clone(),
-        })
+        .map(|r| to_comment(r, now))
         .collect();
 
-    return web::Json(comments);
+    Ok(web::Json(fields::filter_fields(&comments, query.fields.as_deref())))
 }
 
-async fn professor_course_comments(path: web::Path<(String, String)>, data: web::Data<AppState>) -> impl Responder {
-    let comments: Vec<Comment> = data.rmp_controller.professor_comments(path.0.clone(), Some(path.1.clone())).await
+async fn professor_course_comments(req: HttpRequest, path: web::Path<CoursePath>, query: web::Query<FieldsQuery>, filter: web::Query<filter::CommentFilter>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<serde_json::Value>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/course/{course}/comments")?;
+    let freshness = data.freshness("/r0/professor/{name}/course/{course}/comments", *freshness);
+    let include_hidden = data.include_hidden(&req, &filter)?;
+    let ratings = moderated_comments(data.controller(path.school.as_deref())?, path.name.clone(), Some(path.course.clone()), freshness, include_hidden).await
+        .map(|(_, f)| f.value)
+        .unwrap_or_default();
+
+    let now = data.clock.now();
+    let comments: Vec<Comment> = filter.apply(ratings, now)?
+        .iter()
+        .map(|r| to_comment(r, now))
+        .collect();
+
+    Ok(web::Json(fields::filter_fields(&comments, query.fields.as_deref())))
+}
+
+async fn professor_top_comments(path: web::Path<ProfessorPath>, query: web::Query<FieldsQuery>, top: web::Query<TopQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<serde_json::Value>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/comments/top")?;
+    let freshness = data.freshness("/r0/professor/{name}/comments/top", *freshness);
+    let n = top.n.unwrap_or(5).clamp(1, 50);
+
+    let comments: Vec<Comment> = data.controller(path.school.as_deref())?
+        .top_comments(path.name.clone(), n, freshness).await?
+        .value
+        .iter()
+        .map(|r| to_comment(r, data.clock.now()))
+        .collect();
+
+    Ok(web::Json(fields::filter_fields(&comments, query.fields.as_deref())))
+}
+
+async fn professor_sample_comments(path: web::Path<ProfessorPath>, query: web::Query<FieldsQuery>, sample: web::Query<SampleQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<serde_json::Value>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/comments/sample")?;
+    let freshness = data.freshness("/r0/professor/{name}/comments/sample", *freshness);
+    let n = sample.n.unwrap_or(5).clamp(1, 50);
+    let seed = sample.seed.unwrap_or_else(analysis::Rng::seed);
+
+    let comments: Vec<Comment> = data.controller(path.school.as_deref())?
+        .sample_comments(path.name.clone(), n, seed, freshness).await?
+        .value
         .iter()
-        .map(|r| Comment {
-            class: r.class.clone(),
-            comment: r.comment.replace("&quot;", "\""),
-            grade: r.grade.clone(),
-            attendance_mandatory: r.attendance_mandatory.clone(),
-            quality: (r.clarity + r.helpful) as f32 / 2.0,
-            difficulty: r.difficulty as f32,
-            date: r.date.clone(),
+        .map(|r| to_comment(r, data.clock.now()))
+        .collect();
+
+    Ok(web::Json(json!({
+        "seed": seed,
+        "comments": fields::filter_fields(&comments, query.fields.as_deref()),
+    })))
+}
+
+async fn professor_score(path: web::Path<ProfessorPath>, params: web::Query<rmp::ScoreParams>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<rmp::DryRunScore>, error::Error> {
+    path.validate()?;
+    params.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/score")?;
+    let freshness = data.freshness("/r0/professor/{name}/score", *freshness);
+
+    Ok(web::Json(data.controller(path.school.as_deref())?
+        .dry_run_score(path.name.clone(), &params, freshness).await?))
+}
+
+async fn batch_comments(path: web::Path<SchoolPath>, body: web::Json<Vec<BatchItem>>, query: web::Query<BatchQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<HashMap<String, Vec<Comment>>>, error::Error> {
+    path.validate()?;
+    body.validate()?;
+    if body.len() > MAX_BATCH_SIZE {
+        return Err(error::Error::BadRequest(format!("at most {} professors per batch", MAX_BATCH_SIZE)));
+    }
+
+    let controller = data.controller(path.school.as_deref())?;
+    let _permit = data.limits.acquire("/r0/professors/comments")?;
+    let freshness = data.freshness("/r0/professors/comments", *freshness);
+    let limit = query.limit.unwrap_or(3).clamp(1, 50);
+    let now = data.clock.now();
+
+    let comments = futures::stream::iter(body.iter())
+        .map(|item| async move {
+            let key = match &item.course {
+                Some(course) => format!("{}/{}", item.name, course),
+                None => item.name.clone(),
+            };
+
+            let mut ratings = controller.professor_comments(item.name.clone(), item.course.clone(), freshness).await
+                .map(|(_, f)| f.value)
+                .unwrap_or_default();
+            ratings.sort_by_key(|r| std::cmp::Reverse(r.date));
+
+            (key, ratings.iter().take(limit).map(|r| to_comment(r, now)).collect::<Vec<Comment>>())
+        })
+        .buffer_unordered(BATCH_CONCURRENCY)
+        .collect::<HashMap<String, Vec<Comment>>>()
+        .await;
+
+    Ok(web::Json(comments))
+}
+
+async fn instructor_professors(controller: &rmp::Controller, instructors: std::collections::BTreeSet<String>, freshness: rmp::Freshness) -> HashMap<String, rmp::Professor> {
+    futures::stream::iter(instructors)
+        .map(|instructor| async move {
+            let pr = controller.professor_overview(schedule::instructor_query(instructor.as_str()), freshness).await.ok()?;
+            let professor = pr.lock().await.clone();
+
+            Some((instructor, professor))
+        })
+        .buffer_unordered(BATCH_CONCURRENCY)
+        .filter_map(futures::future::ready)
+        .collect()
+        .await
+}
+
+async fn ge_courses(path: web::Path<GePath>, query: web::Query<GeQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<Vec<GeCourseResponse>>, error::Error> {
+    path.validate()?;
+    query.validate()?;
+    let _permit = data.limits.acquire("/r0/ge/{area}/courses")?;
+    let freshness = data.freshness("/r0/ge/{area}/courses", *freshness);
+    let controller = data.controller(Some("ucsb"))?;
+    let quarter = query.quarter.as_deref()
+        .and_then(quarter::Term::parse)
+        .map(|t| t.code())
+        .unwrap_or_else(|| data.quarter.clone());
+
+    if !data.ge.read().await.contains_key(quarter.as_str()) {
+        let catalog = data.catalog.as_ref().ok_or(error::Error::NotFound)?;
+        let index = ge::GeIndex::from_classes(catalog.classes(quarter.as_str()).await?.as_slice());
+        data.ge.write().await.insert(quarter.clone(), index);
+    }
+
+    let courses = data.ge.read().await.get(quarter.as_str())
+        .and_then(|index| index.courses(path.area.as_str()))
+        .ok_or(error::Error::NotFound)?;
+
+    let instructors = courses.iter().flat_map(|c| c.instructors.iter().cloned()).collect();
+    let professors = instructor_professors(controller, instructors, freshness).await;
+
+    let mut resp: Vec<GeCourseResponse> = courses.into_iter()
+        .map(|c| GeCourseResponse {
+            instructors: c.instructors.iter()
+                .map(|i| GeInstructor {
+                    name: i.clone(),
+                    professor: professors.get(i).cloned().map(ProfessorResponse::from),
+                })
+                .collect(),
+            catalog_url: course::catalog_url(c.course.as_str()),
+            course: c.course,
+            title: c.title,
         })
         .collect();
 
-    return web::Json(comments);
+    resp.sort_by(|a, b| b.best_quality().partial_cmp(&a.best_quality()).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(web::Json(resp))
+}
+
+#[cfg(feature = "scheduler")]
+async fn watch_section(body: web::Json<watch::WatchRequest>, data: web::Data<AppState>) -> Result<actix_web::HttpResponse, error::Error> {
+    body.validate()?;
+
+    let watch = data.watchlist.register(body.into_inner()).await?;
+
+    Ok(actix_web::HttpResponse::Created().json(watch))
+}
+
+async fn schedule_suggest(body: web::Json<schedule::SuggestRequest>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<Vec<schedule::Suggestion>>, error::Error> {
+    body.validate()?;
+    let _permit = data.limits.acquire("/r0/schedule/suggest")?;
+    let freshness = data.freshness("/r0/schedule/suggest", *freshness);
+    let controller = data.controller(Some("ucsb"))?;
+
+    let options = {
+        let index = data.sections.read().await;
+
+        body.courses.iter()
+            .map(|course| index.options(course.as_str())
+                .ok_or_else(|| error::Error::BadRequest(format!("unknown course {}", course))))
+            .collect::<Result<Vec<Vec<schedule::SectionChoice>>, error::Error>>()?
+    };
+
+    let instructors: std::collections::BTreeSet<String> = options.iter()
+        .flatten()
+        .filter_map(|c| c.instructor.clone())
+        .collect();
+
+    let qualities: HashMap<String, f32> = instructor_professors(controller, instructors, freshness).await
+        .into_iter()
+        .filter_map(|(instructor, p)| Some((instructor, p.score?.quality?)))
+        .collect();
+
+    let limit = body.limit.unwrap_or(10).clamp(1, 50);
+
+    Ok(web::Json(schedule::suggest(options, &qualities, &body.constraints, limit)))
+}
+
+async fn professors_by_last_name(path: web::Path<LastNamePath>, query: web::Query<FieldsQuery>, department: web::Query<department::DepartmentQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<serde_json::Value>, error::Error> {
+    path.validate()?;
+    let freshness = data.freshness("/r0/professor/lastname/{last}", *freshness);
+    let professors: Vec<ProfessorMatch> = data.controller(path.school.as_deref())?.professors_by_last_name(path.last.clone(), freshness).await
+        .value
+        .into_iter()
+        .filter(|p| department.accepts(p.department.as_str()))
+        .map(ProfessorMatch::from)
+        .collect();
+
+    Ok(web::Json(fields::filter_fields(&professors, query.fields.as_deref())))
+}
+
+impl From<rmp::Professor> for ProfessorSuggestion {
+    fn from(p: rmp::Professor) -> Self {
+        ProfessorSuggestion {
+            ids: rmp::rmp_ids(p.rmp_id),
+            full_name: p.full_name,
+            department: p.department,
+        }
+    }
+}
+
+const MATCH_COURSES: usize = 3;
+
+fn group_by_department(matches: Vec<(rmp::Professor, Vec<CourseCount>)>) -> Vec<DepartmentGroup> {
+    let mut groups: Vec<DepartmentGroup> = Vec::new();
+
+    for (professor, top_courses) in matches {
+        let department = professor.department.clone();
+        let professor = ProfessorMatch { top_courses, ..ProfessorMatch::from(professor) };
+
+        match groups.iter_mut().find(|g| g.department == department) {
+            Some(group) => group.professors.push(professor),
+            None => groups.push(DepartmentGroup { department, professors: vec![professor] }),
+        }
+    }
+
+    for group in groups.iter_mut() {
+        group.professors.sort_by_key(|p| std::cmp::Reverse(p.professor.num_ratings));
+    }
+    groups.sort_by(|a, b| {
+        let total = |g: &DepartmentGroup| g.professors.iter().filter_map(|p| p.professor.num_ratings).sum::<u32>();
+        total(b).cmp(&total(a)).then(a.department.cmp(&b.department))
+    });
+
+    groups
+}
+
+async fn professor_matches(path: web::Path<ProfessorPath>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<Vec<DepartmentGroup>>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r0/professor/{name}/matches")?;
+    let freshness = data.freshness("/r0/professor/{name}/matches", *freshness);
+    let matches = data.controller(path.school.as_deref())?
+        .professor_matches(path.name.clone(), MATCH_COURSES, freshness).await?;
+
+    Ok(web::Json(group_by_department(matches)))
+}
+
+async fn autocomplete_professor(path: web::Path<SchoolPath>, query: web::Query<AutocompleteQuery>, department: web::Query<department::DepartmentQuery>, data: web::Data<AppState>) -> Result<web::Json<Vec<ProfessorSuggestion>>, error::Error> {
+    path.validate()?;
+    query.validate()?;
+    let limit = query.limit.unwrap_or(10).min(50);
+
+    let suggestions: Vec<ProfessorSuggestion> = data.controller(path.school.as_deref())?.autocomplete_professor(query.q.clone(), limit).await
+        .value
+        .into_iter()
+        .filter(|p| department.accepts(p.department.as_str()))
+        .map(ProfessorSuggestion::from)
+        .collect();
+
+    Ok(web::Json(suggestions))
+}
+
+async fn autocomplete_course(path: web::Path<SchoolPath>, query: web::Query<AutocompleteQuery>, data: web::Data<AppState>) -> Result<web::Json<Vec<String>>, error::Error> {
+    path.validate()?;
+    query.validate()?;
+    let limit = query.limit.unwrap_or(10).min(50);
+
+    Ok(web::Json(data.controller(path.school.as_deref())?.autocomplete_course(query.q.clone(), limit).await.value))
+}
+
+async fn comments_envelope(controller: &rmp::Controller, name: String, course: Option<String>, query: &envelope::ListQuery, filter: &filter::CommentFilter, include_hidden: bool, freshness: rmp::Freshness) -> Result<(rmp::Professor, envelope::Envelope), error::Error> {
+    let now = controller.now();
+    let sort = filter.sort(query)?;
+
+    if let Some(ratings_query) = filter.ratings_query(query)? {
+        if let Some((professor, ratings, total)) = controller.query_comments(name.clone(), course.clone(), ratings_query, include_hidden, freshness).await? {
+            let key = if sort == store::RatingsSort::Newest { Some(envelope::Cursor::rating) } else { None };
+            let envelope = envelope::Envelope::from_rows(ratings, total, query, key, |r| to_comment(&filter.present(r.clone()), now), now);
+
+            return Ok((professor, envelope));
+        }
+    }
+
+    let (professor, ratings) = moderated_comments(controller, name, course, freshness, include_hidden).await?;
+    let ratings = rmp::Fetched { value: filter.apply(ratings.value, now)?, ..ratings };
+
+    let envelope = match sort {
+        store::RatingsSort::Newest => envelope::Envelope::paginate_cursor(ratings, query, envelope::Cursor::rating, |r| to_comment(r, now), now)?,
+        _ => envelope::Envelope::paginate(ratings.map(|ratings| ratings.iter().map(|r| to_comment(r, now)).collect::<Vec<Comment>>()), query, now),
+    };
+
+    Ok((professor, envelope))
+}
+
+fn with_professor(envelope: envelope::Envelope, professor: rmp::Professor, name_match: NameMatch) -> envelope::Envelope {
+    envelope::Envelope {
+        data: json!({
+            "professor": ProfessorSuggestion::from(professor),
+            "match": name_match,
+            "comments": envelope.data,
+        }),
+        ..envelope
+    }
+}
+
+async fn r1_professor_comments(req: HttpRequest, path: web::Path<ProfessorPath>, query: web::Query<envelope::ListQuery>, filter: web::Query<filter::CommentFilter>, strict: web::Query<StrictQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<envelope::Envelope>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r1/professor/{name}/comments")?;
+    let freshness = data.freshness("/r1/professor/{name}/comments", *freshness);
+    let controller = data.controller(path.school.as_deref())?;
+    let include_hidden = data.include_hidden(&req, &filter)?;
+    let (professor, envelope) = comments_envelope(controller, path.name.clone(), None, &query, &filter, include_hidden, freshness).await?;
+    let name_match = name_match(controller, path.name.as_str(), &professor, &strict).await?;
+
+    Ok(web::Json(with_professor(envelope, professor, name_match)))
+}
+
+async fn r1_professor_course_comments(req: HttpRequest, path: web::Path<CoursePath>, query: web::Query<envelope::ListQuery>, filter: web::Query<filter::CommentFilter>, strict: web::Query<StrictQuery>, freshness: web::Query<rmp::Freshness>, data: web::Data<AppState>) -> Result<web::Json<envelope::Envelope>, error::Error> {
+    path.validate()?;
+    let _permit = data.limits.acquire("/r1/professor/{name}/course/{course}/comments")?;
+    let freshness = data.freshness("/r1/professor/{name}/course/{course}/comments", *freshness);
+    let controller = data.controller(path.school.as_deref())?;
+    let include_hidden = data.include_hidden(&req, &filter)?;
+    let (professor, envelope) = comments_envelope(controller, path.name.clone(), Some(path.course.clone()), &query, &filter, include_hidden, freshness).await?;
+    let name_match = name_match(controller, path.name.as_str(), &professor, &strict).await?;
+
+    let links = CourseLinks::new(professor.rmp_id, path.course.as_str());
+
+    let mut envelope = with_professor(envelope, professor, name_match);
+    envelope.data["links"] = json!(links);
+
+    Ok(web::Json(envelope))
+}
+
+async fn r1_autocomplete_professor(path: web::Path<SchoolPath>, query: web::Query<AutocompleteQuery>, list: web::Query<envelope::ListQuery>, department: web::Query<department::DepartmentQuery>, data: web::Data<AppState>) -> Result<web::Json<envelope::Envelope>, error::Error> {
+    path.validate()?;
+    query.validate()?;
+    let limit = query.limit.unwrap_or(10).min(50);
+
+    let suggestions = data.controller(path.school.as_deref())?.autocomplete_professor(query.q.clone(), limit).await
+        .map(|professors| professors.into_iter()
+            .filter(|p| department.accepts(p.department.as_str()))
+            .map(ProfessorSuggestion::from)
+            .collect::<Vec<ProfessorSuggestion>>());
+
+    Ok(web::Json(envelope::Envelope::paginate(suggestions, &list, data.clock.now())))
 }
 
-async fn rmp_graphql_token(data: web::Data<AppState>) -> impl Responder {
-    if let Ok(token) = data.rmp_controller.graphql_token().await {
-        return web::Json(json!({
-            "token": token,
+async fn r1_autocomplete_course(path: web::Path<SchoolPath>, query: web::Query<AutocompleteQuery>, list: web::Query<envelope::ListQuery>, data: web::Data<AppState>) -> Result<web::Json<envelope::Envelope>, error::Error> {
+    path.validate()?;
+    query.validate()?;
+    let limit = query.limit.unwrap_or(10).min(50);
+
+    Ok(web::Json(envelope::Envelope::paginate(data.controller(path.school.as_deref())?.autocomplete_course(query.q.clone(), limit).await, &list, data.clock.now())))
+}
+
+async fn create_account(data: web::Data<AppState>) -> Result<actix_web::HttpResponse, error::Error> {
+    let _permit = data.limits.acquire("accounts")?;
+
+    Ok(actix_web::HttpResponse::Created().json(data.accounts.create()?))
+}
+
+async fn get_favorites(req: HttpRequest, data: web::Data<AppState>) -> Result<web::Json<account::Favorites>, error::Error> {
+    let account_id = data.account(&req)?;
+    let _permit = data.limits.acquire("favorites")?;
+
+    Ok(web::Json(data.accounts.favorites(account_id.as_str()).await?))
+}
+
+async fn put_favorites(req: HttpRequest, body: web::Json<account::Favorites>, data: web::Data<AppState>) -> Result<web::Json<account::Favorites>, error::Error> {
+    let account_id = data.account(&req)?;
+    body.validate()?;
+    let _permit = data.limits.acquire("favorites")?;
+
+    Ok(web::Json(data.accounts.save_favorites(account_id.as_str(), body.into_inner()).await?))
+}
+
+async fn dump_ratings(path: web::Path<SchoolPath>, department: web::Query<department::DepartmentQuery>, data: web::Data<AppState>) -> Result<actix_web::HttpResponse, error::Error> {
+    path.validate()?;
+
+    let rows = data.controller(path.school.as_deref())?
+        .dump_ratings(department.into_inner().department).await?
+        .map(|rows| rows.and_then(|rows| {
+            let mut lines = Vec::new();
+            for row in rows {
+                serde_json::to_writer(&mut lines, &row).map_err(|e| error::Error::Store(e.to_string()))?;
+                lines.push(b'\n');
+            }
+
+            Ok(web::Bytes::from(lines))
         }));
+
+    Ok(actix_web::HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(Box::pin(rows)))
+}
+
+async fn departments() -> impl Responder {
+    web::Json(department::DEPARTMENTS)
+}
+
+async fn school_stats(path: web::Path<SchoolPath>, data: web::Data<AppState>) -> Result<web::Json<stats::SchoolStats>, error::Error> {
+    path.validate()?;
+    Ok(web::Json(data.controller(path.school.as_deref())?.school_stats().await))
+}
+
+async fn tag_professors(path: web::Path<TagPath>, query: web::Query<LimitQuery>, department: web::Query<department::DepartmentQuery>, data: web::Data<AppState>) -> Result<web::Json<stats::TagReport>, error::Error> {
+    path.validate()?;
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    Ok(web::Json(data.controller(path.school.as_deref())?
+        .tag_professors(path.tag.as_str(), department.into_inner().department, limit).await))
+}
+
+async fn grade_vs_quality(path: web::Path<SchoolPath>, department: web::Query<department::DepartmentQuery>, data: web::Data<AppState>) -> Result<web::Json<stats::GradeQualityReport>, error::Error> {
+    path.validate()?;
+    Ok(web::Json(data.controller(path.school.as_deref())?.grade_vs_quality(department.into_inner().department).await))
+}
+
+async fn course_prereqs(path: web::Path<CourseIdPath>, data: web::Data<AppState>) -> Result<web::Json<prereq::Dependencies>, error::Error> {
+    path.validate()?;
+    Ok(web::Json(data.prereqs.read().await.prereqs(path.course.as_str()).ok_or(error::Error::NotFound)?))
+}
+
+async fn course_unlocks(path: web::Path<CourseIdPath>, data: web::Data<AppState>) -> Result<web::Json<prereq::Dependencies>, error::Error> {
+    path.validate()?;
+    Ok(web::Json(data.prereqs.read().await.unlocks(path.course.as_str()).ok_or(error::Error::NotFound)?))
+}
+
+async fn crawl_school(pace: std::time::Duration, data: web::Data<AppState>) {
+    for controller in data.controllers() {
+        match controller.crawl(pace).await {
+            Ok(count) => println!("crawl_school: crawled {} professors at {}", count, controller.school_id()),
+            Err(e) => println!("crawl_school: error {}", e),
+        }
     }
+}
+
+async fn warm_cache(entries: Vec<String>, concurrency: usize, data: web::Data<AppState>) {
+    let controller = match data.controller(None) {
+        Ok(controller) => controller,
+        Err(_) => return,
+    };
+
+    let warmed = futures::stream::iter(entries.iter())
+        .map(|entry| async move {
+            let result = controller.warm(entry.as_str()).await;
+
+            if let Err(e) = &result {
+                println!("warm_cache: {} error {}", entry, e);
+            }
+
+            result.is_ok()
+        })
+        .buffer_unordered(concurrency.max(1))
+        .fold(0, |warmed, ok| futures::future::ready(if ok { warmed + 1 } else { warmed }))
+        .await;
+
+    println!("warm_cache: warmed {} of {} professors", warmed, entries.len());
+}
+
+#[cfg(feature = "scheduler")]
+async fn run_scheduled_job(job: scheduler::JobConfig, data: web::Data<AppState>) {
+    data.scheduler.run_forever(job, data.controllers().as_slice()).await
+}
+
+async fn admin_jobs(data: web::Data<AppState>) -> impl Responder {
+    #[cfg(feature = "scheduler")]
+    let jobs = data.scheduler.status().await;
+    #[cfg(not(feature = "scheduler"))]
+    let jobs: Vec<serde_json::Value> = Vec::new();
 
     web::Json(json!({
-        "error": "RMP",
+        "jobs": jobs,
+        "tasks": data.supervisor.health(),
     }))
 }
 
+#[cfg(feature = "scheduler")]
+async fn admin_run_job(req: HttpRequest, path: web::Path<String>, data: web::Data<AppState>) -> Result<actix_web::HttpResponse, error::Error> {
+    let job = path.parse::<scheduler::Job>().map_err(|_| error::Error::NotFound)?;
+
+    if data.scheduler.is_running(job).await {
+        return Err(error::Error::BadRequest(format!("{:?} is already running", job)));
+    }
+
+    data.audit.record(data.actor(&req).as_str(), "run_job", path.as_str(), None).await;
+
+    actix_rt::spawn(async move {
+        let controllers = data.controllers();
+        let run = std::panic::AssertUnwindSafe(data.scheduler.run(job, controllers.as_slice()));
+
+        match futures::FutureExt::catch_unwind(run).await {
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) => println!("admin_run_job: {:?} error {}", job, e),
+            Err(payload) => println!("admin_run_job: {:?} panicked: {}", job, supervisor::panic_message(payload.as_ref())),
+        }
+    });
+
+    Ok(actix_web::HttpResponse::Accepted().json(json!({
+        "job": job,
+        "status": "started",
+    })))
+}
+
+async fn admin_pin_mapping(req: HttpRequest, path: web::Path<MappingPath>, body: web::Json<MappingBody>, data: web::Data<AppState>) -> Result<web::Json<ProfessorSuggestion>, error::Error> {
+    path.validate()?;
+    let professor = data.controller(path.school.as_deref())?
+        .pin_name(path.name.clone(), body.rmp_id).await?;
+
+    let target = format!("{}/{}", path.school.as_deref().unwrap_or(data.default_school.as_str()), path.name);
+    data.audit.record(data.actor(&req).as_str(), "pin_mapping", target.as_str(), Some(format!("rmp_id={}", body.rmp_id))).await;
+
+    Ok(web::Json(ProfessorSuggestion::from(professor)))
+}
+
+async fn admin_merge_professors(req: HttpRequest, path: web::Path<MergePath>, body: web::Json<MergeBody>, data: web::Data<AppState>) -> Result<web::Json<ProfessorSuggestion>, error::Error> {
+    path.validate()?;
+    let professor = data.controller(path.school.as_deref())?
+        .merge_professors(path.rmp_id, body.duplicate_id).await?;
+
+    let target = format!("{}/{}", path.school.as_deref().unwrap_or(data.default_school.as_str()), path.rmp_id);
+    data.audit.record(data.actor(&req).as_str(), "merge_professors", target.as_str(), Some(format!("duplicate_id={}", body.duplicate_id))).await;
+
+    Ok(web::Json(ProfessorSuggestion::from(professor)))
+}
+
+async fn admin_keys(data: web::Data<AppState>) -> impl Responder {
+    web::Json(data.quotas.keys())
+}
+
+async fn admin_create_key(req: HttpRequest, body: web::Json<quota::KeyRequest>, data: web::Data<AppState>) -> Result<actix_web::HttpResponse, error::Error> {
+    body.validate()?;
+    let created = data.quotas.create(body.into_inner()).await?;
+
+    data.audit.record(data.actor(&req).as_str(), "create_key", created.api_key.id.as_str(), Some(created.api_key.name.clone())).await;
+
+    Ok(actix_web::HttpResponse::Created().json(created))
+}
+
+async fn admin_revoke_key(req: HttpRequest, path: web::Path<String>, data: web::Data<AppState>) -> Result<web::Json<quota::ApiKey>, error::Error> {
+    let revoked = data.quotas.revoke(path.as_str()).await?;
+
+    data.audit.record(data.actor(&req).as_str(), "revoke_key", revoked.id.as_str(), None).await;
+
+    Ok(web::Json(revoked))
+}
+
+async fn admin_audit(query: web::Query<audit::AuditQuery>, data: web::Data<AppState>) -> Result<web::Json<Vec<audit::AuditEntry>>, error::Error> {
+    Ok(web::Json(data.audit.entries(&query).await?))
+}
+
+async fn admin_upstream(data: web::Data<AppState>) -> impl Responder {
+    web::Json(data.budget.report(data.clock.now()))
+}
+
+async fn internal_metrics(data: web::Data<AppState>) -> actix_web::HttpResponse {
+    actix_web::HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(data.budget.metrics(data.clock.now()))
+}
+
+async fn admin_hidden_ratings(data: web::Data<AppState>) -> impl Responder {
+    web::Json(data.hidden.list())
+}
+
+async fn admin_hide_rating(req: HttpRequest, path: web::Path<u32>, body: web::Json<moderation::HideRequest>, data: web::Data<AppState>) -> Result<web::Json<moderation::HiddenRating>, error::Error> {
+    body.validate()?;
+    let hidden = data.hidden.hide(*path, body.into_inner().reason).await?;
+    for controller in data.controllers() {
+        controller.forget_scores().await;
+    }
+
+    data.audit.record(data.actor(&req).as_str(), "hide_rating", path.to_string().as_str(), Some(hidden.reason.clone())).await;
+
+    Ok(web::Json(hidden))
+}
+
+async fn admin_unhide_rating(req: HttpRequest, path: web::Path<u32>, data: web::Data<AppState>) -> Result<web::Json<moderation::HiddenRating>, error::Error> {
+    let hidden = data.hidden.unhide(*path).await?;
+    for controller in data.controllers() {
+        controller.forget_scores().await;
+    }
+
+    data.audit.record(data.actor(&req).as_str(), "unhide_rating", path.to_string().as_str(), None).await;
+
+    Ok(web::Json(hidden))
+}
+
+async fn index_catalog_courses(data: web::Data<AppState>) {
+    let (controller, catalog) = match (data.controller(Some("ucsb")), &data.catalog) {
+        (Ok(controller), Some(catalog)) => (controller, catalog),
+        _ => return,
+    };
+
+    match catalog.classes(data.quarter.as_str()).await {
+        Ok(classes) => {
+            *data.prereqs.write().await = prereq::PrereqGraph::from_classes(classes.as_slice());
+            *data.sections.write().await = schedule::SectionIndex::from_classes(classes.as_slice());
+            data.ge.write().await.insert(data.quarter.clone(), ge::GeIndex::from_classes(classes.as_slice()));
+
+            let corrected = controller.correct_departments(&ucsb::instructor_subjects(classes.as_slice())).await;
+            println!("index_catalog_courses: corrected {} professor departments", corrected);
+
+            let cross_listings = course::CrossListings::from_classes(classes.as_slice());
+            println!("index_catalog_courses: indexed {} cross-listed courses", cross_listings.course_count());
+            controller.index_cross_listings(cross_listings).await;
+            controller.index_courses(classes.into_iter().map(|c| c.course_id).collect()).await;
+        }
+        Err(e) => println!("index_catalog_courses: error {}", e),
+    }
+}
+
+async fn rmp_graphql_token(query: web::Query<TokenQuery>, data: web::Data<AppState>) -> Result<web::Json<rmp::GraphQLToken>, error::Error> {
+    Ok(web::Json(data.controller(None)?.graphql_token_info(query.refresh.unwrap_or(false)).await?))
+}
+
+fn round_json(head: &mut ResponseHead, body: ResponseBody<Body>, precision: u32) -> ResponseBody<Body> {
+    let json = head.headers().get("Content-Type")
+        .and_then(|h| h.to_str().ok())
+        .map(|h| h.starts_with("application/json"))
+        .unwrap_or(false);
+
+    let bytes = match &body {
+        ResponseBody::Body(Body::Bytes(bytes)) | ResponseBody::Other(Body::Bytes(bytes)) if json => bytes,
+        _ => return body,
+    };
+
+    match serde_json::from_slice(bytes) {
+        Ok(value) => ResponseBody::Body(Body::from(fields::round_numbers(value, precision).to_string())),
+        Err(_) => body,
+    }
+}
+
+fn read_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        .route("/", web::get().to(landing))
+        .route("/version", web::get().to(version))
+        .route("/readyz", web::get().to(readyz))
+        .route("/r0/professor/lastname/{last}", web::get().to(professors_by_last_name))
+        .route("/r0/professor/{name}/overview", web::get().to(professor_overview))
+        .route("/r0/professor/{name}/matches", web::get().to(professor_matches))
+        .route("/r0/professor/{name}/comments", web::get().to(professor_comments))
+        .route("/r0/professor/{name}/comments/top", web::get().to(professor_top_comments))
+        .route("/r0/professor/{name}/comments/sample", web::get().to(professor_sample_comments))
+        .route("/r0/professor/{name}/score", web::get().to(professor_score))
+        .route("/r0/professor/{name}/card", web::get().to(professor_card))
+        .route("/r0/professor/{name}/card.svg", web::get().to(professor_card_svg))
+        .route("/r0/professor/{name}/badge.json", web::get().to(professor_badge))
+        .route("/r0/professor/{name}/course/{course}/comments", web::get().to(professor_course_comments))
+        .route("/r0/autocomplete/professor", web::get().to(autocomplete_professor))
+        .route("/r0/autocomplete/course", web::get().to(autocomplete_course))
+        .route("/r1/professor/{name}/comments", web::get().to(r1_professor_comments))
+        .route("/r1/professor/{name}/course/{course}/comments", web::get().to(r1_professor_course_comments))
+        .route("/r1/autocomplete/professor", web::get().to(r1_autocomplete_professor))
+        .route("/r1/autocomplete/course", web::get().to(r1_autocomplete_course))
+        .route("/r0/departments", web::get().to(departments))
+        .route("/r0/ge/{area}/courses", web::get().to(ge_courses))
+        .route("/r0/course/{course}/prereqs", web::get().to(course_prereqs))
+        .route("/r0/course/{course}/unlocks", web::get().to(course_unlocks))
+        .route("/r0/school/stats", web::get().to(school_stats))
+        .route("/r0/reports/grade_vs_quality", web::get().to(grade_vs_quality))
+        .route("/r0/tags/{tag}/professors", web::get().to(tag_professors))
+        .route("/r0/{school}/professor/lastname/{last}", web::get().to(professors_by_last_name))
+        .route("/r0/{school}/professor/{name}/overview", web::get().to(professor_overview))
+        .route("/r0/{school}/professor/{name}/matches", web::get().to(professor_matches))
+        .route("/r0/{school}/professor/{name}/comments", web::get().to(professor_comments))
+        .route("/r0/{school}/professor/{name}/comments/top", web::get().to(professor_top_comments))
+        .route("/r0/{school}/professor/{name}/comments/sample", web::get().to(professor_sample_comments))
+        .route("/r0/{school}/professor/{name}/score", web::get().to(professor_score))
+        .route("/r0/{school}/professor/{name}/card", web::get().to(professor_card))
+        .route("/r0/{school}/professor/{name}/card.svg", web::get().to(professor_card_svg))
+        .route("/r0/{school}/professor/{name}/badge.json", web::get().to(professor_badge))
+        .route("/r0/{school}/professor/{name}/course/{course}/comments", web::get().to(professor_course_comments))
+        .route("/r0/{school}/autocomplete/professor", web::get().to(autocomplete_professor))
+        .route("/r0/{school}/autocomplete/course", web::get().to(autocomplete_course))
+        .route("/r0/{school}/stats", web::get().to(school_stats))
+        .route("/r0/{school}/reports/grade_vs_quality", web::get().to(grade_vs_quality))
+        .route("/r0/{school}/tags/{tag}/professors", web::get().to(tag_professors))
+        .route("/r1/{school}/professor/{name}/comments", web::get().to(r1_professor_comments))
+        .route("/r1/{school}/professor/{name}/course/{course}/comments", web::get().to(r1_professor_course_comments))
+        .route("/r1/{school}/autocomplete/professor", web::get().to(r1_autocomplete_professor))
+        .route("/r1/{school}/autocomplete/course", web::get().to(r1_autocomplete_course));
+}
+
+fn full_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        .route("/r0/professors/comments", web::post().to(batch_comments))
+        .route("/r0/schedule/suggest", web::post().to(schedule_suggest))
+        .route("/r0/dump/ratings", web::get().to(dump_ratings))
+        .route("/r0/accounts", web::post().to(create_account))
+        .route("/r0/me/favorites", web::get().to(get_favorites))
+        .route("/r0/me/favorites", web::put().to(put_favorites))
+        .route("/r0/{school}/dump/ratings", web::get().to(dump_ratings))
+        .route("/r0/{school}/professors/comments", web::post().to(batch_comments))
+        .route("/admin/audit", web::get().to(admin_audit))
+        .route("/admin/ratings/hidden", web::get().to(admin_hidden_ratings))
+        .route("/admin/ratings/{id}/hidden", web::put().to(admin_hide_rating))
+        .route("/admin/ratings/{id}/hidden", web::delete().to(admin_unhide_rating))
+        .route("/admin/keys", web::get().to(admin_keys))
+        .route("/admin/keys", web::post().to(admin_create_key))
+        .route("/admin/keys/{id}", web::delete().to(admin_revoke_key))
+        .route("/admin/mapping/{name}", web::put().to(admin_pin_mapping))
+        .route("/admin/{school}/mapping/{name}", web::put().to(admin_pin_mapping))
+        .route("/admin/merge/{rmp_id}", web::put().to(admin_merge_professors))
+        .route("/admin/{school}/merge/{rmp_id}", web::put().to(admin_merge_professors))
+        .route("/admin/upstream", web::get().to(admin_upstream))
+        .route("/internal/rmp_graphql_token", web::get().to(rmp_graphql_token))
+        .route("/internal/metrics", web::get().to(internal_metrics))
+        .route("/admin/jobs", web::get().to(admin_jobs));
+
+    #[cfg(feature = "scheduler")]
+    cfg
+        .route("/admin/jobs/{name}/run", web::post().to(admin_run_job))
+        .route("/r0/watch/section", web::post().to(watch_section));
+}
+
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("self-test") {
+        return selftest::run(args.next()).await;
+    }
+
+    let readiness = std::sync::Arc::new(startup::Readiness::new());
+
+    readiness.enter(startup::Phase::Config);
+    let config = config::AppConfig::load().map_err(|e| readiness.fail(e))?;
+    let warmup = config.warmup.entries().map_err(|e| readiness.fail(e))?;
+
+    readiness.enter(startup::Phase::Store);
+    let store = match &config.storage.url {
+        Some(url) => Some(store::connect(url.as_str()).await
+            .map_err(|e| readiness.fail(e))?),
+        None => None,
+    };
+
+    let hidden = std::sync::Arc::new(moderation::HiddenRatings::new(store.clone()));
+    let budget = std::sync::Arc::new(budget::UpstreamBudget::new(config.rmp.daily_budget));
+    let clock: std::sync::Arc<dyn clock::Clock> = std::sync::Arc::new(clock::SystemClock);
+
+    let mut schools = HashMap::new();
+    for school in config.schools.enabled.iter() {
+        let mut builder = rmp::ControllerBuilder::from_config(&config)
+            .school_id(school.rmp_id)
+            .hidden(hidden.clone())
+            .budget(budget.clone())
+            .clock(clock.clone());
+
+        if let Some(store) = &store {
+            builder = builder.store(store.clone());
+        }
+
+        let rmp_controller = builder.build()
+            .map_err(|e| readiness.fail(format!("{} controller: {}", school.slug, e)))?;
+
+        schools.insert(school.slug.clone(), rmp_controller);
+    }
+
+    readiness.enter(startup::Phase::Snapshots);
+    match hidden.load().await {
+        Ok(count) => println!("main: loaded {} hidden ratings", count),
+        Err(e) => readiness.degrade("hidden ratings", e),
+    }
+
+    if store.is_some() {
+        for (slug, rmp_controller) in schools.iter() {
+            match rmp_controller.load_from_store().await {
+                Ok(count) => println!("main: loaded {} professors for {} from store", count, slug),
+                Err(e) => readiness.degrade(format!("{} professors", slug).as_str(), e),
+            }
+        }
+    }
+
+    let crawl_pace = config.shedding.pace(std::time::Duration::from_millis(config.crawl.pace_ms));
+    if config.shedding.enabled {
+        println!("main: load shedding enabled, serving cached data only and refreshing every {}ms", crawl_pace.as_millis());
+    }
+
+    let catalog = ucsb::Catalog::from_env();
+    let quarter = std::env::var("UCSB_QUARTER").unwrap_or_else(|_| "20241".to_owned());
+    #[cfg(feature = "scheduler")]
+    let watchlist = std::sync::Arc::new(watch::Watchlist::new(catalog.clone(), quarter.clone(), mail::Mailer::new(&config.mail)));
+
     let app_state = web::Data::new(AppState {
-        rmp_controller: rmp::Controller::new(),
+        schools,
+        default_school: config.schools.default.clone(),
+        stale_fallback: config.stale_fallback.clone(),
+        mode: config.mode,
+        shedding: config.shedding.clone(),
+        versions: config.versions.clone(),
+        proxy: config.proxy.clone(),
+        auth: auth::Verifier::new(&config.auth, clock.clone()),
+        limits: limit::ConcurrencyLimits::new(match config.mode {
+            config::Mode::Public => config.limits.capped(config.public.concurrency),
+            config::Mode::Full => config.limits.clone(),
+        }),
+        prereqs: tokio::sync::RwLock::new(prereq::PrereqGraph::new()),
+        sections: tokio::sync::RwLock::new(schedule::SectionIndex::new()),
+        catalog,
+        quarter,
+        ge: tokio::sync::RwLock::new(HashMap::new()),
+        accounts: account::Accounts::new(&config.accounts, store.clone(), clock.clone()),
+        quotas: quota::Quotas::new(&config.quotas, store.clone(), clock.clone()),
+        audit: audit::AuditLog::new(store.clone()),
+        hidden,
+        budget,
+        clock,
+        readiness: readiness.clone(),
+        supervisor: std::sync::Arc::new(supervisor::Supervisor::new()),
+        #[cfg(feature = "scheduler")]
+        scheduler: scheduler::Scheduler::new(&config.scheduler, crawl_pace, watchlist.clone()),
+        #[cfg(feature = "scheduler")]
+        watchlist,
     });
 
-    HttpServer::new(move || {
-        App::new()
+    if store.is_some() {
+        match app_state.quotas.load().await {
+            Ok(count) => println!("main: loaded {} API keys from store", count),
+            Err(e) => readiness.degrade("API keys", e),
+        }
+    }
+
+    readiness.enter(startup::Phase::Tasks);
+    let supervisor = app_state.supervisor.clone();
+
+    if app_state.catalog.is_some() {
+        let state = app_state.clone();
+        supervisor.spawn("index_catalog_courses", move || index_catalog_courses(state.clone()));
+    }
+
+    if !warmup.is_empty() {
+        let state = app_state.clone();
+        let concurrency = config.warmup.concurrency;
+        supervisor.spawn("warm_cache", move || warm_cache(warmup.clone(), concurrency, state.clone()));
+    }
+
+    if config.crawl.enabled {
+        let state = app_state.clone();
+        supervisor.spawn("crawl_school", move || crawl_school(crawl_pace, state.clone()));
+    }
+
+    #[cfg(feature = "scheduler")]
+    for job in app_state.scheduler.jobs() {
+        let state = app_state.clone();
+        let job = job.clone();
+        supervisor.spawn(format!("scheduler:{:?}", job.job).as_str(), move || run_scheduled_job(job.clone(), state.clone()));
+    }
+
+    readiness.enter(startup::Phase::Listen);
+    let mode = config.mode;
+    let precision = config.format.precision;
+    if mode == config::Mode::Public {
+        println!("main: public mode, serving read-only routes with concurrency capped at {}", config.public.concurrency);
+    }
+
+    let server = HttpServer::new(move || {
+        let guard = app_state.clone();
+
+        let app = App::new()
             .app_data(app_state.clone())
-            .route("/version", web::get().to(version))
-            .route("/r0/professor/{name}/overview", web::get().to(professor_overview))
-            .route("/r0/professor/{name}/comments", web::get().to(professor_comments))
-            .route("/r0/professor/{name}/course/{course}/comments", web::get().to(professor_course_comments))
-            .route("/internal/rmp_graphql_token", web::get().to(rmp_graphql_token))
+            .wrap_fn(move |req, srv| {
+                let response = srv.call(req);
+
+                async move {
+                    let response = response.await?;
+
+                    Ok(match precision {
+                        Some(precision) => response.map_body(|head, body| round_json(head, body, precision)),
+                        None => response,
+                    })
+                }
+            })
+            .wrap(middleware::Compress::default())
+            .wrap_fn(move |req, srv| {
+                let state = guard.clone();
+                let header = req.headers().get("Authorization").and_then(|h| h.to_str().ok());
+                let key = req.headers().get("X-API-Key").and_then(|h| h.to_str().ok());
+                let deprecation = guard.versions.headers(req.path());
+                let client = guard.proxy.client_ip(req.peer_addr().map(|a| a.ip()), req.headers());
+
+                let admitted = guard.auth.check(req.path(), header)
+                    .and_then(|_| guard.quotas.consume(req.path(), key, client))
+                    .map_err(|e| {
+                        println!("wrap_fn: rejected {} from {} error {}", req.path(), client.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_owned()), e);
+                        e
+                    })
+                    .map(|usage| (usage, srv.call(req)));
+
+                async move {
+                    let (usage, response) = admitted?;
+                    let mut response = response.await?;
+
+                    for (name, value) in deprecation {
+                        if let Ok(value) = HeaderValue::from_str(value.as_str()) {
+                            response.headers_mut().insert(HeaderName::from_static(name), value);
+                        }
+                    }
+
+                    if let Some((usage, updates)) = usage {
+                        let headers = response.headers_mut();
+                        headers.insert(HeaderName::from_static("x-ratelimit-limit"), HeaderValue::from(usage.limit));
+                        headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(usage.remaining));
+
+                        actix_rt::spawn(async move { state.quotas.persist(updates).await });
+                    }
+
+                    Ok(response)
+                }
+            })
+            .configure(read_routes);
+
+        match mode {
+            config::Mode::Full => app.configure(full_routes),
+            config::Mode::Public => app,
+        }
     })
-        .bind("localhost:8000")?
-        .run()
-        .await
+        .bind("localhost:8000")
+        .map_err(|e| readiness.fail(e))?;
+
+    readiness.enter(startup::Phase::Ready);
+    server.run().await
 }
@@ -0,0 +1,240 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::clock::Clock;
+use crate::error::Error;
+use crate::outbound::{HttpBackend, OutboundRequest, UpstreamResponse};
+use crate::rmp::{GraphQLConfig, SolrConfig};
+
+const BASE: &str = "http://rmp.mock";
+
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    pub body: String,
+    pub retry_after: Option<u64>,
+}
+
+impl Reply {
+    pub fn ok(body: serde_json::Value) -> Self {
+        Reply { status: 200, body: body.to_string(), retry_after: None }
+    }
+
+    pub fn raw(status: u16, body: &str) -> Self {
+        Reply { status, body: body.to_owned(), retry_after: None }
+    }
+
+    pub fn rate_limited(retry_after: u64) -> Self {
+        Reply { status: 429, body: String::new(), retry_after: Some(retry_after) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub authorization: Option<String>,
+    pub body: serde_json::Value,
+}
+
+#[derive(Default)]
+struct Route {
+    replies: VecDeque<Reply>,
+    requests: Vec<Request>,
+}
+
+impl Route {
+    fn next(&mut self, request: Request) -> Reply {
+        self.requests.push(request);
+
+        match self.replies.len() {
+            0 => Reply::raw(404, ""),
+            1 => self.replies[0].clone(),
+            _ => self.replies.pop_front().unwrap(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Routes {
+    solr: Mutex<Route>,
+    token: Mutex<Route>,
+    graphql: Mutex<Route>,
+}
+
+impl Routes {
+    fn route(&self, url: &str) -> Option<&Mutex<Route>> {
+        let path = reqwest::Url::parse(url).ok()?.path().to_owned();
+
+        match path.as_str() {
+            "/solr" => Some(&self.solr),
+            "/token" => Some(&self.token),
+            "/graphql" => Some(&self.graphql),
+            _ => None,
+        }
+    }
+
+    fn respond(&self, url: &str, request: Request) -> UpstreamResponse {
+        let reply = match self.route(url) {
+            Some(route) => route.lock().unwrap().next(request),
+            None => Reply::raw(404, ""),
+        };
+
+        UpstreamResponse {
+            status: reqwest::StatusCode::from_u16(reply.status).unwrap(),
+            retry_after: reply.retry_after,
+            body: reply.body.into_bytes(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MockRmp {
+    routes: Arc<Routes>,
+}
+
+#[async_trait]
+impl HttpBackend for MockRmp {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error> {
+        Ok(self.routes.respond(request.url.as_str(), Request {
+            authorization: request.authorization.clone(),
+            body: request.body.clone().unwrap_or(serde_json::Value::Null),
+        }))
+    }
+}
+
+impl MockRmp {
+    pub fn new() -> Self {
+        MockRmp::default()
+    }
+
+    pub fn solr_config(&self) -> SolrConfig {
+        SolrConfig {
+            url: format!("{}/solr", BASE),
+            ..SolrConfig::default()
+        }
+    }
+
+    pub fn graphql_config(&self, page_size: u32) -> GraphQLConfig {
+        GraphQLConfig {
+            url: format!("{}/graphql", BASE),
+            token_url: format!("{}/token", BASE),
+            page_size,
+            ..GraphQLConfig::default()
+        }
+    }
+
+    pub fn solr(&self, replies: Vec<Reply>) {
+        self.routes.solr.lock().unwrap().replies = replies.into();
+    }
+
+    pub fn token(&self, replies: Vec<Reply>) {
+        self.routes.token.lock().unwrap().replies = replies.into();
+    }
+
+    pub fn graphql(&self, replies: Vec<Reply>) {
+        self.routes.graphql.lock().unwrap().replies = replies.into();
+    }
+
+    pub fn solr_requests(&self) -> usize {
+        self.routes.solr.lock().unwrap().requests.len()
+    }
+
+    pub fn token_requests(&self) -> usize {
+        self.routes.token.lock().unwrap().requests.len()
+    }
+
+    pub fn graphql_requests(&self) -> Vec<Request> {
+        self.routes.graphql.lock().unwrap().requests.clone()
+    }
+}
+
+pub fn token_page(token: &str) -> Reply {
+    Reply::raw(200, format!("<script>window.process.env = {{\"REACT_APP_GRAPHQL_AUTH\":\"{}\"}};</script>", token).as_str())
+}
+
+pub fn teacher(id: u32, first: &str, last: &str, department: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": format!("teacher:{}", id),
+        "averageratingscore_rf": 4.0,
+        "teacherfirstname_t": first,
+        "teacherlastname_t": last,
+        "teacherfullname_s": format!("{} {}", first, last),
+        "teacherdepartment_s": department,
+        "total_number_of_ratings_i": 20,
+    })
+}
+
+pub fn solr_teachers(teachers: Vec<serde_json::Value>) -> Reply {
+    Reply::ok(serde_json::json!({
+        "grouped": {
+            "content_type_s": {
+                "groups": [
+                    { "groupValue": "TEACHER", "doclist": { "docs": teachers } },
+                ],
+            },
+        },
+    }))
+}
+
+pub fn rating(legacy_id: u32, class: &str, quality: u32) -> serde_json::Value {
+    serde_json::json!({
+        "node": {
+            "attendanceMandatory": "non mandatory",
+            "clarityRating": quality,
+            "class": class,
+            "comment": format!("rating {}", legacy_id),
+            "courseType": null,
+            "date": "2023-10-01 12:00:00 +0000 UTC",
+            "difficultyRating": 3,
+            "grade": "A",
+            "helpfulRating": quality,
+            "legacyId": legacy_id,
+            "ratingTags": "Clear grading--Tough grader",
+            "textbookUse": null,
+            "thumbs": [],
+            "thumbsDownTotal": 0,
+            "thumbsUpTotal": 1,
+            "wouldTakeAgain": 1,
+        },
+    })
+}
+
+pub fn dated(mut rating: serde_json::Value, date: &str) -> serde_json::Value {
+    rating["node"]["date"] = format!("{} 12:00:00 +0000 UTC", date).into();
+    rating
+}
+
+pub fn ratings_page(edges: Vec<serde_json::Value>, next: Option<&str>) -> Reply {
+    Reply::ok(serde_json::json!({
+        "data": {
+            "node": {
+                "ratings": {
+                    "edges": edges,
+                    "pageInfo": { "hasNextPage": next.is_some(), "endCursor": next },
+                },
+            },
+        },
+    }))
+}
+
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    pub fn at(now: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(MockClock { now: Mutex::new(now) })
+    }
+
+    pub fn advance(&self, seconds: i64) {
+        let mut now = self.now.lock().unwrap();
+        *now = *now + chrono::Duration::seconds(seconds);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
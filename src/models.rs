@@ -0,0 +1,246 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfessorResponse {
+    #[serde(flatten)]
+    pub ids: RmpIds,
+
+    pub quality: Option<f32>,
+    pub quality_yr: Option<f32>,
+    pub num_ratings: Option<u32>,
+    #[serde(default)]
+    pub insufficient_data: bool,
+
+    pub first_name: String,
+    pub last_name: String,
+    pub full_name: String,
+
+    pub department: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rmp_department: Option<String>,
+
+    pub department_percentile: Option<f32>,
+    pub school_percentile: Option<f32>,
+
+    pub stale: bool,
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<DataSource>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<TeacherMetadata>,
+
+    #[serde(rename = "match", default, skip_serializing_if = "Option::is_none")]
+    pub name_match: Option<NameMatch>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TeacherMetadata {
+    pub locked: bool,
+    pub school: Option<String>,
+    pub num_ratings: Option<u32>,
+    pub avg_difficulty: Option<f32>,
+    pub would_take_again_percent: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RmpIds {
+    pub rmp_id: u32,
+    #[serde(default)]
+    pub rmp_node_id: String,
+    #[serde(default)]
+    pub rmp_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameMatch {
+    pub query: String,
+    pub resolved_name: String,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfessorMatch {
+    #[serde(flatten)]
+    pub professor: ProfessorResponse,
+
+    pub rmp_quality: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_courses: Vec<CourseCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CourseCount {
+    pub course: String,
+    pub num_ratings: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepartmentGroup {
+    pub department: String,
+    pub professors: Vec<ProfessorMatch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseType {
+    InPerson,
+    Online,
+    Hybrid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub class: String,
+
+    pub comment: String,
+
+    #[serde(default)]
+    pub word_count: u32,
+    #[serde(default)]
+    pub reading_seconds: u32,
+
+    pub grade: String,
+
+    pub attendance_mandatory: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub course_type: Option<CourseType>,
+    #[serde(default)]
+    pub online: Option<bool>,
+
+    pub quality: f32,
+    pub difficulty: f32,
+
+    pub date: chrono::DateTime<chrono::Utc>,
+
+    pub quarter: String,
+    pub quarter_code: String,
+    #[serde(default)]
+    pub terms_ago: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfessorSuggestion {
+    #[serde(flatten)]
+    pub ids: RmpIds,
+
+    pub full_name: String,
+
+    pub department: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfessorCard {
+    pub title: String,
+    pub subtitle: String,
+
+    pub score: Option<f32>,
+    pub score_label: String,
+    pub difficulty: Option<f32>,
+    pub num_ratings: usize,
+    pub top_tags: Vec<String>,
+
+    pub url: String,
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShieldsBadge {
+    pub schema_version: u8,
+    pub label: String,
+    pub message: String,
+    pub color: String,
+}
+
+impl ShieldsBadge {
+    pub fn quality(quality: Option<f32>) -> Self {
+        let (message, color) = match quality.filter(|q| q.is_finite()).map(|q| (q * 10.0).round() / 10.0) {
+            Some(q) => (format!("{:.1}/5", q), match q {
+                q if q >= 4.0 => "brightgreen",
+                q if q >= 3.0 => "yellow",
+                q if q >= 2.0 => "orange",
+                _ => "red",
+            }),
+            None => ("no ratings".to_owned(), "lightgrey"),
+        };
+
+        ShieldsBadge {
+            schema_version: 1,
+            label: "RMP quality".to_owned(),
+            message,
+            color: color.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfessorComments {
+    pub professor: ProfessorSuggestion,
+
+    #[serde(rename = "match")]
+    pub name_match: NameMatch,
+
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Meta {
+    pub count: usize,
+    pub page: usize,
+    pub total: usize,
+    pub next_cursor: Option<String>,
+    pub cache_age: i64,
+    pub source: String,
+    pub stale: bool,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub sources: Vec<DataSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataSource {
+    pub kind: String,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub data: T,
+    pub meta: Meta,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn quality_badges_follow_the_shields_schema() {
+        assert_eq!(serde_json::to_value(ShieldsBadge::quality(Some(4.2))).unwrap(), serde_json::json!({
+            "schemaVersion": 1,
+            "label": "RMP quality",
+            "message": "4.2/5",
+            "color": "brightgreen",
+        }));
+        assert_eq!(ShieldsBadge::quality(Some(2.5)).color, "orange");
+        assert_eq!(ShieldsBadge::quality(None).color, "lightgrey");
+    }
+
+    #[test]
+    fn badge_colors_follow_the_displayed_score() {
+        let badge = |q| {
+            let badge = ShieldsBadge::quality(Some(q));
+            (badge.message, badge.color)
+        };
+
+        assert_eq!(badge(3.96), ("4.0/5".to_owned(), "brightgreen".to_owned()));
+        assert_eq!(badge(2.94), ("2.9/5".to_owned(), "orange".to_owned()));
+        assert_eq!(badge(4.25), ("4.3/5".to_owned(), "brightgreen".to_owned()));
+        assert_eq!(badge(1.0), ("1.0/5".to_owned(), "red".to_owned()));
+        assert_eq!(ShieldsBadge::quality(Some(f32::NAN)).message, "no ratings");
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::error::Error;
+use crate::rmp::NormalizedRating;
+use crate::store::Store;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HiddenRating {
+    pub rating_id: u32,
+    pub reason: String,
+    pub hidden_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HideRequest {
+    pub reason: String,
+}
+
+#[derive(Default)]
+pub struct HiddenRatings {
+    store: Option<Arc<dyn Store>>,
+    hidden: RwLock<HashMap<u32, HiddenRating>>,
+}
+
+impl HiddenRatings {
+    pub fn new(store: Option<Arc<dyn Store>>) -> Self {
+        HiddenRatings {
+            store,
+            hidden: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn load(&self) -> Result<usize, Error> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(0),
+        };
+
+        let hidden = store.load_hidden().await?;
+
+        let mut map = self.hidden.write().unwrap();
+        *map = hidden.into_iter().map(|h| (h.rating_id, h)).collect();
+
+        Ok(map.len())
+    }
+
+    pub fn list(&self) -> Vec<HiddenRating> {
+        let mut hidden: Vec<HiddenRating> = self.hidden.read().unwrap().values().cloned().collect();
+        hidden.sort_by_key(|h| std::cmp::Reverse(h.hidden_at));
+
+        hidden
+    }
+
+    pub fn ids(&self) -> Vec<u32> {
+        self.hidden.read().unwrap().keys().cloned().collect()
+    }
+
+    pub async fn hide(&self, rating_id: u32, reason: String) -> Result<HiddenRating, Error> {
+        let hidden = HiddenRating {
+            rating_id,
+            reason,
+            hidden_at: Utc::now(),
+        };
+
+        if let Some(store) = &self.store {
+            store.save_hidden(&hidden).await?;
+        }
+        self.hidden.write().unwrap().insert(rating_id, hidden.clone());
+
+        Ok(hidden)
+    }
+
+    pub async fn unhide(&self, rating_id: u32) -> Result<HiddenRating, Error> {
+        let hidden = self.hidden.read().unwrap().get(&rating_id).cloned().ok_or(Error::NotFound)?;
+
+        if let Some(store) = &self.store {
+            store.delete_hidden(rating_id).await?;
+        }
+        self.hidden.write().unwrap().remove(&rating_id);
+
+        Ok(hidden)
+    }
+
+    pub fn visible(&self, ratings: Vec<NormalizedRating>) -> Vec<NormalizedRating> {
+        let hidden = self.hidden.read().unwrap();
+
+        if hidden.is_empty() {
+            return ratings;
+        }
+
+        ratings.into_iter()
+            .filter(|r| r.legacy_id.map(|id| !hidden.contains_key(&id)).unwrap_or(true))
+            .collect()
+    }
+}
@@ -0,0 +1,134 @@
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+const STOP_WORDS: &[&str] = &["dr", "prof", "professor", "mr", "mrs", "ms", "mx", "phd"];
+const MIN_NAME_CHARS: usize = 2;
+
+pub fn fold(s: &str) -> String {
+    s.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+pub fn canonical(name: &str) -> String {
+    let name = fold(name);
+
+    let name = match name.find(',') {
+        Some(i) => format!("{} {}", &name[i + 1..], &name[..i]),
+        None => name,
+    };
+
+    let cleaned: String = name.chars()
+        .filter(|c| *c != '\'' && *c != '\u{2019}')
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+
+    let tokens: Vec<&str> = cleaned.split_whitespace()
+        .filter(|t| !STOP_WORDS.contains(t))
+        .collect();
+    let last = tokens.len().saturating_sub(1);
+
+    tokens.iter()
+        .enumerate()
+        .filter(|(i, t)| *i == 0 || *i == last || t.chars().count() > 1)
+        .map(|(_, t)| *t)
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+pub fn compact(name: &str) -> String {
+    fold(name).chars().filter(|c| c.is_alphanumeric()).collect()
+}
+
+pub fn is_trivial(name: &str) -> bool {
+    canonical(name).chars().filter(|c| c.is_alphanumeric()).count() < MIN_NAME_CHARS
+}
+
+pub fn confidence(query: &str, resolved: &str) -> f32 {
+    let query = canonical(query);
+    let resolved = canonical(resolved);
+
+    let query: Vec<&str> = query.split_whitespace().collect();
+    let resolved: Vec<&str> = resolved.split_whitespace().collect();
+
+    if query.is_empty() || resolved.is_empty() {
+        return 0.0;
+    }
+
+    let score: f32 = query.iter()
+        .map(|q| resolved.iter().map(|r| token_similarity(q, r)).fold(0.0, f32::max))
+        .sum();
+
+    score / query.len().max(resolved.len()) as f32
+}
+
+fn token_similarity(query: &str, candidate: &str) -> f32 {
+    if query == candidate {
+        1.0
+    } else if candidate.starts_with(query) {
+        0.5 + 0.5 * query.chars().count() as f32 / candidate.chars().count() as f32
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn diacritics_fold_to_plain_lowercase() {
+        assert_eq!(fold("José Ñúñez"), "jose nunez");
+        assert_eq!(fold("ＺＯË"), "zoe");
+        assert_eq!(canonical("Zoë Böhm"), canonical("zoe bohm"));
+        assert_eq!(compact("Müller-Lüdenscheidt"), "mullerludenscheidt");
+    }
+
+    #[test]
+    fn last_first_is_reordered() {
+        assert_eq!(canonical("Núñez, José"), "jose nunez");
+        assert_eq!(canonical("Doe,Jane"), "jane doe");
+        assert_eq!(canonical("Doe, Jane, PhD"), canonical("Jane Doe"));
+    }
+
+    #[test]
+    fn middle_initials_and_titles_are_dropped() {
+        assert_eq!(canonical("John Q. Public"), "john public");
+        assert_eq!(canonical("Public, John Q."), "john public");
+        assert_eq!(canonical("Dr. J. R. R. Tolkien"), "j tolkien");
+        assert_eq!(canonical("Prof. Jane A Doe"), "jane doe");
+        assert_eq!(canonical("J. Smith"), "j smith");
+    }
+
+    #[test]
+    fn hyphenated_and_double_surnames_keep_every_part() {
+        assert_eq!(canonical("Mary-Jane O'Neil"), "mary jane oneil");
+        assert_eq!(canonical("O\u{2019}Neil, Mary-Jane"), "mary jane oneil");
+        assert_eq!(canonical("García Márquez, Gabriel"), "gabriel garcia marquez");
+        assert_eq!(canonical("Gabriel García-Márquez"), canonical("Gabriel Garcia Marquez"));
+        assert_eq!(compact("Mary-Jane O'Neil"), "maryjaneoneil");
+    }
+
+    #[test]
+    fn trivial_names_have_too_few_letters() {
+        assert!(is_trivial(""));
+        assert!(is_trivial("Dr. X"));
+        assert!(is_trivial(" - , "));
+        assert!(!is_trivial("Li"));
+    }
+
+    #[test]
+    fn confidence_rewards_exact_and_prefix_tokens() {
+        assert!(close(confidence("Núñez, José", "jose nunez"), 1.0));
+        assert!(close(confidence("Smith", "John Smith"), 0.5));
+        assert!(close(confidence("jo smi", "John Smith"), 0.775));
+        assert!(close(confidence("Garcia-Marquez", "Gabriel García Márquez"), 2.0 / 3.0));
+        assert!(close(confidence("Jane Doe", "John Smith"), 0.0));
+        assert!(close(confidence("Dr.", "John Smith"), 0.0));
+    }
+}
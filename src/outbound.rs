@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::TryFutureExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::budget::{Upstream, UpstreamBudget};
+use crate::clock::Clock;
+use crate::error::Error;
+
+pub const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
+const MAX_RESPONSE_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutboundConfig {
+    pub retries: u32,
+    pub retry_backoff_ms: u64,
+    pub record_path: Option<String>,
+}
+
+impl Default for OutboundConfig {
+    fn default() -> Self {
+        OutboundConfig {
+            retries: 2,
+            retry_backoff_ms: 250,
+            record_path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboundRequest {
+    pub upstream: Upstream,
+    pub url: String,
+    pub authorization: Option<String>,
+    pub body: Option<serde_json::Value>,
+    pub what: &'static str,
+}
+
+impl OutboundRequest {
+    pub fn get(upstream: Upstream, url: &str, what: &'static str) -> Self {
+        OutboundRequest { upstream, url: url.to_owned(), authorization: None, body: None, what }
+    }
+
+    pub fn post(upstream: Upstream, url: &str, authorization: String, body: serde_json::Value, what: &'static str) -> Self {
+        OutboundRequest { upstream, url: url.to_owned(), authorization: Some(authorization), body: Some(body), what }
+    }
+}
+
+pub struct UpstreamResponse {
+    pub status: reqwest::StatusCode,
+    pub retry_after: Option<u64>,
+    pub body: Vec<u8>,
+}
+
+#[async_trait]
+pub trait HttpBackend: Send + Sync {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error>;
+}
+
+pub struct ReqwestBackend {
+    client: reqwest::Client,
+}
+
+impl ReqwestBackend {
+    pub fn new(timeout: std::time::Duration) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| Error::Config(e.to_string()))?;
+
+        Ok(ReqwestBackend { client })
+    }
+}
+
+#[async_trait]
+impl HttpBackend for ReqwestBackend {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error> {
+        let mut builder = match &request.body {
+            Some(body) => self.client.post(request.url.as_str()).json(body),
+            None => self.client.get(request.url.as_str()),
+        };
+        if let Some(authorization) = &request.authorization {
+            builder = builder.header(reqwest::header::AUTHORIZATION, authorization.as_str());
+        }
+
+        let resp = builder.send()
+            .map_err(|e| {
+                println!("{}: error {}", request.what, e);
+                Error::Rmp
+            }).await?;
+
+        read_response(resp, request.what).await
+    }
+}
+
+async fn read_response(resp: reqwest::Response, what: &str) -> Result<UpstreamResponse, Error> {
+    let status = resp.status();
+    let retry_after = resp.headers().get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    Ok(UpstreamResponse {
+        status,
+        retry_after,
+        body: read_capped(resp, what).await?,
+    })
+}
+
+async fn read_capped(mut resp: reqwest::Response, what: &str) -> Result<Vec<u8>, Error> {
+    if resp.content_length().map(|l| l as usize > MAX_RESPONSE_BYTES).unwrap_or(false) {
+        println!("{}: response of {:?} bytes exceeds limit", what, resp.content_length());
+        return Err(Error::RmpTooLarge);
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = resp.chunk().await.map_err(|e| {
+        println!("{}: error {}", what, e);
+        Error::Rmp
+    })? {
+        if body.len() + chunk.len() > MAX_RESPONSE_BYTES {
+            println!("{}: response exceeds {} bytes", what, MAX_RESPONSE_BYTES);
+            return Err(Error::RmpTooLarge);
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+pub fn chain(base: Arc<dyn HttpBackend>, config: &OutboundConfig, budget: Arc<UpstreamBudget>, clock: Arc<dyn Clock>) -> Arc<dyn HttpBackend> {
+    let mut backend = base;
+
+    if let Some(path) = &config.record_path {
+        backend = Arc::new(Record { inner: backend, path: path.clone(), lock: Mutex::new(()) });
+    }
+    backend = Arc::new(Meter { inner: backend, budget, clock: clock.clone() });
+    if config.retries > 0 {
+        backend = Arc::new(Retry {
+            inner: backend,
+            retries: config.retries,
+            backoff: std::time::Duration::from_millis(config.retry_backoff_ms),
+        });
+    }
+
+    Arc::new(RateLimit { inner: backend, clock, blocked_until: Mutex::new(HashMap::new()) })
+}
+
+pub struct RateLimit {
+    inner: Arc<dyn HttpBackend>,
+    clock: Arc<dyn Clock>,
+    blocked_until: Mutex<HashMap<Upstream, DateTime<Utc>>>,
+}
+
+#[async_trait]
+impl HttpBackend for RateLimit {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error> {
+        let now = self.clock.now();
+        let blocked = self.blocked_until.lock().unwrap().get(&request.upstream)
+            .map(|until| (*until - now).num_seconds())
+            .filter(|remaining| *remaining > 0);
+        if let Some(remaining) = blocked {
+            println!("{}: upstream rate limited for another {}s", request.what, remaining);
+            return Err(Error::Overloaded(remaining as u64));
+        }
+
+        let resp = self.inner.send(request).await?;
+
+        if resp.status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = resp.retry_after.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+            self.blocked_until.lock().unwrap()
+                .insert(request.upstream, now + chrono::Duration::seconds(retry_after as i64));
+        }
+
+        Ok(resp)
+    }
+}
+
+pub struct Retry {
+    inner: Arc<dyn HttpBackend>,
+    retries: u32,
+    backoff: std::time::Duration,
+}
+
+#[async_trait]
+impl HttpBackend for Retry {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error> {
+        let mut attempt = 0;
+
+        loop {
+            let result = self.inner.send(request).await;
+            let retryable = match &result {
+                Ok(resp) => resp.status.is_server_error(),
+                Err(e) => matches!(e, Error::Rmp),
+            };
+            if !retryable || attempt >= self.retries {
+                return result;
+            }
+
+            attempt += 1;
+            println!("{}: retrying ({}/{})", request.what, attempt, self.retries);
+            tokio::time::delay_for(self.backoff * 2u32.pow(attempt - 1)).await;
+        }
+    }
+}
+
+pub struct Meter {
+    inner: Arc<dyn HttpBackend>,
+    budget: Arc<UpstreamBudget>,
+    clock: Arc<dyn Clock>,
+}
+
+#[async_trait]
+impl HttpBackend for Meter {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error> {
+        self.budget.spend(request.upstream, self.clock.now())?;
+
+        self.inner.send(request).await
+    }
+}
+
+#[derive(Serialize)]
+struct Exchange<'a> {
+    upstream: Upstream,
+    url: &'a str,
+    request: Option<&'a serde_json::Value>,
+    status: u16,
+    body: String,
+}
+
+pub struct Record {
+    inner: Arc<dyn HttpBackend>,
+    path: String,
+    lock: Mutex<()>,
+}
+
+impl Record {
+    fn _append(&self, exchange: &Exchange) -> std::io::Result<()> {
+        let line = serde_json::to_string(exchange)?;
+        let _guard = self.lock.lock().unwrap();
+
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(self.path.as_str())?;
+        writeln!(file, "{}", line)
+    }
+}
+
+#[async_trait]
+impl HttpBackend for Record {
+    async fn send(&self, request: &OutboundRequest) -> Result<UpstreamResponse, Error> {
+        let resp = self.inner.send(request).await?;
+
+        let exchange = Exchange {
+            upstream: request.upstream,
+            url: request.url.as_str(),
+            request: request.body.as_ref(),
+            status: resp.status.as_u16(),
+            body: String::from_utf8_lossy(resp.body.as_slice()).into_owned(),
+        };
+        if let Err(e) = self._append(&exchange) {
+            println!("{}: failed to record exchange to {}: {}", request.what, self.path, e);
+        }
+
+        Ok(resp)
+    }
+}
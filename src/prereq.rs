@@ -0,0 +1,127 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::course::normalize_course;
+use crate::ucsb::ClassResponse;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Dependencies {
+    pub course: String,
+    pub direct: BTreeSet<String>,
+    pub transitive: BTreeSet<String>,
+}
+
+pub struct PrereqGraph {
+    prereqs: BTreeMap<String, BTreeSet<String>>,
+    unlocks: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl PrereqGraph {
+    pub fn new() -> Self {
+        PrereqGraph {
+            prereqs: BTreeMap::new(),
+            unlocks: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_classes(classes: &[ClassResponse]) -> Self {
+        let mut graph = Self::new();
+
+        for class in classes {
+            let course = match normalize_course(class.course_id.as_str()) {
+                Some(course) => course,
+                None => continue,
+            };
+
+            graph.prereqs.entry(course.clone()).or_default();
+
+            let description = match &class.description {
+                Some(description) => description,
+                None => continue,
+            };
+
+            for prereq in parse_prerequisites(description.as_str()) {
+                if prereq != course {
+                    graph.unlocks.entry(prereq.clone()).or_default().insert(course.clone());
+                    graph.prereqs.entry(course.clone()).or_default().insert(prereq);
+                }
+            }
+        }
+
+        graph
+    }
+
+    pub fn prereqs(&self, course: &str) -> Option<Dependencies> {
+        Self::_walk(&self.prereqs, course)
+    }
+
+    pub fn unlocks(&self, course: &str) -> Option<Dependencies> {
+        Self::_walk(&self.unlocks, course)
+            .or_else(|| self.prereqs(course).map(|d| Dependencies {
+                direct: BTreeSet::new(),
+                transitive: BTreeSet::new(),
+                ..d
+            }))
+    }
+
+    fn _walk(edges: &BTreeMap<String, BTreeSet<String>>, course: &str) -> Option<Dependencies> {
+        let course = normalize_course(course)?;
+        let direct = edges.get(course.as_str())?.clone();
+
+        let mut transitive = BTreeSet::new();
+        let mut queue: VecDeque<&String> = direct.iter().collect();
+
+        while let Some(next) = queue.pop_front() {
+            if *next == course || !transitive.insert(next.clone()) {
+                continue;
+            }
+
+            if let Some(more) = edges.get(next.as_str()) {
+                queue.extend(more.iter());
+            }
+        }
+
+        Some(Dependencies {
+            course,
+            direct,
+            transitive,
+        })
+    }
+}
+
+pub fn parse_prerequisites(description: &str) -> Vec<String> {
+    let lower = description.to_lowercase();
+    let start = match lower.find("prerequisite") {
+        Some(start) => start,
+        None => return Vec::new(),
+    };
+
+    let text = &description[start..];
+    let text = text.split_once(':').map(|(_, rest)| rest).unwrap_or(text);
+    let text = text.split(". ").next().unwrap_or(text);
+
+    let re = match Regex::new(r"\b([A-Z]{2,6}(?: [A-Z]{1,2}\b)?)?\s*\b(\d{1,3}[A-Z]{0,2})\b") {
+        Ok(re) => re,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut subject: Option<String> = None;
+    let mut courses = Vec::new();
+
+    for cap in re.captures_iter(text) {
+        if let Some(s) = cap.get(1) {
+            subject = Some(s.as_str().to_owned());
+        }
+
+        if let Some(subject) = &subject {
+            if let Some(course) = normalize_course(format!("{} {}", subject, &cap[2]).as_str()) {
+                if !courses.contains(&course) {
+                    courses.push(course);
+                }
+            }
+        }
+    }
+
+    courses
+}
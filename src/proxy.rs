@@ -0,0 +1,129 @@
+use actix_web::http::HeaderMap;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub trusted: Vec<IpAddr>,
+}
+
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim().trim_matches('"');
+
+    if let Some(rest) = hop.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+    if let Ok(ip) = hop.parse() {
+        return Some(ip);
+    }
+
+    hop.rsplit_once(':').and_then(|(ip, _)| ip.parse().ok())
+}
+
+fn forwarded_hops(headers: &HeaderMap) -> Vec<String> {
+    let forwarded: Vec<String> = headers.get_all("Forwarded")
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .filter_map(|element| element.split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+            .map(|(_, value)| value.to_owned()))
+        .collect();
+
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    headers.get_all("X-Forwarded-For")
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .map(|hop| hop.to_owned())
+        .collect()
+}
+
+impl ProxyConfig {
+    pub fn client_ip(&self, peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut client = peer?;
+
+        for hop in forwarded_hops(headers).iter().rev() {
+            if !self.trusted.contains(&client) {
+                break;
+            }
+
+            client = match parse_hop(hop.as_str()) {
+                Some(ip) => ip,
+                None => break,
+            };
+        }
+
+        Some(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(HeaderName::from_static(name), HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        s.parse().ok()
+    }
+
+    fn proxy() -> ProxyConfig {
+        ProxyConfig { trusted: vec!["127.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()] }
+    }
+
+    #[test]
+    fn forwarded_clients_resolve_through_trusted_proxies() {
+        let xff = headers(&[("x-forwarded-for", "6.6.6.6, 203.0.113.7, 10.0.0.2")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &xff), ip("203.0.113.7"));
+        assert_eq!(proxy().client_ip(ip("198.51.100.1"), &xff), ip("198.51.100.1"));
+
+        let forwarded = headers(&[("forwarded", "for=\"[2001:db8::17]:4711\";proto=https, for=10.0.0.2:80")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &forwarded), ip("2001:db8::17"));
+
+        let hidden = headers(&[("forwarded", "for=_hidden")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &hidden), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn repeated_headers_are_read_in_order() {
+        let split = headers(&[("x-forwarded-for", "203.0.113.7"), ("x-forwarded-for", "10.0.0.2")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &split), ip("203.0.113.7"));
+
+        let both = headers(&[("forwarded", "proto=https;For=198.51.100.9"), ("x-forwarded-for", "203.0.113.7")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &both), ip("198.51.100.9"));
+    }
+
+    #[test]
+    fn untrusted_or_missing_peers_are_never_rewritten() {
+        let xff = headers(&[("x-forwarded-for", "203.0.113.7")]);
+
+        assert_eq!(ProxyConfig::default().client_ip(ip("127.0.0.1"), &xff), ip("127.0.0.1"));
+        assert_eq!(proxy().client_ip(None, &xff), None);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &HeaderMap::new()), ip("127.0.0.1"));
+    }
+
+    #[test]
+    fn chains_of_trusted_hops_stop_at_the_first_unparseable_one() {
+        let all_trusted = headers(&[("x-forwarded-for", "10.0.0.2, 127.0.0.1")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &all_trusted), ip("10.0.0.2"));
+
+        let garbage = headers(&[("x-forwarded-for", "203.0.113.7, unknown, 10.0.0.2")]);
+        assert_eq!(proxy().client_ip(ip("127.0.0.1"), &garbage), ip("10.0.0.2"));
+
+        assert_eq!(parse_hop(" 192.0.2.1:8080 "), ip("192.0.2.1"));
+        assert_eq!(parse_hop("\"[::1]\""), ip("::1"));
+        assert_eq!(parse_hop("2001:db8::1"), ip("2001:db8::1"));
+        assert_eq!(parse_hop(""), None);
+    }
+}
@@ -0,0 +1,293 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Fall,
+}
+
+impl Season {
+    fn letter(&self) -> char {
+        match self {
+            Season::Winter => 'W',
+            Season::Spring => 'S',
+            Season::Summer => 'M',
+            Season::Fall => 'F',
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Season::Winter => "Winter",
+            Season::Spring => "Spring",
+            Season::Summer => "Summer",
+            Season::Fall => "Fall",
+        }
+    }
+
+    fn from_str_loose(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "w" | "winter" => Some(Season::Winter),
+            "s" | "spring" => Some(Season::Spring),
+            "m" | "summer" => Some(Season::Summer),
+            "f" | "fall" => Some(Season::Fall),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Session {
+    A,
+    B,
+    Full,
+    Extension,
+}
+
+impl Session {
+    fn letter(&self) -> char {
+        match self {
+            Session::A => 'A',
+            Session::B => 'B',
+            Session::Full => 'F',
+            Session::Extension => 'X',
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Session::A => "Summer Session A",
+            Session::B => "Summer Session B",
+            Session::Full => "Summer Full Session",
+            Session::Extension => "Extension",
+        }
+    }
+
+    fn from_letter(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'A' => Some(Session::A),
+            'B' => Some(Session::B),
+            'F' => Some(Session::Full),
+            'X' => Some(Session::Extension),
+            _ => None,
+        }
+    }
+
+    pub fn from_date(date: DateTime<Utc>) -> Option<Self> {
+        let day = (date.month(), date.day());
+
+        if day <= (6, 15) || day > (9, 20) {
+            None
+        } else if day <= (7, 31) {
+            Some(Session::A)
+        } else {
+            Some(Session::B)
+        }
+    }
+
+    pub fn matches(&self, session: &str) -> bool {
+        let session = session.trim();
+
+        match self {
+            Session::Full => session.is_empty() || session.eq_ignore_ascii_case("F") || session.eq_ignore_ascii_case("full"),
+            _ => session.chars().next().and_then(Self::from_letter) == Some(*self),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quarter {
+    pub year: i32,
+    pub season: Season,
+}
+
+const REMOTE_FROM: Quarter = Quarter { year: 2020, season: Season::Spring };
+const REMOTE_UNTIL: Quarter = Quarter { year: 2021, season: Season::Summer };
+
+impl Quarter {
+    pub fn from_date(date: DateTime<Utc>) -> Self {
+        let day = (date.month(), date.day());
+
+        let season = if day <= (3, 31) {
+            Season::Winter
+        } else if day <= (6, 15) {
+            Season::Spring
+        } else if day <= (9, 20) {
+            Season::Summer
+        } else {
+            Season::Fall
+        };
+
+        Quarter {
+            year: date.year(),
+            season,
+        }
+    }
+
+    fn index(&self) -> i32 {
+        self.year * 4 + self.season as i32
+    }
+
+    pub fn terms_since(&self, earlier: &Quarter) -> u32 {
+        (self.index() - earlier.index()).max(0) as u32
+    }
+
+    pub fn back(&self, n: u32) -> Self {
+        let index = self.index() - n as i32;
+        let season = match index.rem_euclid(4) {
+            0 => Season::Winter,
+            1 => Season::Spring,
+            2 => Season::Summer,
+            _ => Season::Fall,
+        };
+
+        Quarter {
+            year: index.div_euclid(4),
+            season,
+        }
+    }
+
+    pub fn code(&self) -> String {
+        format!("{}{:02}", self.season.letter(), self.year % 100)
+    }
+
+    pub fn is_remote(&self) -> bool {
+        *self >= REMOTE_FROM && *self <= REMOTE_UNTIL
+    }
+
+    pub fn ucsb_code(&self) -> String {
+        format!("{}{}", self.year, self.season as i32 + 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Term {
+    pub quarter: Quarter,
+    pub session: Option<Session>,
+}
+
+impl Term {
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.trim();
+        if !code.is_ascii() || code.len() < 5 || code.len() > 6 || !code[..5].chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let season = match &code[4..5] {
+            "1" => Season::Winter,
+            "2" => Season::Spring,
+            "3" => Season::Summer,
+            "4" => Season::Fall,
+            _ => return None,
+        };
+
+        let session = match code[5..].chars().next() {
+            Some(c) => Some(Session::from_letter(c)?),
+            None => None,
+        };
+
+        match (season, session) {
+            (Season::Summer, _) | (_, None) | (_, Some(Session::Extension)) => Some(Term {
+                quarter: Quarter { year: code[..4].parse().ok()?, season },
+                session,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn ucsb_code(&self) -> String {
+        self.quarter.ucsb_code()
+    }
+
+    pub fn code(&self) -> String {
+        match self.session {
+            Some(session) => format!("{}{}", self.ucsb_code(), session.letter()),
+            None => self.ucsb_code(),
+        }
+    }
+}
+
+pub fn valid_code(code: &str) -> bool {
+    Term::parse(code).is_some()
+}
+
+impl fmt::Display for Quarter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.season.name(), self.year)
+    }
+}
+
+impl Serialize for Quarter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+impl FromStr for Quarter {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some((season, year)) = s.split_once(' ') {
+            let season = Season::from_str_loose(season).ok_or(())?;
+            let year = year.trim().parse::<i32>().map_err(|_| ())?;
+
+            return Ok(Quarter { year, season });
+        }
+
+        let (season, year) = s.split_at(s.chars().next().map(|c| c.len_utf8()).unwrap_or(0));
+        let season = Season::from_str_loose(season).ok_or(())?;
+
+        let year = match year.len() {
+            2 => 2000 + year.parse::<i32>().map_err(|_| ())?,
+            4 => year.parse::<i32>().map_err(|_| ())?,
+            _ => return Err(()),
+        };
+
+        Ok(Quarter { year, season })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn on(month: u32, day: u32) -> Quarter {
+        Quarter::from_date(Utc.ymd(2024, month, day).and_hms(12, 0, 0))
+    }
+
+    #[test]
+    fn terms_count_back() {
+        let now = on(10, 1);
+
+        assert_eq!(now.terms_since(&now.back(5)), 5);
+        assert_eq!(now.terms_since(&on(2, 1)), 3);
+        assert_eq!(now.back(1).terms_since(&now), 0);
+    }
+
+    #[test]
+    fn seasons_switch_on_the_boundary_days() {
+        assert_eq!((on(3, 31).season, on(4, 1).season), (Season::Winter, Season::Spring));
+        assert_eq!((on(6, 15).season, on(6, 16).season), (Season::Spring, Season::Summer));
+        assert_eq!((on(9, 20).season, on(9, 21).season), (Season::Summer, Season::Fall));
+        assert_eq!(on(12, 31), Quarter { year: 2024, season: Season::Fall });
+    }
+
+    #[test]
+    fn counting_back_crosses_years() {
+        let winter = on(1, 15);
+
+        assert_eq!(winter.back(1), Quarter { year: 2023, season: Season::Fall });
+        assert_eq!(winter.back(4), Quarter { year: 2023, season: Season::Winter });
+        assert_eq!(winter.back(9).to_string(), "Fall 2021");
+        assert_eq!(winter.terms_since(&winter.back(9)), 9);
+        assert_eq!(winter.back(0), winter);
+    }
+}
@@ -0,0 +1,388 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::auth::{self, Scope};
+use crate::clock::Clock;
+use crate::error::Error;
+use crate::store::Store;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuotaConfig {
+    pub require_key: bool,
+    pub daily: u64,
+    pub monthly: u64,
+    pub anonymous_per_minute: u64,
+}
+
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        QuotaConfig {
+            require_key: false,
+            daily: 10_000,
+            monthly: 200_000,
+            anonymous_per_minute: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKey {
+    pub id: String,
+    pub name: String,
+    #[serde(skip)]
+    pub key_hash: String,
+    pub daily_quota: u64,
+    pub monthly_quota: u64,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeyRequest {
+    pub name: String,
+    pub daily_quota: Option<u64>,
+    pub monthly_quota: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    pub limit: u64,
+    pub remaining: u64,
+}
+
+pub type UsageCount = (String, String, u64);
+
+pub fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(len)
+        .collect()
+}
+
+fn periods(now: DateTime<Utc>) -> [String; 2] {
+    [now.format("d:%Y-%m-%d").to_string(), now.format("m:%Y-%m").to_string()]
+}
+
+struct Counters {
+    keys: HashMap<String, ApiKey>,
+    usage: HashMap<(String, String), u64>,
+    periods: [String; 2],
+    window: i64,
+    anonymous: HashMap<IpAddr, u64>,
+}
+
+pub struct Quotas {
+    config: QuotaConfig,
+    store: Option<Arc<dyn Store>>,
+    counters: Mutex<Counters>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Quotas {
+    pub fn new(config: &QuotaConfig, store: Option<Arc<dyn Store>>, clock: Arc<dyn Clock>) -> Self {
+        Quotas {
+            config: config.clone(),
+            store,
+            clock,
+            counters: Mutex::new(Counters {
+                keys: HashMap::new(),
+                usage: HashMap::new(),
+                periods: Default::default(),
+                window: 0,
+                anonymous: HashMap::new(),
+            }),
+        }
+    }
+
+    pub async fn load(&self) -> Result<usize, Error> {
+        let store = self._store()?;
+
+        let keys = store.load_api_keys().await?;
+        let periods = periods(self.clock.now());
+        let usage = store.load_usage(&periods).await?;
+
+        let mut counters = self.counters.lock().unwrap();
+        counters.keys = keys.into_iter().map(|k| (k.key_hash.clone(), k)).collect();
+        counters.usage = usage.into_iter().map(|(id, period, count)| ((id, period), count)).collect();
+        counters.periods = periods;
+
+        Ok(counters.keys.len())
+    }
+
+    pub fn keys(&self) -> Vec<ApiKey> {
+        let mut keys: Vec<ApiKey> = self.counters.lock().unwrap().keys.values().cloned().collect();
+        keys.sort_by_key(|k| k.created_at);
+
+        keys
+    }
+
+    pub async fn create(&self, request: KeyRequest) -> Result<CreatedKey, Error> {
+        let store = self._store()?;
+
+        let key = random_token(40);
+
+        let api_key = ApiKey {
+            id: random_token(12),
+            name: request.name,
+            key_hash: hash_key(key.as_str()),
+            daily_quota: request.daily_quota.unwrap_or(self.config.daily),
+            monthly_quota: request.monthly_quota.unwrap_or(self.config.monthly),
+            created_at: self.clock.now(),
+            revoked_at: None,
+        };
+
+        store.save_api_key(&api_key).await?;
+        self.counters.lock().unwrap().keys.insert(api_key.key_hash.clone(), api_key.clone());
+
+        Ok(CreatedKey { key, api_key })
+    }
+
+    pub async fn revoke(&self, id: &str) -> Result<ApiKey, Error> {
+        let store = self._store()?;
+
+        let api_key = {
+            let mut counters = self.counters.lock().unwrap();
+            let api_key = counters.keys.values_mut()
+                .find(|k| k.id == id)
+                .ok_or(Error::NotFound)?;
+
+            api_key.revoked_at.get_or_insert_with(|| self.clock.now());
+            api_key.clone()
+        };
+
+        store.save_api_key(&api_key).await?;
+
+        Ok(api_key)
+    }
+
+    pub fn consume(&self, path: &str, key: Option<&str>, client: Option<IpAddr>) -> Result<Option<(Usage, Vec<UsageCount>)>, Error> {
+        if auth::is_probe(path) {
+            return Ok(None);
+        }
+
+        if let Some(Scope::Admin) | Some(Scope::Internal) = auth::route_scope(path) {
+            return Ok(None);
+        }
+
+        let key = match key {
+            Some(key) => key.trim(),
+            None if self.config.require_key => return Err(Error::Unauthorized),
+            None => return self._consume_anonymous(client),
+        };
+
+        let mut counters = self.counters.lock().unwrap();
+        let api_key = counters.keys.get(&hash_key(key))
+            .filter(|k| k.revoked_at.is_none())
+            .cloned()
+            .ok_or(Error::Unauthorized)?;
+
+        let periods = periods(self.clock.now());
+        if counters.periods != periods {
+            counters.usage.retain(|(_, period), _| periods.contains(period));
+            counters.periods = periods.clone();
+        }
+
+        let [day, month] = periods;
+        let limits = [(day, api_key.daily_quota), (month, api_key.monthly_quota)];
+
+        let used: Vec<u64> = limits.iter()
+            .map(|(period, _)| counters.usage.get(&(api_key.id.clone(), period.clone())).cloned().unwrap_or(0))
+            .collect();
+
+        if limits.iter().zip(used.iter()).any(|((_, limit), used)| used >= limit) {
+            return Err(Error::QuotaExceeded);
+        }
+
+        let mut updates = Vec::new();
+        for (period, _) in limits.iter() {
+            let count = counters.usage.entry((api_key.id.clone(), period.clone())).or_insert(0);
+            *count += 1;
+            updates.push((api_key.id.clone(), period.clone(), *count));
+        }
+
+        let usage = limits.iter().zip(used.iter())
+            .map(|((_, limit), used)| Usage { limit: *limit, remaining: limit - used - 1 })
+            .min_by_key(|u| u.remaining)
+            .unwrap_or(Usage { limit: 0, remaining: 0 });
+
+        Ok(Some((usage, updates)))
+    }
+
+    pub async fn persist(&self, updates: Vec<UsageCount>) {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return,
+        };
+
+        for (id, period, count) in updates {
+            if let Err(e) = store.save_usage(id.as_str(), period.as_str(), count).await {
+                println!("persist: usage {} {} error {}", id, period, e);
+            }
+        }
+    }
+
+    fn _consume_anonymous(&self, client: Option<IpAddr>) -> Result<Option<(Usage, Vec<UsageCount>)>, Error> {
+        let limit = self.config.anonymous_per_minute;
+        let client = match client {
+            Some(client) if limit > 0 => client,
+            _ => return Ok(None),
+        };
+
+        let minute = self.clock.now().timestamp() / 60;
+        let mut counters = self.counters.lock().unwrap();
+        if counters.window != minute {
+            counters.window = minute;
+            counters.anonymous.clear();
+        }
+
+        let used = counters.anonymous.entry(client).or_insert(0);
+        if *used >= limit {
+            return Err(Error::QuotaExceeded);
+        }
+        *used += 1;
+
+        Ok(Some((Usage { limit, remaining: limit - *used }, Vec::new())))
+    }
+
+    fn _store(&self) -> Result<&Arc<dyn Store>, Error> {
+        self.store.as_ref()
+            .ok_or_else(|| Error::Config("no storage configured".to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockClock;
+    use chrono::TimeZone;
+
+    const PATH: &str = "/r0/professor/ada/overview";
+
+    fn quotas(config: QuotaConfig) -> (Quotas, Arc<MockClock>) {
+        let clock = MockClock::at(Utc.ymd(2024, 1, 31).and_hms(23, 59, 0));
+
+        (Quotas::new(&config, None, clock.clone()), clock)
+    }
+
+    fn issue(quotas: &Quotas, key: &str, daily_quota: u64, monthly_quota: u64) {
+        let api_key = ApiKey {
+            id: random_token(12),
+            name: "test".to_owned(),
+            key_hash: hash_key(key),
+            daily_quota,
+            monthly_quota,
+            created_at: quotas.clock.now(),
+            revoked_at: None,
+        };
+
+        quotas.counters.lock().unwrap().keys.insert(api_key.key_hash.clone(), api_key);
+    }
+
+    fn remaining(quotas: &Quotas, key: &str) -> Result<u64, Error> {
+        quotas.consume(PATH, Some(key), None).map(|usage| usage.unwrap().0.remaining)
+    }
+
+    #[test]
+    fn key_ids_do_not_reveal_the_key() {
+        let ids: Vec<String> = (0..32).map(|_| random_token(12)).collect();
+
+        assert!(ids.iter().all(|id| id.len() == 12 && id.chars().all(|c| c.is_ascii_alphanumeric())));
+        assert_eq!(ids.iter().collect::<std::collections::HashSet<_>>().len(), ids.len());
+        assert_eq!(hash_key("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn the_tighter_quota_runs_out_first() {
+        let (quotas, clock) = quotas(QuotaConfig::default());
+        issue(&quotas, "daily", 2, 100);
+        issue(&quotas, "monthly", 100, 3);
+
+        assert_eq!(remaining(&quotas, "daily").unwrap(), 1);
+        assert_eq!(remaining(&quotas, "daily").unwrap(), 0);
+        assert!(matches!(remaining(&quotas, "daily"), Err(Error::QuotaExceeded)));
+
+        assert_eq!(remaining(&quotas, "monthly").unwrap(), 2);
+        clock.advance(60);
+        assert_eq!(remaining(&quotas, " daily ").unwrap(), 1);
+        assert_eq!(remaining(&quotas, "monthly").unwrap(), 2);
+        assert_eq!(remaining(&quotas, "monthly").unwrap(), 1);
+    }
+
+    #[test]
+    fn usage_from_past_periods_is_pruned() {
+        let (quotas, clock) = quotas(QuotaConfig::default());
+        issue(&quotas, "key", 10, 10);
+
+        let (_, updates) = quotas.consume(PATH, Some("key"), None).unwrap().unwrap();
+        assert_eq!(updates.iter().map(|(_, period, count)| (period.as_str(), *count)).collect::<Vec<_>>(), vec![("d:2024-01-31", 1), ("m:2024-01", 1)]);
+
+        clock.advance(60);
+        quotas.consume(PATH, Some("key"), None).unwrap();
+
+        let mut periods: Vec<String> = quotas.counters.lock().unwrap().usage.keys().map(|(_, period)| period.clone()).collect();
+        periods.sort();
+        assert_eq!(periods, vec!["d:2024-02-01".to_owned(), "m:2024-02".to_owned()]);
+    }
+
+    #[test]
+    fn revoked_and_unknown_keys_are_unauthorized() {
+        let (quotas, _) = quotas(QuotaConfig::default());
+        issue(&quotas, "key", 10, 10);
+        assert!(remaining(&quotas, "key").is_ok());
+
+        quotas.counters.lock().unwrap().keys.get_mut(&hash_key("key")).unwrap().revoked_at = Some(Utc.ymd(2024, 1, 1).and_hms(0, 0, 0));
+        assert!(matches!(remaining(&quotas, "key"), Err(Error::Unauthorized)));
+        assert!(matches!(remaining(&quotas, "other"), Err(Error::Unauthorized)));
+    }
+
+    #[actix_rt::test]
+    async fn key_management_needs_storage() {
+        let (quotas, _) = quotas(QuotaConfig::default());
+        issue(&quotas, "key", 10, 10);
+        let id = quotas.keys()[0].id.clone();
+
+        assert!(matches!(quotas.revoke(id.as_str()).await, Err(Error::Config(_))));
+        assert!(quotas.keys()[0].revoked_at.is_none());
+        assert!(matches!(quotas.create(KeyRequest { name: "new".to_owned(), daily_quota: None, monthly_quota: None }).await, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn anonymous_clients_share_a_per_minute_window() {
+        let (quotas, clock) = quotas(QuotaConfig { anonymous_per_minute: 2, ..QuotaConfig::default() });
+        let client = "203.0.113.7".parse().ok();
+
+        assert_eq!(quotas.consume(PATH, None, client).unwrap().unwrap().0.remaining, 1);
+        assert_eq!(quotas.consume(PATH, None, client).unwrap().unwrap().0.remaining, 0);
+        assert!(matches!(quotas.consume(PATH, None, client), Err(Error::QuotaExceeded)));
+        assert!(quotas.consume(PATH, None, None).unwrap().is_none());
+        assert!(quotas.consume("/readyz", None, client).unwrap().is_none());
+
+        clock.advance(60);
+        assert_eq!(quotas.consume(PATH, None, client).unwrap().unwrap().0.remaining, 1);
+    }
+
+    #[test]
+    fn required_keys_reject_anonymous_calls() {
+        let (quotas, _) = quotas(QuotaConfig { require_key: true, ..QuotaConfig::default() });
+
+        assert!(matches!(quotas.consume(PATH, None, None), Err(Error::Unauthorized)));
+        assert!(quotas.consume("/admin/audit", None, None).unwrap().is_none());
+    }
+}
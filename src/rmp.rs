@@ -1,36 +1,51 @@
 use serde::{Serialize, Deserialize};
-use failure::Fail;
+use serde::de::DeserializeOwned;
 use regex::Regex;
-use futures::TryFutureExt;
+use futures::{Stream, StreamExt};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use std::collections::HashMap;
-use std::time::{SystemTime, UNIX_EPOCH};
-
-const SOLR_QUERY: &'static str =
-    "https://solr-aws-elb-production.ratemyprofessors.com/solr/rmp/select\
-?rows=200\
-&wt=json\
-&fq=schoolid_s:1077\
-&defType=edismax\
-&qf=teacherfirstname_t%5E2000+teacherlastname_t%5E2000+teacherfullname_t%5E2000+autosuggest\
-&sort=score+desc\
-&group=on\
-&group.field=content_type_s\
-&group.limit=-1\
-&spellcheck=false\
-&echoParams=none\
-&q=";
-
-const GRAPHQL_TOKEN_URL: &'static str =
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use crate::autocomplete::PrefixIndex;
+use crate::cache::Lru;
+use crate::error::Error;
+use crate::course;
+use crate::name;
+use crate::stats::{self, GradeQualityReport, SchoolStats, TagReport};
+use crate::department;
+use crate::grade;
+use crate::quarter::Quarter;
+use crate::text;
+use crate::store::{RatingsQuery, Store};
+use crate::moderation::HiddenRatings;
+use crate::alert::SchemaMonitor;
+use crate::analysis;
+use crate::budget::{Upstream, UpstreamBudget};
+use crate::clock::{Clock, SystemClock};
+use crate::outbound::{self, HttpBackend, OutboundConfig, OutboundRequest, ReqwestBackend, UpstreamResponse, DEFAULT_RETRY_AFTER_SECS};
+use crate::config::{AccessConfig, AlertConfig, AppConfig, SheddingConfig};
+use ucsb_courses::models::{CourseCount, CourseType, DataSource, RmpIds, TeacherMetadata};
+
+pub const DEFAULT_SCHOOL_ID: u32 = 1077;
+
+const SOLR_URL: &str = "https://solr-aws-elb-production.ratemyprofessors.com/solr/rmp/select";
+
+const GRAPHQL_TOKEN_URL: &str =
     "https://www.ratemyprofessors.com/ShowRatings.jsp?tid=10000";
 
-const GRAPHQL_URL: &'static str =
+const GRAPHQL_URL: &str =
     "https://www.ratemyprofessors.com/graphql";
 
-const GRAPHQL_QUERY: &'static str =
+const RMP_PROFILE_URL: &str =
+    "https://www.ratemyprofessors.com/professor";
+
+const GRAPHQL_QUERY: &str =
     r#"query RatingsListQuery(
     $id: ID!
+    $count: Int!
+    $cursor: String
     $courseFilter: String
 ) {
     node(id: $id) {
@@ -45,8 +60,15 @@ const GRAPHQL_QUERY: &'static str =
 fragment RatingsList_teacher_4pguUW on Teacher {
     id
     legacyId
+    lockStatus
+    numRatings
+    avgDifficulty
+    wouldTakeAgainPercent
+    school {
+        name
+    }
     ...Rating_teacher
-    ratings(courseFilter: $courseFilter) {
+    ratings(first: $count, after: $cursor, courseFilter: $courseFilter) {
         edges {
             node {
               ...Rating_rating
@@ -54,6 +76,10 @@ fragment RatingsList_teacher_4pguUW on Teacher {
               __typename
             }
         }
+        pageInfo {
+            hasNextPage
+            endCursor
+        }
     }
 }
 
@@ -115,31 +141,9 @@ fragment RatingFooter_teacher on Teacher {
     lockStatus
 }"#;
 
-#[derive(Debug, Fail)]
-pub enum Error {
-    #[fail(display = "ratemyprofessor.com broken")]
-    RMP,
-}
-
 mod int_bool {
     use serde::{self, Deserialize, Deserializer};
 
-    pub fn deserialize<'de, D>(
-        deserializer: D,
-    ) -> Result<bool, D::Error>
-        where
-            D: Deserializer<'de>,
-    {
-        match u8::deserialize(deserializer)? {
-            0 => Ok(false),
-            1 => Ok(true),
-            other => Err(serde::de::Error::invalid_value(
-                serde::de::Unexpected::Unsigned(other as u64),
-                &"zero or one",
-            )),
-        }
-    }
-
     pub fn deserialize_opt<'de, D>(
         deserializer: D,
     ) -> Result<Option<bool>, D::Error>
@@ -167,7 +171,7 @@ mod rmp_date {
     use chrono::{DateTime, Utc, TimeZone};
     use serde::{self, Deserialize, Deserializer};
 
-    const FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
+    const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
     pub fn deserialize<'de, D>(
         deserializer: D,
@@ -202,6 +206,8 @@ mod rmp_mandatory {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct GraphQLVariables {
     id: String,
+    count: u32,
+    cursor: Option<String>,
     #[serde(rename = "courseFilter")]
     course_filter: Option<String>,
 }
@@ -225,6 +231,8 @@ struct ProfessorResponse {
     pub full_name: String,
     #[serde(rename = "teacherdepartment_s")]
     pub department: String,
+    #[serde(rename = "total_number_of_ratings_i")]
+    pub num_ratings: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -283,6 +291,8 @@ pub struct Rating {
     pub grade: String,
     #[serde(rename = "helpfulRating")]
     pub helpful: u32,
+    #[serde(rename = "legacyId")]
+    pub legacy_id: Option<u32>,
     #[serde(rename = "ratingTags")]
     pub tags: String,
     #[serde(rename = "textbookUse")]
@@ -296,19 +306,149 @@ pub struct Rating {
     pub would_take_again: Option<bool>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct NormalizedRating {
+    pub legacy_id: Option<u32>,
+
+    pub class: String,
+    pub course: Option<String>,
+
+    pub comment: String,
+    pub tags: Vec<String>,
+
+    pub word_count: u32,
+    pub reading_seconds: u32,
+
+    pub grade: String,
+    pub gpa: Option<f32>,
+
+    pub attendance_mandatory: Option<bool>,
+    pub would_take_again: Option<bool>,
+    pub textbook_use: Option<u32>,
+    pub course_type: Option<u32>,
+
+    pub clarity: u32,
+    pub helpful: u32,
+    pub difficulty: u32,
+
+    pub thumbs_up: u32,
+    pub thumbs_down: u32,
+
+    pub date: chrono::DateTime<chrono::Utc>,
+    pub quarter: Quarter,
+}
+
+impl NormalizedRating {
+    pub fn quality(&self) -> f32 {
+        (self.clarity + self.helpful) as f32 / 2.0
+    }
+
+    pub fn net_thumbs(&self) -> i64 {
+        self.thumbs_up as i64 - self.thumbs_down as i64
+    }
+
+    pub fn course_type(&self) -> Option<CourseType> {
+        match self.course_type? {
+            1 => Some(CourseType::InPerson),
+            2 => Some(CourseType::Online),
+            3 => Some(CourseType::Hybrid),
+            _ => None,
+        }
+    }
+
+    pub fn online(&self) -> Option<bool> {
+        match self.course_type() {
+            Some(course_type) => Some(course_type == CourseType::Online),
+            None if self.quarter.is_remote() => Some(true),
+            None => None,
+        }
+    }
+}
+
+impl From<&Rating> for NormalizedRating {
+    fn from(r: &Rating) -> Self {
+        let comment = text::sanitize(r.comment.as_str());
+        let word_count = text::word_count(comment.as_str());
+
+        NormalizedRating {
+            legacy_id: r.legacy_id,
+            class: r.class.clone(),
+            course: course::normalize_course(r.class.as_str()),
+            comment,
+            word_count,
+            reading_seconds: text::reading_seconds(word_count),
+            tags: r.tags.split("--")
+                .map(|t| t.trim())
+                .filter(|t| !t.is_empty())
+                .map(|t| t.to_owned())
+                .collect(),
+            grade: r.grade.clone(),
+            gpa: grade::gpa(r.grade.as_str()),
+            attendance_mandatory: r.attendance_mandatory,
+            would_take_again: r.would_take_again,
+            textbook_use: r.textbook_use,
+            course_type: r.course_type,
+            clarity: r.clarity,
+            helpful: r.helpful,
+            difficulty: r.difficulty,
+            thumbs_up: r.thumbs_up,
+            thumbs_down: r.thumbs_down,
+            date: r.date,
+            quarter: Quarter::from_date(r.date),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct InnerCommentsRatingsEdgesResponse {
     pub node: Rating,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PageInfo {
+    #[serde(rename = "hasNextPage")]
+    pub has_next_page: bool,
+    #[serde(rename = "endCursor")]
+    pub end_cursor: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct InnerCommentsRatingsResponse {
     pub edges: Vec<InnerCommentsRatingsEdgesResponse>,
+    #[serde(rename = "pageInfo", default)]
+    pub page_info: Option<PageInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SchoolNameResponse {
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct InnerCommentsNodeResponse {
     pub ratings: InnerCommentsRatingsResponse,
+    #[serde(rename = "lockStatus", default)]
+    pub lock_status: Option<String>,
+    #[serde(rename = "numRatings", default)]
+    pub num_ratings: Option<u32>,
+    #[serde(rename = "avgDifficulty", default)]
+    pub avg_difficulty: Option<f32>,
+    #[serde(rename = "wouldTakeAgainPercent", default)]
+    pub would_take_again_percent: Option<f32>,
+    #[serde(default)]
+    pub school: Option<SchoolNameResponse>,
+}
+
+impl InnerCommentsNodeResponse {
+    fn metadata(&self) -> TeacherMetadata {
+        TeacherMetadata {
+            locked: self.lock_status.as_deref().map(|s| s != "none").unwrap_or(false),
+            school: self.school.as_ref().map(|s| s.name.clone()),
+            num_ratings: self.num_ratings,
+            avg_difficulty: self.avg_difficulty.filter(|d| *d > 0.0),
+            would_take_again_percent: self.would_take_again_percent.filter(|p| *p >= 0.0),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -321,10 +461,236 @@ struct CommentsResponse {
     pub data: InnerCommentsDataResponse,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Cache,
+    Store,
+    Rmp,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Source::Cache => write!(f, "cache"),
+            Source::Store => write!(f, "store"),
+            Source::Rmp => write!(f, "rmp"),
+        }
+    }
+}
+
+pub const RMP_SOURCE: &str = "rmp";
+pub const UCSB_SOURCE: &str = "ucsb_api";
+
+pub fn data_source(kind: &str, fetched_at: chrono::DateTime<chrono::Utc>) -> DataSource {
+    DataSource { kind: kind.to_owned(), fetched_at }
+}
+
+pub fn rmp_ids(rmp_id: u32) -> RmpIds {
+    RmpIds {
+        rmp_id,
+        rmp_node_id: base64::encode(format!("Teacher-{}", rmp_id).as_str()),
+        rmp_url: format!("{}/{}", RMP_PROFILE_URL, rmp_id),
+    }
+}
+
+pub fn rmp_course_url(rmp_id: u32, course: &str) -> String {
+    format!("{}?course={}", rmp_ids(rmp_id).rmp_url, course::compact_course(course))
+}
+
+pub struct Fetched<T> {
+    pub value: T,
+    pub source: Source,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    pub stale: bool,
+    pub sources: Vec<DataSource>,
+}
+
+impl<T> Fetched<T> {
+    fn at(value: T, source: Source, fetched_at: chrono::DateTime<chrono::Utc>, stale: bool) -> Self {
+        Fetched {
+            value,
+            source,
+            fetched_at,
+            stale,
+            sources: vec![data_source(RMP_SOURCE, fetched_at)],
+        }
+    }
+
+    pub fn with_source(mut self, kind: &str, fetched_at: chrono::DateTime<chrono::Utc>) -> Self {
+        self.sources.retain(|s| s.kind != kind);
+        self.sources.push(data_source(kind, fetched_at));
+        self
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Fetched<U> {
+        Fetched {
+            value: f(self.value),
+            source: self.source,
+            fetched_at: self.fetched_at,
+            stale: self.stale,
+            sources: self.sources,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScorerConfig {
+    pub min_thumb_score: Option<i64>,
+}
+
+impl ScorerConfig {
+    fn accepts(&self, rating: &NormalizedRating) -> bool {
+        self.min_thumb_score.map(|min| rating.net_thumbs() >= min).unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SolrConfig {
+    pub url: String,
+    pub rows: u32,
+    pub qf: String,
+}
+
+impl Default for SolrConfig {
+    fn default() -> Self {
+        SolrConfig {
+            url: SOLR_URL.to_owned(),
+            rows: 200,
+            qf: "teacherfirstname_t^2000 teacherlastname_t^2000 teacherfullname_t^2000 autosuggest".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GraphQLConfig {
+    pub url: String,
+    pub token_url: String,
+    pub page_size: u32,
+    pub max_pages: u32,
+    pub token_retry_secs: i64,
+    pub query: String,
+    pub query_file: Option<String>,
+}
+
+impl Default for GraphQLConfig {
+    fn default() -> Self {
+        GraphQLConfig {
+            url: GRAPHQL_URL.to_owned(),
+            token_url: GRAPHQL_TOKEN_URL.to_owned(),
+            page_size: 100,
+            max_pages: 20,
+            token_retry_secs: 30,
+            query: GRAPHQL_QUERY.to_owned(),
+            query_file: None,
+        }
+    }
+}
+
+const GRAPHQL_VARIABLES: &[&str] = &["id", "count", "cursor", "courseFilter"];
+
+pub fn load_graphql_query(config: &GraphQLConfig) -> Result<String, Error> {
+    let query = match &config.query_file {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("cannot read graphql query {}: {}", path, e)))?,
+        None => config.query.clone(),
+    };
+
+    for variable in GRAPHQL_VARIABLES {
+        let re = Regex::new(format!(r"\${}\s*:", variable).as_str()).map_err(|e| Error::Config(e.to_string()))?;
+
+        if !re.is_match(query.as_str()) {
+            return Err(Error::Config(format!("graphql query must declare ${}", variable)));
+        }
+    }
+
+    if !query.contains("ratings(") {
+        return Err(Error::Config("graphql query must select ratings".to_owned()));
+    }
+
+    Ok(query)
+}
+
+pub fn dedup_ratings(ratings: Vec<NormalizedRating>) -> Vec<NormalizedRating> {
+    let mut seen = std::collections::HashSet::new();
+
+    ratings.into_iter()
+        .filter(|r| r.legacy_id.map(|id| seen.insert(id)).unwrap_or(true))
+        .collect()
+}
+
+pub fn ratings_hash(ratings: &[NormalizedRating]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    for r in ratings {
+        (r.legacy_id, &r.class, &r.comment, &r.tags, &r.grade).hash(&mut hasher);
+        (r.attendance_mandatory, r.would_take_again, r.textbook_use, r.course_type).hash(&mut hasher);
+        (r.clarity, r.helpful, r.difficulty, r.thumbs_up, r.thumbs_down, r.date.timestamp()).hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+fn solr_url(school_id: u32, name: &str, department: Option<&str>, solr: &SolrConfig) -> Result<reqwest::Url, Error> {
+    let mut url = reqwest::Url::parse_with_params(solr.url.as_str(), &[
+        ("rows", solr.rows.to_string()),
+        ("wt", "json".to_owned()),
+        ("fq", format!("schoolid_s:{}", school_id)),
+        ("defType", "edismax".to_owned()),
+        ("qf", solr.qf.clone()),
+        ("sort", "score desc".to_owned()),
+        ("group", "on".to_owned()),
+        ("group.field", "content_type_s".to_owned()),
+        ("group.limit", "-1".to_owned()),
+        ("spellcheck", "false".to_owned()),
+        ("echoParams", "none".to_owned()),
+        ("q", name.to_owned()),
+    ]).map_err(|e| Error::Config(e.to_string()))?;
+
+    if let Some(department) = department {
+        let department: String = department.chars().filter(|c| *c != '"' && *c != '\\').collect();
+        url.query_pairs_mut().append_pair("fq", format!("teacherdepartment_s:\"{}\"", department).as_str());
+    }
+
+    Ok(url)
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Freshness {
+    pub max_age: Option<i64>,
+    #[serde(default)]
+    pub refresh: bool,
+    #[serde(skip)]
+    pub allow_stale: bool,
+    #[serde(skip)]
+    pub cache_only: bool,
+}
+
+impl Freshness {
+    pub fn is_stale(&self, fetched_at: chrono::DateTime<chrono::Utc>, now: chrono::DateTime<chrono::Utc>) -> bool {
+        if self.refresh {
+            return true;
+        }
+
+        match self.max_age {
+            Some(max_age) => (now - fetched_at).num_seconds() > max_age,
+            None => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Score {
     pub quality: Option<f32>,
     pub quality_yr: Option<f32>,
+    pub num_ratings: u32,
+    pub insufficient_data: bool,
+
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    pub computed_at: chrono::DateTime<chrono::Utc>,
+    pub stale: bool,
 }
 
 #[derive(Clone)]
@@ -333,232 +699,3017 @@ pub struct Professor {
 
     pub score: Option<Score>,
 
+    pub rmp_quality: Option<f32>,
+    pub num_ratings: Option<u32>,
+
     pub first_name: String,
     pub last_name: String,
     pub full_name: String,
 
     pub department: String,
+    pub rmp_department: Option<String>,
 }
 
-struct ControllerData {
-    rmp_graphql_token: Option<String>,
-    name_id_map: HashMap<String, Vec<u32>>,
-    id_professor_map: HashMap<u32, Arc<Mutex<Professor>>>,
+#[cfg(feature = "scheduler")]
+#[derive(Serialize)]
+pub struct ProfessorSnapshot {
+    pub school_id: u32,
+    pub rmp_id: u32,
+    pub full_name: String,
+    pub department: String,
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub ratings: Vec<NormalizedRating>,
 }
 
-pub struct Controller {
-    data: Arc<Mutex<ControllerData>>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreAlgo {
+    Weighted,
+    Recency,
+    Mean,
+}
 
-    client: reqwest::Client,
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScoreParams {
+    pub window_secs: Option<u64>,
+    pub min_weight: Option<f32>,
+    pub algo: Option<ScoreAlgo>,
 }
 
-impl Controller {
-    pub fn new() -> Self {
-        let controller_data = ControllerData {
-            rmp_graphql_token: None,
-            name_id_map: HashMap::new(),
-            id_professor_map: HashMap::new(),
-        };
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunScore {
+    pub rmp_id: u32,
+    pub quality: Option<f32>,
+    pub weight: f32,
+    pub ratings_used: usize,
+    pub window_secs: u64,
+    pub min_weight: f32,
+    pub algo: ScoreAlgo,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
 
-        Controller {
-            data: Arc::new(Mutex::new(controller_data)),
-            client: reqwest::Client::default(),
-        }
-    }
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphQLToken {
+    pub token: String,
+    pub scraped_at: chrono::DateTime<chrono::Utc>,
+    pub uses: u64,
+}
 
-    pub async fn graphql_token(&self) -> Result<String, Error> {
-        {
-            let data = self.data.lock().await;
+#[derive(Serialize)]
+pub struct DumpedRating {
+    pub rmp_id: u32,
+    pub department: String,
+    #[serde(flatten)]
+    pub rating: NormalizedRating,
+}
 
-            if let Some(token) = data.rmp_graphql_token.clone() {
-                return Ok(token);
-            }
-        }
+const DEFAULT_REFRESH_MIN_INTERVAL: i64 = 300;
 
-        let resp = self.client.get(GRAPHQL_TOKEN_URL)
-            .send()
-            .and_then(|r| async move { r.text().await }).map_err(|_| Error::RMP).await?;
+const DEFAULT_TIMEOUT_MS: u64 = 10000;
 
-        let re = Regex::new("\"REACT_APP_GRAPHQL_AUTH\":\"(.*?)\"").map_err(|_| Error::RMP)?;
+const SCORE_WINDOW: u64 = 157680000;
 
-        if let Some(cap) = re.captures_iter(resp.as_str()).next() {
-            let token = cap[1].to_string();
+const SAMPLE_BYTES: usize = 512;
 
-            let mut data = self.data.lock().await;
-            data.rmp_graphql_token = Some(token.clone());
+const DEFAULT_RATINGS_TTL: i64 = 21600;
 
-            return Ok(token);
-        }
+const DEFAULT_EMPTY_RATINGS_TTL: i64 = 3600;
 
-        Err(Error::RMP)
-    }
+const DEFAULT_TTL_JITTER: i64 = 3600;
 
-    pub async fn professor_overview(&self, name: String) -> Option<Arc<Mutex<Professor>>> {
-        if let Some(pr) = self._name_to_professor(name).await {
-            let professor_lock = pr.clone();
-            let mut professor = professor_lock.lock().await;
+const DEFAULT_SCORE_TTL: i64 = 300;
 
-            if let Some(score) = professor.score.clone() {
-                return Some(pr);
-            }
+const DEFAULT_IDENTITY_TTL: i64 = 604800;
 
-            if let Ok(resp) = self._professor_comments(professor.rmp_id, None).await {
-                let (score, weight) = Self::_weighted_score(&resp, 157680000);
-                let (score_yr, weight_yr) = Self::_weighted_score(&resp, 31536000);
+const DEFAULT_CACHE_SIZE: usize = 256;
 
-                let professor_score = Score {
-                    quality: if weight < 8.0 { None } else { Some(score / weight) },
-                    quality_yr: if weight_yr < 2.0 { None } else { Some(score_yr / weight_yr) },
-                };
+const INCREMENTAL_OVERLAP: i64 = 604800;
 
-                professor.score = Some(professor_score.clone());
+const POPULARITY_HALF_LIFE: f64 = 86400.0;
 
-                return Some(pr);
-            }
-        }
+#[cfg(feature = "scheduler")]
+const COLD_TTL_DOUBLINGS: i32 = 3;
 
-        None
-    }
+#[cfg(feature = "scheduler")]
+const HOT_TTL_HALVINGS: i32 = 2;
 
-    pub async fn professor_comments(&self, name: String, course: Option<String>) -> Vec<Rating> {
-        if let Some(pr) = self._name_to_professor(name).await {
-            let professor = pr.lock().await;
+fn check_status(resp: &UpstreamResponse, what: &str) -> Result<(), Error> {
+    let status = resp.status;
 
-            if let Ok(ratings) = self._professor_comments(professor.rmp_id, course).await {
-                return ratings;
-            }
-        }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = resp.retry_after.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+        println!("{}: rate limited, retry after {}s", what, retry_after);
 
-        Vec::new()
+        return Err(Error::Overloaded(retry_after));
     }
 
-    async fn _name_to_professor(&self, name: String) -> Option<Arc<Mutex<Professor>>> {
-        let mut data = self.data.lock().await;
+    if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+        println!("{}: upstream rejected credentials ({})", what, status);
+        return Err(Error::Unauthorized);
+    }
 
-        let name = name.to_lowercase();
-        let mut id_opt: Option<u32> = None;
+    if !status.is_success() {
+        println!("{}: upstream status {}", what, status);
+        return Err(Error::Rmp);
+    }
 
-        if let Some(ids) = data.name_id_map.get(name.as_str()) {
-            id_opt = ids.get(0).cloned();
-        } else {
-            let res = self._search_professor(name.as_str()).await.ok()?;
+    Ok(())
+}
 
-            let ids: Vec<u32> = res.iter()
-                .map(|r| &r.id)
-                .map(|r| r.replace("teacher:", ""))
-                .map(|r| r.parse::<u32>().ok())
-                .filter_map(|r| r)
-                .collect();
+fn schema_error(what: &str, reason: String, body: &[u8]) -> Error {
+    let sample = String::from_utf8_lossy(&body[..body.len().min(SAMPLE_BYTES)]);
+    println!("{}: unexpected response ({}): {}", what, reason, sample);
 
-            id_opt = ids.get(0).cloned();
-
-            data.name_id_map.insert(name, ids);
-
-            for pr in res {
-                if let Ok(id) = pr.id.replace("teacher:", "").parse::<u32>() {
-                    if !data.id_professor_map.contains_key(&id) {
-                        data.id_professor_map.insert(
-                            id,
-                            Arc::new(
-                                Mutex::new(
-                                    Professor {
-                                        rmp_id: id,
-                                        score: None,
-                                        first_name: pr.first_name,
-                                        last_name: pr.last_name,
-                                        full_name: pr.full_name,
-                                        department: pr.department,
-                                    }
-                                )
-                            ),
-                        );
-                    }
-                }
-            }
-        }
+    Error::RmpSchema(format!("{}: {}", what, reason))
+}
 
-        if let Some(id) = id_opt {
-            return data.id_professor_map.get(&id).cloned();
-        }
+fn parse_guarded<T: DeserializeOwned>(body: &[u8], what: &str, required: &[&str]) -> Result<T, Error> {
+    let value: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| schema_error(what, format!("invalid json: {}", e), body))?;
 
-        None
+    for pointer in required {
+        if value.pointer(pointer).map(|v| v.is_null()).unwrap_or(true) {
+            return Err(schema_error(what, format!("missing {}", pointer), body));
+        }
     }
 
-    async fn _search_professor(&self, name: &str) -> Result<Vec<ProfessorResponse>, Error> {
-        let resp = self.client.get((SOLR_QUERY.to_owned() + name).as_str())
-            .send()
-            .and_then(|r| async move { r.json::<RMPResponse>().await })
-            .map_err(|e| {
-                println!("get_professor_overview: error {}", e);
-                Error::RMP
-            }).await?;
+    serde_json::from_value(value).map_err(|e| schema_error(what, e.to_string(), body))
+}
 
-        let grouped: Vec<GroupResponse> = resp.grouped.inner.groups;
+#[derive(Clone, Copy)]
+struct Popularity {
+    score: f64,
+    updated_at: chrono::DateTime<chrono::Utc>,
+}
 
-        if let Some(teacher_group) = grouped.iter()
-            .filter(|g| g.group_name == "TEACHER")
-            .next() {
-            return Ok(teacher_group
-                .doc_list
-                .docs
-                .clone());
-        }
+impl Popularity {
+    fn at(&self, now: chrono::DateTime<chrono::Utc>) -> f64 {
+        let elapsed = (now - self.updated_at).num_seconds().max(0) as f64;
 
-        Ok(Vec::new())
+        self.score * 0.5f64.powf(elapsed / POPULARITY_HALF_LIFE)
     }
 
-    async fn _professor_comments(&self, rmp_id: u32, course: Option<String>) -> Result<Vec<Rating>, Error> {
-        if let Ok(token) = self.graphql_token().await {
-            let resp: CommentsResponse = self.client
-                .post(GRAPHQL_URL)
-                .json(&GraphQLRequest {
-                    query: GRAPHQL_QUERY.to_owned(),
-                    variables: GraphQLVariables {
-                        id: base64::encode(format!("Teacher-{}", rmp_id).as_str()),
-                        course_filter: course,
-                    },
-                })
-                .header(reqwest::header::AUTHORIZATION, format!("Basic {}", token))
-                .send()
-                .and_then(|r| async move { r.json::<CommentsResponse>().await })
-                .map_err(|e| {
-                    println!("{:?}", e);
-                    Error::RMP
-                }).await?;
-
-            let ratings = resp.data.node.ratings.edges.iter()
-                .map(|r| r.node.clone())
-                .collect();
-
-            return Ok(ratings);
-        }
+    #[cfg(feature = "scheduler")]
+    fn ttl_factor(&self, now: chrono::DateTime<chrono::Utc>) -> f64 {
+        let tier = (self.at(now) + 1.0).log2().floor() as i32;
 
-        Err(Error::RMP)
+        2f64.powi(COLD_TTL_DOUBLINGS - tier.min(COLD_TTL_DOUBLINGS + HOT_TTL_HALVINGS))
     }
+}
 
-    fn _weighted_score(data: &Vec<Rating>, offset: u64) -> (f32, f32) {
-        let mut quality_ratings_sum = 0.0;
-        let mut total_weight = 0.0;
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    pub step: &'static str,
+    pub ok: bool,
+    pub detail: String,
+    pub elapsed_ms: u128,
+}
+
+struct RatingsEntry {
+    ratings: Vec<NormalizedRating>,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+}
 
-        let offsetted = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() - offset;
+struct NameEntry {
+    ids: Vec<u32>,
+    fetched_at: chrono::DateTime<chrono::Utc>,
+}
 
-        for r in data {
-            if (r.date.timestamp() as u64) < offsetted {
-                continue;
-            }
+struct ControllerData {
+    rmp_graphql_token: Option<GraphQLToken>,
+    name_id_map: HashMap<String, NameEntry>,
+    pinned_names: HashMap<String, u32>,
+    merges: HashMap<u32, u32>,
+    last_refresh: HashMap<String, chrono::DateTime<chrono::Utc>>,
+    id_professor_map: HashMap<u32, Arc<Mutex<Professor>>>,
+    ratings_map: Lru<RatingsEntry>,
+    evicted: HashSet<u32>,
+    truncated: HashSet<u32>,
+    missing_courses: HashMap<(u32, String), chrono::DateTime<chrono::Utc>>,
+    popularity: HashMap<u32, Popularity>,
+    metadata: HashMap<u32, TeacherMetadata>,
+    department_map: HashMap<u32, String>,
+    professor_index: PrefixIndex<u32>,
+    course_index: PrefixIndex<String>,
+    cross_listings: course::CrossListings,
+    catalog_fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+    last_crawl: Option<chrono::DateTime<chrono::Utc>>,
+    school_stats: Option<SchoolStats>,
+}
 
-            let quality = (r.helpful + r.clarity) as f32 / 2.0;
+pub struct Controller {
+    data: Arc<Mutex<ControllerData>>,
 
-            let thumbs_weight = (r.thumbs_up + 1) as f32 / (r.thumbs_up + r.thumbs_down + 1) as f32;
-            let time_weight = ((r.date.timestamp() as u64 - offsetted) as f64 / offset as f64) as f32;
-            let quantity_weight = ((r.thumbs_up + r.thumbs_down) as f32 / 2.0).ln_1p() + 1.0;
+    token_scrape: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
 
-            let avg_weight = thumbs_weight * time_weight * quantity_weight;
+    school_id: u32,
 
-            total_weight += avg_weight;
-            quality_ratings_sum += quality * avg_weight;
+    scorer: ScorerConfig,
+
+    store: Option<Arc<dyn Store>>,
+
+    refresh_min_interval: i64,
+
+    ratings_ttl: i64,
+
+    empty_ratings_ttl: i64,
+
+    ttl_jitter: i64,
+
+    score_ttl: i64,
+
+    identity_ttl: i64,
+
+    solr: SolrConfig,
+
+    graphql: GraphQLConfig,
+
+    shedding: SheddingConfig,
+
+    crawl_departments: Vec<String>,
+
+    access: AccessConfig,
+
+    schema_monitor: Arc<SchemaMonitor>,
+
+    hidden: Arc<HiddenRatings>,
+
+    backend: Arc<dyn HttpBackend>,
+
+    clock: Arc<dyn Clock>,
+
+    budget: Arc<UpstreamBudget>,
+}
+
+#[derive(Clone)]
+pub struct ControllerBuilder {
+    school_id: u32,
+    scorer: ScorerConfig,
+    store: Option<Arc<dyn Store>>,
+    cache_size: usize,
+    timeout: std::time::Duration,
+    refresh_min_interval: i64,
+    ratings_ttl: i64,
+    empty_ratings_ttl: i64,
+    ttl_jitter: i64,
+    score_ttl: i64,
+    identity_ttl: i64,
+    solr: SolrConfig,
+    graphql: GraphQLConfig,
+    shedding: SheddingConfig,
+    crawl_departments: Vec<String>,
+    alerts: AlertConfig,
+    access: AccessConfig,
+    hidden: Arc<HiddenRatings>,
+    budget: Arc<UpstreamBudget>,
+    outbound: OutboundConfig,
+    backend: Option<Arc<dyn HttpBackend>>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl Default for ControllerBuilder {
+    fn default() -> Self {
+        ControllerBuilder {
+            school_id: DEFAULT_SCHOOL_ID,
+            scorer: ScorerConfig::default(),
+            store: None,
+            cache_size: DEFAULT_CACHE_SIZE,
+            timeout: std::time::Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            refresh_min_interval: DEFAULT_REFRESH_MIN_INTERVAL,
+            ratings_ttl: DEFAULT_RATINGS_TTL,
+            empty_ratings_ttl: DEFAULT_EMPTY_RATINGS_TTL,
+            ttl_jitter: DEFAULT_TTL_JITTER,
+            score_ttl: DEFAULT_SCORE_TTL,
+            identity_ttl: DEFAULT_IDENTITY_TTL,
+            solr: SolrConfig::default(),
+            graphql: GraphQLConfig::default(),
+            shedding: SheddingConfig::default(),
+            crawl_departments: Vec::new(),
+            alerts: AlertConfig::default(),
+            access: AccessConfig::default(),
+            hidden: Arc::new(HiddenRatings::default()),
+            budget: Arc::new(UpstreamBudget::default()),
+            outbound: OutboundConfig::default(),
+            backend: None,
+            clock: None,
         }
+    }
+}
+
+impl ControllerBuilder {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let school_id = config.schools.enabled.iter()
+            .find(|s| s.slug == config.schools.default)
+            .map(|s| s.rmp_id)
+            .unwrap_or(DEFAULT_SCHOOL_ID);
+
+        Controller::builder()
+            .school_id(school_id)
+            .scorer(config.scorer.clone())
+            .cache_size(config.storage.cache_size)
+            .timeout(std::time::Duration::from_millis(config.rmp.timeout_ms))
+            .refresh_min_interval(config.rmp.refresh_min_interval)
+            .ratings_ttl(config.rmp.ratings_ttl)
+            .empty_ratings_ttl(config.rmp.empty_ratings_ttl)
+            .ttl_jitter(config.rmp.ttl_jitter)
+            .score_ttl(config.rmp.score_ttl)
+            .identity_ttl(config.rmp.identity_ttl)
+            .solr(config.rmp.solr.clone())
+            .graphql(config.rmp.graphql.clone())
+            .shedding(config.shedding.clone())
+            .crawl_departments(config.crawl.departments.clone())
+            .alerts(config.alerts.clone())
+            .access(config.access.clone())
+            .outbound(config.rmp.outbound.clone())
+    }
+
+    pub fn school_id(mut self, school_id: u32) -> Self {
+        self.school_id = school_id;
+        self
+    }
+
+    pub fn scorer(mut self, scorer: ScorerConfig) -> Self {
+        self.scorer = scorer;
+        self
+    }
+
+    pub fn store(mut self, store: Arc<dyn Store>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = cache_size;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn refresh_min_interval(mut self, seconds: i64) -> Self {
+        self.refresh_min_interval = seconds;
+        self
+    }
+
+    pub fn ratings_ttl(mut self, seconds: i64) -> Self {
+        self.ratings_ttl = seconds;
+        self
+    }
+
+    pub fn empty_ratings_ttl(mut self, seconds: i64) -> Self {
+        self.empty_ratings_ttl = seconds;
+        self
+    }
+
+    pub fn ttl_jitter(mut self, seconds: i64) -> Self {
+        self.ttl_jitter = seconds;
+        self
+    }
+
+    pub fn score_ttl(mut self, seconds: i64) -> Self {
+        self.score_ttl = seconds;
+        self
+    }
+
+    pub fn identity_ttl(mut self, seconds: i64) -> Self {
+        self.identity_ttl = seconds;
+        self
+    }
+
+    pub fn solr(mut self, solr: SolrConfig) -> Self {
+        self.solr = solr;
+        self
+    }
+
+    pub fn graphql(mut self, graphql: GraphQLConfig) -> Self {
+        self.graphql = graphql;
+        self
+    }
+
+    pub fn shedding(mut self, shedding: SheddingConfig) -> Self {
+        self.shedding = shedding;
+        self
+    }
+
+    pub fn crawl_departments(mut self, crawl_departments: Vec<String>) -> Self {
+        self.crawl_departments = crawl_departments;
+        self
+    }
+
+    pub fn alerts(mut self, alerts: AlertConfig) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    pub fn access(mut self, access: AccessConfig) -> Self {
+        self.access = access;
+        self
+    }
+
+    pub fn hidden(mut self, hidden: Arc<HiddenRatings>) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    pub fn budget(mut self, budget: Arc<UpstreamBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn outbound(mut self, outbound: OutboundConfig) -> Self {
+        self.outbound = outbound;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn backend(mut self, backend: Arc<dyn HttpBackend>) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn build(mut self) -> Result<Controller, Error> {
+        if self.school_id == 0 {
+            return Err(Error::Config("school id must be set".to_owned()));
+        }
+
+        if self.timeout.as_millis() == 0 {
+            return Err(Error::Config("timeout must be positive".to_owned()));
+        }
+
+        if self.refresh_min_interval < 0 {
+            return Err(Error::Config("refresh interval must not be negative".to_owned()));
+        }
+
+        if self.ratings_ttl <= 0 {
+            return Err(Error::Config("ratings ttl must be positive".to_owned()));
+        }
+
+        if self.ttl_jitter < 0 {
+            return Err(Error::Config("ttl jitter must not be negative".to_owned()));
+        }
+
+        if self.score_ttl < 0 || self.identity_ttl < 0 {
+            return Err(Error::Config("score and identity ttls must not be negative".to_owned()));
+        }
+
+        if self.solr.rows == 0 {
+            return Err(Error::Config("solr rows must be positive".to_owned()));
+        }
+
+        if self.graphql.page_size == 0 || self.graphql.max_pages == 0 {
+            return Err(Error::Config("graphql page size and max pages must be positive".to_owned()));
+        }
+
+        self.graphql.query = load_graphql_query(&self.graphql)?;
+
+        if !(self.alerts.schema_failure_rate > 0.0 && self.alerts.schema_failure_rate <= 1.0) || self.alerts.min_samples == 0 {
+            return Err(Error::Config("alert failure rate must be in (0, 1] and min samples positive".to_owned()));
+        }
+
+        let base: Arc<dyn HttpBackend> = match self.backend {
+            Some(backend) => backend,
+            None => Arc::new(ReqwestBackend::new(self.timeout)?),
+        };
+        let clock = self.clock.unwrap_or_else(|| Arc::new(SystemClock));
+        let backend = outbound::chain(base, &self.outbound, self.budget.clone(), clock.clone());
+
+        let controller_data = ControllerData {
+            rmp_graphql_token: None,
+            name_id_map: HashMap::new(),
+            pinned_names: HashMap::new(),
+            merges: HashMap::new(),
+            last_refresh: HashMap::new(),
+            id_professor_map: HashMap::new(),
+            ratings_map: Lru::new(if self.store.is_some() { self.cache_size } else { 0 }),
+            evicted: HashSet::new(),
+            truncated: HashSet::new(),
+            missing_courses: HashMap::new(),
+            popularity: HashMap::new(),
+            metadata: HashMap::new(),
+            department_map: HashMap::new(),
+            professor_index: PrefixIndex::new(),
+            course_index: PrefixIndex::new(),
+            cross_listings: course::CrossListings::default(),
+            catalog_fetched_at: None,
+            last_crawl: None,
+            school_stats: None,
+        };
+
+        Ok(Controller {
+            data: Arc::new(Mutex::new(controller_data)),
+            token_scrape: Mutex::new(None),
+            school_id: self.school_id,
+            scorer: self.scorer,
+            store: self.store,
+            refresh_min_interval: self.refresh_min_interval,
+            ratings_ttl: self.ratings_ttl,
+            empty_ratings_ttl: self.empty_ratings_ttl,
+            ttl_jitter: self.ttl_jitter,
+            score_ttl: self.score_ttl,
+            identity_ttl: self.identity_ttl,
+            solr: self.solr,
+            graphql: self.graphql,
+            shedding: self.shedding,
+            crawl_departments: self.crawl_departments,
+            access: self.access,
+            schema_monitor: Arc::new(SchemaMonitor::new(self.alerts)),
+            hidden: self.hidden,
+            backend,
+            clock,
+            budget: self.budget,
+        })
+    }
+}
+
+impl Controller {
+    pub fn builder() -> ControllerBuilder {
+        ControllerBuilder::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn from_config(config: &AppConfig) -> Result<Self, Error> {
+        ControllerBuilder::from_config(config).build()
+    }
+
+    pub fn school_id(&self) -> u32 {
+        self.school_id
+    }
+
+    pub async fn load_from_store(&self) -> Result<usize, Error> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(0),
+        };
+
+        let professors = store.load_professors(self.school_id).await?;
+        let mappings = store.load_mappings(self.school_id).await?;
+        let merges = store.load_merges(self.school_id).await?;
+        let token = store.load_token(self.school_id).await?;
+        let count = professors.len();
+
+        let mut migrated = Vec::new();
+        {
+            let mut data = self.data.lock().await;
+            for professor in professors {
+                if self.access.permits(professor.rmp_id, professor.department.as_str()) {
+                    Self::_cache_professor(&mut data, professor);
+                }
+            }
+            for (stored, rmp_id) in mappings {
+                let key = name::canonical(stored.as_str());
+                if key != stored {
+                    migrated.push((stored, key.clone(), rmp_id));
+                }
+                data.pinned_names.insert(key, rmp_id);
+            }
+            data.merges.extend(merges);
+            if data.rmp_graphql_token.is_none() {
+                data.rmp_graphql_token = token;
+            }
+        }
+
+        for (stored, key, rmp_id) in migrated.iter() {
+            let moved = store.save_mapping(self.school_id, key.as_str(), *rmp_id).await
+                .and(store.delete_mapping(self.school_id, stored.as_str()).await);
+            if let Err(e) = moved {
+                println!("load_from_store: mapping {} store error {}", stored, e);
+            }
+        }
+        if !migrated.is_empty() {
+            println!("load_from_store: migrated {} name mappings to canonical keys", migrated.len());
+        }
+
+        Ok(count)
+    }
+
+    pub async fn merge_professors(&self, rmp_id: u32, duplicate_id: u32) -> Result<Professor, Error> {
+        if rmp_id == duplicate_id {
+            return Err(Error::BadRequest("a professor cannot be merged into itself".to_owned()));
+        }
+
+        let professor = {
+            let mut data = self.data.lock().await;
+
+            if data.merges.contains_key(&rmp_id) {
+                return Err(Error::BadRequest(format!("professor {} is already merged into another profile", rmp_id)));
+            }
+            if data.merges.values().any(|primary| *primary == duplicate_id) {
+                return Err(Error::BadRequest(format!("professor {} has profiles merged into it", duplicate_id)));
+            }
+            if !data.id_professor_map.contains_key(&duplicate_id) {
+                return Err(Error::BadRequest(format!("professor {} not found", duplicate_id)));
+            }
+
+            let professor = data.id_professor_map.get(&rmp_id).cloned()
+                .ok_or_else(|| Error::BadRequest(format!("professor {} not found", rmp_id)))?;
+
+            data.merges.insert(duplicate_id, rmp_id);
+            data.ratings_map.remove(&rmp_id);
+            data.evicted.remove(&rmp_id);
+            data.school_stats = None;
+
+            professor
+        };
+
+        if let Some(store) = &self.store {
+            store.save_merge(self.school_id, duplicate_id, rmp_id).await?;
+        }
+
+        professor.lock().await.score = None;
+        self._professor_ratings(rmp_id, Freshness { refresh: true, ..Freshness::default() }).await?;
+
+        let professor = professor.lock().await.clone();
+
+        Ok(professor)
+    }
+
+    pub async fn pin_name(&self, name: String, rmp_id: u32) -> Result<Professor, Error> {
+        let name = name::canonical(name.as_str());
+
+        let known = self.data.lock().await.id_professor_map.contains_key(&rmp_id);
+        if !known {
+            let res = self._search_professor(name.as_str()).await?;
+            let new_professors = {
+                let mut data = self.data.lock().await;
+                Self::_cache_professors(&mut data, res)
+            };
+            self._persist_professors(new_professors).await;
+        }
+
+        let professor = {
+            let mut data = self.data.lock().await;
+
+            let professor = data.id_professor_map.get(&rmp_id).cloned()
+                .ok_or_else(|| Error::BadRequest(format!("professor {} not found for {}", rmp_id, name)))?;
+            data.pinned_names.insert(name.clone(), rmp_id);
+
+            professor
+        };
+
+        if let Some(store) = &self.store {
+            store.save_mapping(self.school_id, name.as_str(), rmp_id).await?;
+        }
+
+        let professor = professor.lock().await.clone();
+
+        Ok(professor)
+    }
+
+    pub async fn graphql_token(&self) -> Result<String, Error> {
+        Ok(self._graphql_token(false, true).await?.token)
+    }
+
+    pub async fn graphql_token_info(&self, refresh: bool) -> Result<GraphQLToken, Error> {
+        self._graphql_token(refresh, false).await
+    }
+
+    pub async fn self_test(&self, query: &str) -> Vec<SelfTestStep> {
+        let mut steps = Vec::new();
+
+        let started = std::time::Instant::now();
+        let token = self._graphql_token(true, false).await;
+        steps.push(SelfTestStep {
+            step: "token",
+            ok: token.is_ok(),
+            detail: match &token {
+                Ok(token) => format!("scraped a {} character token", token.token.len()),
+                Err(e) => e.to_string(),
+            },
+            elapsed_ms: started.elapsed().as_millis(),
+        });
+        if token.is_err() {
+            return steps;
+        }
+
+        let started = std::time::Instant::now();
+        let professor = self._search_professor(query).await
+            .and_then(|res| res.into_iter().next().ok_or(Error::NotFound));
+        steps.push(SelfTestStep {
+            step: "search",
+            ok: professor.is_ok(),
+            detail: match &professor {
+                Ok(p) => format!("found {} ({})", p.full_name, p.id),
+                Err(e) => format!("no professor for {}: {}", query, e),
+            },
+            elapsed_ms: started.elapsed().as_millis(),
+        });
+        let rmp_id = match professor.map(|p| p.id.replace("teacher:", "").parse::<u32>()) {
+            Ok(Ok(rmp_id)) => rmp_id,
+            _ => return steps,
+        };
+
+        let started = std::time::Instant::now();
+        let page = self._ratings_page(rmp_id, None, None, false).await;
+        steps.push(SelfTestStep {
+            step: "ratings",
+            ok: page.is_ok(),
+            detail: match &page {
+                Ok(page) => format!("fetched {} ratings for {}", page.ratings.edges.len(), rmp_id),
+                Err(e) => e.to_string(),
+            },
+            elapsed_ms: started.elapsed().as_millis(),
+        });
+
+        steps
+    }
+
+    #[cfg(feature = "scheduler")]
+    pub async fn refresh_graphql_token(&self) -> Result<String, Error> {
+        Ok(self._graphql_token(true, false).await?.token)
+    }
+
+    async fn _graphql_token(&self, refresh: bool, count_use: bool) -> Result<GraphQLToken, Error> {
+        let requested_at = self.now();
+
+        if !refresh {
+            if let Some(token) = self._cached_token(None, count_use).await {
+                return Ok(token);
+            }
+        }
+
+        let mut last_failure = self.token_scrape.lock().await;
+
+        if let Some(token) = self._cached_token(if refresh { Some(requested_at) } else { None }, count_use).await {
+            return Ok(token);
+        }
+
+        if let Some(at) = *last_failure {
+            if (self.now() - at).num_seconds() < self.graphql.token_retry_secs {
+                println!("_graphql_token: last scrape failed at {}, not retrying yet", at);
+                return Err(Error::Rmp);
+            }
+        }
+
+        let token = self._scrape_token(count_use).await;
+        *last_failure = match token {
+            Ok(_) => None,
+            Err(_) => Some(self.now()),
+        };
+
+        token
+    }
+
+    async fn _cached_token(&self, scraped_since: Option<chrono::DateTime<chrono::Utc>>, count_use: bool) -> Option<GraphQLToken> {
+        let mut data = self.data.lock().await;
+
+        let token = data.rmp_graphql_token.as_mut()
+            .filter(|t| scraped_since.map(|since| t.scraped_at > since).unwrap_or(true))?;
+        if count_use {
+            token.uses += 1;
+        }
+
+        Some(token.clone())
+    }
+
+    async fn _scrape_token(&self, count_use: bool) -> Result<GraphQLToken, Error> {
+        let resp = self.backend.send(&OutboundRequest::get(Upstream::Token, self.graphql.token_url.as_str(), "graphql_token")).await?;
+        check_status(&resp, "graphql_token").map_err(|e| match e {
+            Error::Unauthorized => Error::Rmp,
+            e => e,
+        })?;
+        let resp = String::from_utf8_lossy(resp.body.as_slice()).into_owned();
+
+        let re = Regex::new("\"REACT_APP_GRAPHQL_AUTH\":\"(.*?)\"").map_err(|_| Error::Rmp)?;
+
+        if let Some(cap) = re.captures_iter(resp.as_str()).next() {
+            let token = GraphQLToken {
+                token: cap[1].to_string(),
+                scraped_at: self.now(),
+                uses: if count_use { 1 } else { 0 },
+            };
+
+            self.data.lock().await.rmp_graphql_token = Some(token.clone());
+
+            if let Some(store) = &self.store {
+                if let Err(e) = store.save_token(self.school_id, &token).await {
+                    println!("_scrape_token: store error {}", e);
+                }
+            }
+
+            return Ok(token);
+        }
+
+        Err(Error::Rmp)
+    }
+
+    #[cfg(feature = "scheduler")]
+    pub async fn refresh_expired(&self, pace: std::time::Duration) -> usize {
+        let ids: Vec<u32> = {
+            let data = self.data.lock().await;
+            let now = self.now();
+
+            let mut expired: Vec<(chrono::DateTime<chrono::Utc>, u32)> = data.ratings_map.iter()
+                .map(|(id, e)| (self._refresh_at(&data, *id, e, now), *id))
+                .filter(|(expires_at, _)| *expires_at < now)
+                .collect();
+            expired.sort();
+
+            expired.into_iter().map(|(_, id)| id).collect()
+        };
+
+        let mut count = 0;
+        for id in ids {
+            match self._professor_ratings(id, Freshness::default()).await {
+                Ok(_) => count += 1,
+                Err(e) => println!("refresh_expired: professor {} error {}", id, e),
+            }
+
+            tokio::time::delay_for(pace).await;
+        }
+
+        count
+    }
+
+    #[cfg(feature = "scheduler")]
+    pub async fn snapshot(&self) -> Vec<ProfessorSnapshot> {
+        let mut ratings: HashMap<u32, RatingsEntry> = self._all_ratings().await.into_iter().collect();
+        let entries: Vec<(Arc<Mutex<Professor>>, Option<RatingsEntry>)> = {
+            let data = self.data.lock().await;
+
+            data.id_professor_map.iter()
+                .map(|(id, p)| (p.clone(), ratings.remove(id)))
+                .collect()
+        };
+
+        let mut snapshot = Vec::new();
+        for (professor, entry) in entries {
+            let professor = professor.lock().await;
+
+            snapshot.push(ProfessorSnapshot {
+                school_id: self.school_id,
+                rmp_id: professor.rmp_id,
+                full_name: professor.full_name.clone(),
+                department: professor.department.clone(),
+                fetched_at: entry.as_ref().map(|e| e.fetched_at),
+                ratings: entry.map(|e| e.ratings).unwrap_or_default(),
+            });
+        }
+
+        snapshot
+    }
+
+    pub async fn dump_ratings(&self, department: Option<String>) -> Result<impl Stream<Item = Result<Vec<DumpedRating>, Error>>, Error> {
+        let store = self.store.clone().ok_or(Error::NotFound)?;
+
+        let professors: Vec<Professor> = store.load_professors(self.school_id).await?
+            .into_iter()
+            .filter(|p| self.access.permits(p.rmp_id, p.department.as_str()))
+            .filter(|p| department.as_ref().map(|d| department::matches(p.department.as_str(), d.as_str())).unwrap_or(true))
+            .collect();
+
+        let hidden = self.hidden.clone();
+
+        Ok(futures::stream::iter(professors).then(move |professor| {
+            let store = store.clone();
+            let hidden = hidden.clone();
+
+            async move {
+                let ratings = store.load_ratings(professor.rmp_id).await?
+                    .map(|stored| stored.ratings)
+                    .unwrap_or_default();
+
+                Ok(hidden.visible(ratings).into_iter()
+                    .map(|rating| DumpedRating {
+                        rmp_id: professor.rmp_id,
+                        department: professor.department.clone(),
+                        rating,
+                    })
+                    .collect())
+            }
+        }))
+    }
+
+    pub async fn professor_overview(&self, name: String, freshness: Freshness) -> Result<Arc<Mutex<Professor>>, Error> {
+        let freshness = self._limit_refresh(name.as_str(), freshness).await;
+
+        let pr = self._name_to_professor(name, freshness).await?;
+        let rmp_id = pr.lock().await.rmp_id;
+        self._record_request(rmp_id).await;
+
+        {
+            let professor = pr.lock().await;
+
+            if let Some(score) = &professor.score {
+                let expired = (self.now() - score.computed_at).num_seconds() > self.score_ttl;
+
+                if !expired && !freshness.is_stale(score.fetched_at, self.now()) {
+                    return Ok(pr.clone());
+                }
+            }
+        }
+
+        let fetched = match self._professor_ratings(rmp_id, freshness).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                let mut professor = pr.lock().await;
+
+                return match professor.score.as_mut() {
+                    Some(score) if freshness.allow_stale => {
+                        println!("professor_overview: serving stale score for {} after {}", rmp_id, e);
+                        score.stale = true;
+                        Ok(pr.clone())
+                    }
+                    _ => Err(e),
+                };
+            }
+        };
+
+        let fetched = fetched.map(|ratings| self.hidden.visible(ratings));
+        let fetched_at = fetched.fetched_at;
+        let stale = fetched.stale;
+        let num_ratings = fetched.value.len() as u32;
+
+        let resp: Vec<NormalizedRating> = fetched.value
+            .into_iter()
+            .filter(|r| self.scorer.accepts(r))
+            .collect();
+
+        let (score, weight) = self._weighted_score(&resp, SCORE_WINDOW);
+        let (score_yr, weight_yr) = self._weighted_score(&resp, 31536000);
+
+        let score = Score {
+            quality: if weight < 8.0 { None } else { Some(score / weight) },
+            quality_yr: if weight_yr < 2.0 { None } else { Some(score_yr / weight_yr) },
+            num_ratings,
+            insufficient_data: num_ratings == 0,
+            fetched_at,
+            computed_at: self.now(),
+            stale,
+        };
+
+        pr.lock().await.score = Some(score);
+
+        Ok(pr)
+    }
+
+    pub async fn dry_run_score(&self, name: String, params: &ScoreParams, freshness: Freshness) -> Result<DryRunScore, Error> {
+        let (professor, fetched) = self.professor_comments(name, None, freshness).await?;
+
+        let window_secs = params.window_secs.unwrap_or(SCORE_WINDOW);
+        let min_weight = params.min_weight.unwrap_or(8.0);
+        let algo = params.algo.unwrap_or(ScoreAlgo::Weighted);
+
+        let ratings: Vec<NormalizedRating> = fetched.value
+            .into_iter()
+            .filter(|r| self.scorer.accepts(r))
+            .collect();
+
+        let (score, weight, ratings_used) = self._score_with(&ratings, window_secs, algo);
+
+        Ok(DryRunScore {
+            rmp_id: professor.rmp_id,
+            quality: if weight < min_weight || weight == 0.0 { None } else { Some(score / weight) },
+            weight,
+            ratings_used,
+            window_secs,
+            min_weight,
+            algo,
+            fetched_at: fetched.fetched_at,
+        })
+    }
+
+    pub async fn professor_comments(&self, name: String, course: Option<String>, freshness: Freshness) -> Result<(Professor, Fetched<Vec<NormalizedRating>>), Error> {
+        let (professor, fetched) = self.unmoderated_comments(name, course, freshness).await?;
+
+        Ok((professor, fetched.map(|ratings| self.hidden.visible(ratings))))
+    }
+
+    pub async fn unmoderated_comments(&self, name: String, course: Option<String>, freshness: Freshness) -> Result<(Professor, Fetched<Vec<NormalizedRating>>), Error> {
+        let freshness = self._limit_refresh(name.as_str(), freshness).await;
+
+        let pr = self._name_to_professor(name, freshness).await?;
+        let professor = pr.lock().await.clone();
+        let rmp_id = professor.rmp_id;
+        self._record_request(rmp_id).await;
+
+        let fetched = self._professor_ratings(rmp_id, freshness).await?;
+
+        let course = match course {
+            Some(course) => course,
+            None => return Ok((professor, fetched)),
+        };
+
+        let aliases = self.data.lock().await.cross_listings.aliases(course.as_str());
+
+        let filtered: Vec<NormalizedRating> = fetched.value.iter()
+            .filter(|r| aliases.iter().any(|a| course::same_course(r.class.as_str(), a.as_str())))
+            .cloned()
+            .collect();
+
+        if !filtered.is_empty() {
+            return Ok((professor, self._with_catalog_source(Fetched { value: filtered, ..fetched }, aliases.len()).await));
+        }
+
+        if freshness.cache_only || !self._may_be_missing(rmp_id, course.as_str()).await {
+            return Ok((professor, self._with_catalog_source(Fetched { value: filtered, ..fetched }, aliases.len()).await));
+        }
+
+        let ratings = match self._merged_comments(rmp_id, Some(course.clone()), None).await {
+            Ok(ratings) => ratings,
+            Err(_) if fetched.stale => return Ok((professor, self._with_catalog_source(Fetched { value: filtered, ..fetched }, aliases.len()).await)),
+            Err(e) => return Err(e),
+        };
+        self._merge_course_ratings(rmp_id, course.as_str(), ratings.as_slice()).await;
+
+        Ok((professor, self._with_catalog_source(self._fetched(ratings, Source::Rmp), aliases.len()).await))
+    }
+
+    async fn _may_be_missing(&self, rmp_id: u32, course: &str) -> bool {
+        let data = self.data.lock().await;
+
+        let truncated = data.truncated.contains(&rmp_id) || data.merges.iter()
+            .any(|(duplicate, primary)| *primary == rmp_id && data.truncated.contains(duplicate));
+        let missing = data.missing_courses.get(&(rmp_id, course::compact_course(course)))
+            .map(|at| self._expires_at(rmp_id, *at, true) > self.now())
+            .unwrap_or(false);
+
+        truncated && !missing
+    }
+
+    async fn _merge_course_ratings(&self, rmp_id: u32, course: &str, ratings: &[NormalizedRating]) {
+        let now = self.now();
+        let mut data = self.data.lock().await;
+
+        if ratings.is_empty() {
+            data.missing_courses.retain(|(id, _), at| self._expires_at(*id, *at, true) > now);
+            data.missing_courses.insert((rmp_id, course::compact_course(course)), now);
+            return;
+        }
+
+        if let Some(entry) = data.ratings_map.get_mut(&rmp_id) {
+            entry.ratings = dedup_ratings(entry.ratings.iter().chain(ratings.iter()).cloned().collect());
+            data.school_stats = None;
+        }
+    }
+
+    pub async fn query_comments(&self, name: String, course: Option<String>, mut query: RatingsQuery, include_hidden: bool, freshness: Freshness) -> Result<Option<(Professor, Fetched<Vec<NormalizedRating>>, usize)>, Error> {
+        let store = match &self.store {
+            Some(store) => store.clone(),
+            None => return Ok(None),
+        };
+
+        let freshness = self._limit_refresh(name.as_str(), freshness).await;
+
+        let pr = self._name_to_professor(name, freshness).await?;
+        let professor = pr.lock().await.clone();
+        let rmp_id = professor.rmp_id;
+        self._record_request(rmp_id).await;
+
+        let fetched = self._professor_ratings(rmp_id, freshness).await?;
+
+        if let Some(course) = &course {
+            query.courses = self.data.lock().await.cross_listings.aliases(course.as_str());
+        }
+        if !include_hidden {
+            query.exclude = self.hidden.ids();
+        }
+
+        let page = store.query_ratings(rmp_id, &query).await?;
+        if page.total == 0 && !fetched.value.is_empty() {
+            return Ok(None);
+        }
+
+        let (total, aliases) = (page.total, query.courses.len());
+        let fetched = self._with_catalog_source(fetched.map(|_| page.ratings), aliases).await;
+
+        Ok(Some((professor, fetched, total)))
+    }
+
+    async fn _with_catalog_source<T>(&self, fetched: Fetched<T>, aliases: usize) -> Fetched<T> {
+        match self.catalog_fetched_at().await {
+            Some(at) if aliases > 1 => fetched.with_source(UCSB_SOURCE, at),
+            _ => fetched,
+        }
+    }
+
+    pub async fn teacher_metadata(&self, rmp_id: u32) -> Option<TeacherMetadata> {
+        self.data.lock().await.metadata.get(&rmp_id).cloned()
+    }
+
+    pub async fn catalog_fetched_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.data.lock().await.catalog_fetched_at
+    }
+
+    pub async fn sample_comments(&self, name: String, n: usize, seed: u64, freshness: Freshness) -> Result<Fetched<Vec<NormalizedRating>>, Error> {
+        let (_, fetched) = self.professor_comments(name, None, freshness).await?;
+
+        Ok(fetched.map(|ratings| {
+            let candidates: Vec<NormalizedRating> = ratings.into_iter()
+                .filter(|r| !r.comment.is_empty() && self.scorer.accepts(r))
+                .collect();
+
+            analysis::representative(candidates.as_slice(), n, &mut analysis::Rng::new(seed))
+        }))
+    }
+
+    pub async fn top_comments(&self, name: String, n: usize, freshness: Freshness) -> Result<Fetched<Vec<NormalizedRating>>, Error> {
+        let (_, fetched) = self.professor_comments(name, None, freshness).await?;
+
+        let offsetted = (self.now().timestamp() as u64).saturating_sub(SCORE_WINDOW);
+
+        let mut ranked: Vec<(f32, NormalizedRating)> = fetched.value.iter()
+            .filter(|r| !r.comment.is_empty() && self.scorer.accepts(r))
+            .map(|r| (Self::_rating_weight(r, offsetted, SCORE_WINDOW).unwrap_or(0.0), r.clone()))
+            .collect();
+
+        ranked.sort_by(|(a, ra), (b, rb)| b.partial_cmp(a)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(rb.net_thumbs().cmp(&ra.net_thumbs()))
+            .then(rb.date.cmp(&ra.date)));
+
+        let top = ranked.into_iter().take(n).map(|(_, r)| r).collect();
+
+        Ok(Fetched { value: top, ..fetched })
+    }
+
+    fn _expires_at(&self, rmp_id: u32, fetched_at: chrono::DateTime<chrono::Utc>, empty: bool) -> chrono::DateTime<chrono::Utc> {
+        let ttl = if empty { self.empty_ratings_ttl.min(self.ratings_ttl) } else { self.ratings_ttl };
+
+        let mut hasher = DefaultHasher::new();
+        (rmp_id, fetched_at.timestamp()).hash(&mut hasher);
+
+        let jitter = (hasher.finish() % (self.ttl_jitter.min(ttl) as u64 + 1)) as i64;
+
+        fetched_at + chrono::Duration::seconds(ttl + jitter)
+    }
+
+    #[cfg(feature = "scheduler")]
+    fn _refresh_at(&self, data: &ControllerData, rmp_id: u32, entry: &RatingsEntry, now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+        let fetched_at = entry.fetched_at;
+        let ttl = (self._expires_at(rmp_id, fetched_at, entry.ratings.is_empty()) - fetched_at).num_seconds() as f64;
+        let factor = data.popularity.get(&rmp_id)
+            .map(|p| p.ttl_factor(now))
+            .unwrap_or(2f64.powi(COLD_TTL_DOUBLINGS));
+
+        fetched_at + chrono::Duration::seconds((ttl * factor) as i64)
+    }
+
+    async fn _record_request(&self, rmp_id: u32) {
+        let now = self.now();
+        let mut data = self.data.lock().await;
+
+        let popularity = data.popularity.entry(rmp_id).or_insert(Popularity { score: 0.0, updated_at: now });
+        *popularity = Popularity {
+            score: popularity.at(now) + 1.0,
+            updated_at: now,
+        };
+    }
+
+    async fn _professor_ratings(&self, rmp_id: u32, freshness: Freshness) -> Result<Fetched<Vec<NormalizedRating>>, Error> {
+        {
+            let mut data = self.data.lock().await;
+            data.ratings_map.touch(&rmp_id);
+
+            if let Some(entry) = data.ratings_map.get(&rmp_id) {
+                let expired = self._expires_at(rmp_id, entry.fetched_at, entry.ratings.is_empty()) < self.now();
+
+                if !expired && !freshness.is_stale(entry.fetched_at, self.now()) {
+                    return Ok(Fetched::at(entry.ratings.clone(), Source::Cache, entry.fetched_at, false));
+                }
+            }
+        }
+
+        if let Some(store) = &self.store {
+            if let Ok(Some(stored)) = store.load_ratings(rmp_id).await {
+                let expired = self._expires_at(rmp_id, stored.fetched_at, stored.ratings.is_empty()) < self.now();
+
+                if !expired && !freshness.is_stale(stored.fetched_at, self.now()) {
+                    let fetched = Fetched::at(stored.ratings, Source::Store, stored.fetched_at, false);
+                    self._cache_ratings(rmp_id, &fetched).await;
+
+                    return Ok(fetched);
+                }
+            }
+        }
+
+        if freshness.cache_only {
+            return self._stale_ratings(rmp_id).await.ok_or(Error::Shedding(self.shedding.retry_after_secs));
+        }
+
+        let previous = self._stale_ratings(rmp_id).await;
+        let since = previous.as_ref()
+            .filter(|_| !freshness.refresh)
+            .and_then(|p| p.value.iter().map(|r| r.date).max())
+            .map(|newest| newest - chrono::Duration::seconds(INCREMENTAL_OVERLAP));
+
+        let mut ratings = match self._merged_comments(rmp_id, None, since).await {
+            Ok(ratings) => ratings,
+            Err(e) if freshness.allow_stale => {
+                return match previous {
+                    Some(previous) => Ok(previous),
+                    None => self._stale_ratings(rmp_id).await.ok_or(e),
+                };
+            }
+            Err(e) => return Err(e),
+        };
+        if let (Some(_), Some(previous)) = (since, &previous) {
+            println!("_professor_ratings: {} new or updated ratings for {}", ratings.len(), rmp_id);
+            ratings = dedup_ratings(ratings.into_iter().chain(previous.value.iter().cloned()).collect());
+        }
+        let fetched = self._fetched(ratings, Source::Rmp);
+
+        if previous.map(|p| ratings_hash(&p.value)) == Some(ratings_hash(&fetched.value)) {
+            println!("_professor_ratings: ratings for {} unchanged", rmp_id);
+            self._touch_ratings(rmp_id, &fetched).await;
+
+            if let Some(store) = &self.store {
+                if let Err(e) = store.touch_ratings(rmp_id, fetched.fetched_at).await {
+                    println!("_professor_ratings: store error {}", e);
+                }
+            }
+
+            return Ok(fetched);
+        }
+
+        self._cache_ratings(rmp_id, &fetched).await;
+
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save_ratings(rmp_id, fetched.value.as_slice(), fetched.fetched_at).await {
+                println!("_professor_ratings: store error {}", e);
+            }
+        }
+
+        Ok(fetched)
+    }
+
+    async fn _stale_ratings(&self, rmp_id: u32) -> Option<Fetched<Vec<NormalizedRating>>> {
+        {
+            let data = self.data.lock().await;
+
+            if let Some(entry) = data.ratings_map.get(&rmp_id) {
+                return Some(Fetched::at(entry.ratings.clone(), Source::Cache, entry.fetched_at, true));
+            }
+        }
+
+        let stored = match &self.store {
+            Some(store) => store.load_ratings(rmp_id).await.ok().flatten()?,
+            None => return None,
+        };
+
+        Some(Fetched::at(stored.ratings, Source::Store, stored.fetched_at, true))
+    }
+
+    async fn _cache_ratings(&self, rmp_id: u32, fetched: &Fetched<Vec<NormalizedRating>>) {
+        let mut data = self.data.lock().await;
+        let evicted = data.ratings_map.insert(rmp_id, RatingsEntry {
+            ratings: fetched.value.clone(),
+            fetched_at: fetched.fetched_at,
+        });
+        data.evicted.remove(&rmp_id);
+        data.evicted.extend(evicted);
+        data.school_stats = None;
+    }
+
+    async fn _touch_ratings(&self, rmp_id: u32, fetched: &Fetched<Vec<NormalizedRating>>) {
+        {
+            let mut data = self.data.lock().await;
+
+            if let Some(entry) = data.ratings_map.get_mut(&rmp_id) {
+                entry.fetched_at = fetched.fetched_at;
+                return;
+            }
+        }
+
+        self._cache_ratings(rmp_id, fetched).await;
+    }
+
+    async fn _cached_ratings(&self, rmp_id: u32) -> Option<Vec<NormalizedRating>> {
+        {
+            let data = self.data.lock().await;
+
+            if let Some(entry) = data.ratings_map.get(&rmp_id) {
+                return Some(entry.ratings.clone());
+            }
+            if !data.evicted.contains(&rmp_id) {
+                return None;
+            }
+        }
+
+        let store = self.store.as_ref()?;
+        store.load_ratings(rmp_id).await.ok().flatten().map(|stored| stored.ratings)
+    }
+
+    async fn _all_ratings(&self) -> Vec<(u32, RatingsEntry)> {
+        let (mut entries, evicted): (Vec<(u32, RatingsEntry)>, Vec<u32>) = {
+            let data = self.data.lock().await;
+
+            (data.ratings_map.iter()
+                .map(|(id, e)| (*id, RatingsEntry { ratings: e.ratings.clone(), fetched_at: e.fetched_at }))
+                .collect(), data.evicted.iter().cloned().collect())
+        };
+
+        if let Some(store) = &self.store {
+            for id in evicted {
+                match store.load_ratings(id).await {
+                    Ok(Some(stored)) => entries.push((id, RatingsEntry { ratings: stored.ratings, fetched_at: stored.fetched_at })),
+                    Ok(None) => {}
+                    Err(e) => println!("_all_ratings: store error {}", e),
+                }
+            }
+        }
+
+        entries
+    }
+
+    async fn _limit_refresh(&self, name: &str, freshness: Freshness) -> Freshness {
+        if self.budget.exhausted(self.now()) {
+            return Freshness { refresh: false, cache_only: true, ..freshness };
+        }
+
+        if !freshness.refresh {
+            return freshness;
+        }
+
+        let mut data = self.data.lock().await;
+        let now = self.now();
+        let key = name::canonical(name);
+
+        if let Some(last) = data.last_refresh.get(&key) {
+            if (now - *last).num_seconds() < self.refresh_min_interval {
+                return Freshness { refresh: false, ..freshness };
+            }
+        }
+
+        data.last_refresh.insert(key, now);
+        freshness
+    }
+
+    async fn _name_to_professor(&self, name: String, freshness: Freshness) -> Result<Arc<Mutex<Professor>>, Error> {
+        if name::is_trivial(name.as_str()) {
+            return Err(Error::BadRequest("professor name is empty or too short".to_owned()));
+        }
+
+        let name = name::canonical(name.as_str());
+
+        let cached = {
+            let data = self.data.lock().await;
+
+            if let Some(professor) = data.pinned_names.get(name.as_str()).and_then(|id| data.id_professor_map.get(id)) {
+                return Ok(professor.clone());
+            }
+
+            data.name_id_map.get(name.as_str())
+                .map(|e| (e.ids.first().cloned(), e.fetched_at))
+        };
+
+        let id_opt = if let Some((id, _)) = cached.filter(|(_, fetched_at)| {
+            (self.now() - *fetched_at).num_seconds() <= self.identity_ttl && !freshness.is_stale(*fetched_at, self.now())
+        }) {
+            id
+        } else if freshness.cache_only {
+            match cached {
+                Some((id, _)) => id,
+                None => return Err(Error::Shedding(self.shedding.retry_after_secs)),
+            }
+        } else {
+            let res = match self._search_professor(name.as_str()).await {
+                Ok(res) => res,
+                Err(_) if freshness.allow_stale && cached.is_some() => {
+                    let data = self.data.lock().await;
+
+                    return cached.and_then(|(id, _)| id)
+                        .and_then(|id| data.id_professor_map.get(&Self::_merged_id(&data, id)).cloned())
+                        .ok_or(Error::NotFound);
+                }
+                Err(e) => return Err(e),
+            };
+
+            let ids: Vec<u32> = res.iter()
+                .map(|r| &r.id)
+                .map(|r| r.replace("teacher:", ""))
+                .filter_map(|r| r.parse::<u32>().ok())
+                .collect();
+
+            let id_opt = ids.first().cloned();
+
+            let new_professors = {
+                let mut data = self.data.lock().await;
+
+                data.name_id_map.insert(name, NameEntry {
+                    ids,
+                    fetched_at: self.now(),
+                });
+
+                Self::_cache_professors(&mut data, res)
+            };
+
+            self._persist_professors(new_professors).await;
+
+            id_opt
+        };
+
+        let data = self.data.lock().await;
+
+        id_opt
+            .and_then(|id| data.id_professor_map.get(&Self::_merged_id(&data, id)).cloned())
+            .ok_or(Error::NotFound)
+    }
+
+    fn _merged_id(data: &ControllerData, rmp_id: u32) -> u32 {
+        data.merges.get(&rmp_id).cloned().unwrap_or(rmp_id)
+    }
+
+    pub async fn confidence(&self, query: &str, professor: &Professor) -> f32 {
+        let normalized = name::canonical(query);
+
+        if self.data.lock().await.pinned_names.get(normalized.as_str()) == Some(&professor.rmp_id) {
+            return 1.0;
+        }
+
+        name::confidence(query, professor.full_name.as_str())
+    }
+
+    pub async fn candidates(&self, query: &str) -> Vec<Professor> {
+        let normalized = name::canonical(query);
+
+        let professors: Vec<Arc<Mutex<Professor>>> = {
+            let data = self.data.lock().await;
+
+            data.name_id_map.get(normalized.as_str())
+                .map(|e| e.ids.iter().filter_map(|id| data.id_professor_map.get(id).cloned()).collect())
+                .unwrap_or_default()
+        };
+
+        let mut candidates = Vec::new();
+        for professor in professors {
+            candidates.push(professor.lock().await.clone());
+        }
+
+        candidates
+    }
+
+    pub async fn professor_matches(&self, name: String, limit: usize, freshness: Freshness) -> Result<Vec<(Professor, Vec<CourseCount>)>, Error> {
+        self._name_to_professor(name.clone(), freshness).await?;
+
+        let mut matches = Vec::new();
+        for professor in self.candidates(name.as_str()).await {
+            let courses = self._cached_ratings(professor.rmp_id).await
+                .map(|ratings| stats::top_courses(self.hidden.visible(ratings).as_slice(), limit))
+                .unwrap_or_default();
+
+            matches.push((professor, courses));
+        }
+
+        Ok(matches)
+    }
+
+    pub async fn autocomplete_professor(&self, query: String, limit: usize) -> Fetched<Vec<Professor>> {
+        let query = name::canonical(query.as_str());
+        if query.is_empty() {
+            return self._fetched(Vec::new(), Source::Cache);
+        }
+
+        let mut ids = self.data.lock().await.professor_index.search(query.as_str(), limit);
+        let mut source = Source::Cache;
+
+        if ids.is_empty() && !self.shedding.enabled && !self.budget.exhausted(self.now()) {
+            source = Source::Rmp;
+
+            if let Ok(res) = self._search_professor(query.as_str()).await {
+                let new_professors = {
+                    let mut data = self.data.lock().await;
+                    let new_professors = Self::_cache_professors(&mut data, res);
+                    ids = data.professor_index.search(query.as_str(), limit);
+
+                    new_professors
+                };
+
+                self._persist_professors(new_professors).await;
+            }
+        }
+
+        let professor_locks: Vec<Arc<Mutex<Professor>>> = {
+            let data = self.data.lock().await;
+
+            ids.iter()
+                .filter(|id| !data.merges.contains_key(id))
+                .filter_map(|id| data.id_professor_map.get(id).cloned())
+                .collect()
+        };
+
+        let mut professors = Vec::new();
+        for pr in professor_locks {
+            professors.push(pr.lock().await.clone());
+        }
+
+        self._fetched(professors, source)
+    }
+
+    pub async fn professors_by_last_name(&self, last_name: String, freshness: Freshness) -> Fetched<Vec<Professor>> {
+        let last_name = name::canonical(last_name.as_str());
+        let freshness = self._limit_refresh(last_name.as_str(), freshness).await;
+        let mut source = Source::Cache;
+
+        let searched_at = self.data.lock().await.name_id_map.get(last_name.as_str()).map(|e| e.fetched_at);
+        let fresh = searched_at.map(|at| {
+            (self.now() - at).num_seconds() <= self.identity_ttl && !freshness.is_stale(at, self.now())
+        }).unwrap_or(false);
+
+        if !fresh && !freshness.cache_only && !self.shedding.enabled && !self.budget.exhausted(self.now()) {
+            if let Ok(res) = self._search_professor(last_name.as_str()).await {
+                let ids: Vec<u32> = res.iter()
+                    .filter_map(|r| r.id.replace("teacher:", "").parse::<u32>().ok())
+                    .collect();
+
+                let new_professors = {
+                    let mut data = self.data.lock().await;
+                    data.name_id_map.insert(last_name.clone(), NameEntry {
+                        ids,
+                        fetched_at: self.now(),
+                    });
+
+                    Self::_cache_professors(&mut data, res)
+                };
+                self._persist_professors(new_professors).await;
+                source = Source::Rmp;
+            }
+        }
+
+        let professor_locks: Vec<Arc<Mutex<Professor>>> = {
+            let data = self.data.lock().await;
+
+            data.professor_index.get(last_name.as_str()).iter()
+                .filter(|id| !data.merges.contains_key(id))
+                .filter_map(|id| data.id_professor_map.get(id).cloned())
+                .collect()
+        };
+
+        let mut professors = Vec::new();
+        for pr in professor_locks {
+            let professor = pr.lock().await;
+            if name::canonical(professor.last_name.as_str()) == last_name {
+                professors.push(professor.clone());
+            }
+        }
+
+        professors.sort_by_key(|p| std::cmp::Reverse(p.num_ratings));
+
+        self._fetched(professors, source)
+    }
+
+    pub async fn warm(&self, entry: &str) -> Result<(), Error> {
+        match entry.parse::<u32>() {
+            Ok(rmp_id) => {
+                let professor = self.data.lock().await.id_professor_map.get(&rmp_id).cloned().ok_or(Error::NotFound)?;
+                let department = professor.lock().await.department.clone();
+                if !self.access.permits(rmp_id, department.as_str()) {
+                    return Err(Error::NotFound);
+                }
+
+                self._professor_ratings(rmp_id, Freshness::default()).await.map(|_| ())
+            }
+            Err(_) => self.professor_overview(entry.to_owned(), Freshness::default()).await.map(|_| ()),
+        }
+    }
+
+    pub async fn crawl(&self, pace: std::time::Duration) -> Result<usize, Error> {
+        let res = if self.crawl_departments.is_empty() {
+            self._search_professor("*:*").await?
+        } else {
+            let mut res = Vec::new();
+            for department in self.crawl_departments.iter() {
+                res.extend(self._search_department("*:*", Some(department.as_str())).await?);
+            }
+
+            res
+        };
+
+        let ids: Vec<u32> = res.iter()
+            .filter_map(|r| r.id.replace("teacher:", "").parse::<u32>().ok())
+            .collect();
+
+        let new_professors = {
+            let mut data = self.data.lock().await;
+            Self::_cache_professors(&mut data, res)
+        };
+        self._persist_professors(new_professors).await;
+
+        for id in ids.iter() {
+            if let Err(e) = self._professor_ratings(*id, Freshness::default()).await {
+                println!("crawl: professor {} error {}", id, e);
+            }
+
+            tokio::time::delay_for(pace).await;
+        }
+
+        let mut data = self.data.lock().await;
+        data.last_crawl = Some(self.now());
+
+        Ok(ids.len())
+    }
+
+    pub async fn forget_scores(&self) {
+        let professors: Vec<Arc<Mutex<Professor>>> = {
+            let mut data = self.data.lock().await;
+            data.school_stats = None;
+
+            data.id_professor_map.values().cloned().collect()
+        };
+
+        for professor in professors {
+            professor.lock().await.score = None;
+        }
+    }
+
+    pub async fn school_stats(&self) -> SchoolStats {
+        if let Some(stats) = &self.data.lock().await.school_stats {
+            return stats.clone();
+        }
+
+        let ratings = self._all_ratings().await;
+        let mut data = self.data.lock().await;
+
+        let visible: Vec<(String, Vec<NormalizedRating>)> = ratings.into_iter()
+            .map(|(id, e)| (data.department_map.get(&id).cloned().unwrap_or_default(), self.hidden.visible(e.ratings)))
+            .collect();
+        let rating_sets: Vec<(String, &[NormalizedRating])> = visible.iter()
+            .map(|(department, ratings)| (department.clone(), ratings.as_slice()))
+            .collect();
+        let stats = SchoolStats::compute(data.id_professor_map.len(), rating_sets.as_slice(), data.last_crawl, self.now());
+
+        data.school_stats = Some(stats.clone());
+        stats
+    }
+
+    pub async fn grade_vs_quality(&self, department: Option<String>) -> GradeQualityReport {
+        let professors = self._rated_professors(department).await;
+
+        GradeQualityReport::compute(professors.iter()
+            .map(|(rmp_id, full_name, department, ratings)| (*rmp_id, full_name.clone(), department.clone(), ratings.as_slice()))
+            .collect(), self.now())
+    }
+
+    pub async fn tag_professors(&self, tag: &str, department: Option<String>, limit: usize) -> TagReport {
+        let professors = self._rated_professors(department).await;
+
+        TagReport::compute(tag, professors.iter()
+            .map(|(rmp_id, full_name, department, ratings)| (*rmp_id, full_name.clone(), department.clone(), ratings.as_slice()))
+            .collect(), limit, self.now())
+    }
+
+    async fn _rated_professors(&self, department: Option<String>) -> Vec<(u32, String, String, Vec<NormalizedRating>)> {
+        let ratings = self._all_ratings().await;
+        let professors: Vec<(Arc<Mutex<Professor>>, String, Vec<NormalizedRating>)> = {
+            let data = self.data.lock().await;
+
+            ratings.into_iter()
+                .filter_map(|(id, e)| {
+                    let professor_department = data.department_map.get(&id).cloned().unwrap_or_default();
+
+                    if let Some(department) = &department {
+                        if !department::matches(professor_department.as_str(), department.as_str()) {
+                            return None;
+                        }
+                    }
+
+                    data.id_professor_map.get(&id).map(|p| (p.clone(), professor_department, self.hidden.visible(e.ratings)))
+                })
+                .collect()
+        };
+
+        let mut rows = Vec::new();
+        for (professor, professor_department, ratings) in professors {
+            let professor = professor.lock().await;
+            rows.push((professor.rmp_id, professor.full_name.clone(), professor_department, ratings));
+        }
+
+        rows
+    }
+
+    pub async fn professor_percentiles(&self, rmp_id: u32) -> (Option<f32>, Option<f32>) {
+        let stats = self.school_stats().await;
+        let ratings = self._cached_ratings(rmp_id).await;
+
+        let data = self.data.lock().await;
+        let quality = ratings.and_then(|ratings| stats::mean_quality(self.hidden.visible(ratings).as_slice()));
+        let department = data.department_map.get(&rmp_id);
+
+        match (quality, department) {
+            (Some(quality), Some(department)) => (
+                stats.department_percentile(department.as_str(), quality),
+                stats.school_percentile(quality),
+            ),
+            (Some(quality), None) => (None, stats.school_percentile(quality)),
+            _ => (None, None),
+        }
+    }
+
+    pub async fn autocomplete_course(&self, query: String, limit: usize) -> Fetched<Vec<String>> {
+        let data = self.data.lock().await;
+        let fetched = self._fetched(data.course_index.search(course::compact_course(query.as_str()).as_str(), limit), Source::Cache);
+
+        match data.catalog_fetched_at {
+            Some(at) => fetched.with_source(UCSB_SOURCE, at),
+            None => fetched,
+        }
+    }
+
+    pub async fn correct_departments(&self, subjects: &HashMap<String, String>) -> usize {
+        let professors: Vec<Arc<Mutex<Professor>>> = self.data.lock().await.id_professor_map.values().cloned().collect();
+
+        let mut by_name: HashMap<(String, char), Vec<Arc<Mutex<Professor>>>> = HashMap::new();
+        for professor in professors.iter() {
+            let p = professor.lock().await;
+
+            if let Some(initial) = name::fold(p.first_name.as_str()).chars().next() {
+                by_name.entry((name::compact(p.last_name.as_str()), initial)).or_default().push(professor.clone());
+            }
+        }
+
+        let mut corrected = Vec::new();
+        for (instructor, subject) in subjects {
+            let mut tokens: Vec<&str> = instructor.split_whitespace().collect();
+            let initial = match tokens.pop().and_then(|t| name::fold(t).chars().next()) {
+                Some(initial) if !tokens.is_empty() => initial,
+                _ => continue,
+            };
+
+            let department = match department::resolve(subject.as_str()) {
+                Some(d) => d.rmp_name,
+                None => continue,
+            };
+
+            let professor = match by_name.get(&(name::compact(tokens.join(" ").as_str()), initial)) {
+                Some(matches) if matches.len() == 1 => matches[0].clone(),
+                _ => continue,
+            };
+
+            let mut p = professor.lock().await;
+            let original = p.rmp_department.clone().unwrap_or_else(|| p.department.clone());
+
+            if department::matches(original.as_str(), department) {
+                continue;
+            }
+
+            if p.department != department {
+                p.department = department.to_owned();
+                p.rmp_department = Some(original);
+                corrected.push((p.rmp_id, department::canonical(department)));
+            }
+        }
+
+        if !corrected.is_empty() {
+            let mut data = self.data.lock().await;
+            data.department_map.extend(corrected.iter().cloned());
+            data.school_stats = None;
+        }
+
+        corrected.len()
+    }
+
+    pub async fn index_courses(&self, courses: Vec<String>) {
+        let mut data = self.data.lock().await;
+
+        for c in courses {
+            if let Some(code) = course::normalize_course(c.as_str()) {
+                data.course_index.insert(course::compact_course(code.as_str()).as_str(), code);
+            }
+        }
+    }
+
+    pub async fn index_cross_listings(&self, cross_listings: course::CrossListings) {
+        let mut data = self.data.lock().await;
+
+        data.cross_listings = cross_listings;
+        data.catalog_fetched_at = Some(self.now());
+    }
+
+    fn _cache_professors(data: &mut ControllerData, res: Vec<ProfessorResponse>) -> Vec<Professor> {
+        let mut new_professors = Vec::new();
+
+        for pr in res {
+            if let Ok(id) = pr.id.replace("teacher:", "").parse::<u32>() {
+                let professor = Professor {
+                    rmp_id: id,
+                    score: None,
+                    rmp_quality: pr.score,
+                    num_ratings: pr.num_ratings,
+                    first_name: pr.first_name,
+                    last_name: pr.last_name,
+                    full_name: pr.full_name,
+                    department: pr.department,
+                    rmp_department: None,
+                };
+
+                if Self::_cache_professor(data, professor.clone()) {
+                    new_professors.push(professor);
+                }
+            }
+        }
+
+        new_professors
+    }
+
+    fn _cache_professor(data: &mut ControllerData, professor: Professor) -> bool {
+        let id = professor.rmp_id;
+
+        if data.id_professor_map.contains_key(&id) {
+            return false;
+        }
+
+        data.professor_index.insert(name::canonical(professor.full_name.as_str()).as_str(), id);
+        data.professor_index.insert(name::canonical(professor.last_name.as_str()).as_str(), id);
+        data.professor_index.insert(name::canonical(professor.first_name.as_str()).as_str(), id);
+        data.department_map.insert(id, department::canonical(professor.department.as_str()));
+
+        data.id_professor_map.insert(id, Arc::new(Mutex::new(professor)));
+
+        true
+    }
+
+    async fn _persist_professors(&self, professors: Vec<Professor>) {
+        if let Some(store) = &self.store {
+            for professor in professors {
+                if let Err(e) = store.save_professor(self.school_id, &professor).await {
+                    println!("_persist_professors: store error {}", e);
+                }
+            }
+        }
+    }
+
+    fn _parse<T: DeserializeOwned>(&self, body: &[u8], what: &str, required: &[&str]) -> Result<T, Error> {
+        let parsed = parse_guarded(body, what, required);
+
+        let error = match &parsed {
+            Err(Error::RmpSchema(reason)) => Some(reason.as_str()),
+            _ => None,
+        };
+        if let Some(alert) = self.schema_monitor.record(what, error, self.now()) {
+            let monitor = self.schema_monitor.clone();
+            actix_rt::spawn(async move { monitor.fire(alert).await });
+        }
+
+        parsed
+    }
+
+    async fn _search_professor(&self, name: &str) -> Result<Vec<ProfessorResponse>, Error> {
+        self._search_department(name, None).await
+    }
+
+    async fn _search_department(&self, name: &str, department: Option<&str>) -> Result<Vec<ProfessorResponse>, Error> {
+        let url = solr_url(self.school_id, name, department, &self.solr)?;
+        let resp = self.backend.send(&OutboundRequest::get(Upstream::Solr, url.as_str(), "_search_professor")).await?;
+        check_status(&resp, "_search_professor").map_err(|e| match e {
+            Error::Unauthorized => Error::Rmp,
+            e => e,
+        })?;
+        let resp: RMPResponse = self._parse(resp.body.as_slice(), "_search_professor", &["/grouped/content_type_s/groups"])?;
+
+        let grouped: Vec<GroupResponse> = resp.grouped.inner.groups;
+
+        if let Some(teacher_group) = grouped.iter()
+            .find(|g| g.group_name == "TEACHER") {
+            return Ok(teacher_group
+                .doc_list
+                .docs
+                .iter()
+                .filter(|p| p.id.replace("teacher:", "").parse::<u32>()
+                    .map(|id| self.access.permits(id, p.department.as_str()))
+                    .unwrap_or(false))
+                .cloned()
+                .collect());
+        }
+
+        Ok(Vec::new())
+    }
+
+    async fn _merged_comments(&self, rmp_id: u32, course: Option<String>, since: Option<chrono::DateTime<chrono::Utc>>) -> Result<Vec<NormalizedRating>, Error> {
+        let duplicates: Vec<u32> = self.data.lock().await.merges.iter()
+            .filter(|(_, primary)| **primary == rmp_id)
+            .map(|(duplicate, _)| *duplicate)
+            .collect();
+
+        let mut ratings = self._professor_comments(rmp_id, course.clone(), since).await?;
+        if duplicates.is_empty() {
+            return Ok(ratings);
+        }
+
+        for duplicate in duplicates {
+            ratings.extend(self._professor_comments(duplicate, course.clone(), since).await?);
+        }
+
+        Ok(dedup_ratings(ratings))
+    }
+
+    async fn _professor_comments(&self, rmp_id: u32, course: Option<String>, since: Option<chrono::DateTime<chrono::Utc>>) -> Result<Vec<NormalizedRating>, Error> {
+        let mut ratings = Vec::new();
+        let mut cursor = None;
+        let mut complete = false;
+
+        for _ in 0..self.graphql.max_pages {
+            let page = match self._ratings_page(rmp_id, course.clone(), cursor.clone(), false).await {
+                Err(Error::Unauthorized) => {
+                    println!("_professor_comments: token rejected, refreshing");
+                    self._ratings_page(rmp_id, course.clone(), cursor.clone(), true).await
+                }
+                page => page,
+            };
+
+            let page = page.map_err(|e| match e {
+                Error::Unauthorized => Error::Rmp,
+                e => e,
+            })?;
+            if cursor.is_none() {
+                self.data.lock().await.metadata.insert(rmp_id, page.metadata());
+            }
+            let page = page.ratings;
+
+            let count = ratings.len();
+            ratings.extend(page.edges.iter()
+                .map(|r| NormalizedRating::from(&r.node))
+                .filter(|r| since.map(|since| r.date >= since).unwrap_or(true)));
+
+            if ratings.len() - count < page.edges.len() {
+                complete = true;
+                break;
+            }
+
+            cursor = match page.page_info {
+                Some(PageInfo { has_next_page: true, end_cursor: Some(end_cursor) }) if !page.edges.is_empty() => Some(end_cursor),
+                _ => {
+                    complete = true;
+                    break;
+                }
+            };
+        }
+
+        if course.is_none() {
+            let mut data = self.data.lock().await;
+
+            if !complete {
+                println!("_professor_comments: stopped after {} pages for {}", self.graphql.max_pages, rmp_id);
+                data.truncated.insert(rmp_id);
+            } else if since.is_none() {
+                data.truncated.remove(&rmp_id);
+            }
+        }
+
+        self.index_courses(ratings.iter().filter_map(|r| r.course.clone()).collect()).await;
+
+        Ok(dedup_ratings(ratings))
+    }
+
+    async fn _ratings_page(&self, rmp_id: u32, course: Option<String>, cursor: Option<String>, refresh: bool) -> Result<InnerCommentsNodeResponse, Error> {
+        let token = if refresh {
+            self._graphql_token(true, true).await?.token
+        } else {
+            self.graphql_token().await?
+        };
+
+        let request = serde_json::to_value(&GraphQLRequest {
+            query: self.graphql.query.clone(),
+            variables: GraphQLVariables {
+                id: rmp_ids(rmp_id).rmp_node_id,
+                count: self.graphql.page_size,
+                cursor,
+                course_filter: course,
+            },
+        }).map_err(|e| Error::Config(e.to_string()))?;
+
+        let request = OutboundRequest::post(Upstream::Graphql, self.graphql.url.as_str(), format!("Basic {}", token), request, "_professor_comments");
+        let resp = self.backend.send(&request).await?;
+        check_status(&resp, "_professor_comments")?;
+
+        let resp: CommentsResponse = self._parse(resp.body.as_slice(), "_professor_comments", &["/data/node/ratings/edges"])?;
+
+        Ok(resp.data.node)
+    }
+
+    pub fn now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.clock.now()
+    }
+
+    fn _fetched<T>(&self, value: T, source: Source) -> Fetched<T> {
+        Fetched::at(value, source, self.now(), false)
+    }
+
+    fn _weighted_score(&self, data: &[NormalizedRating], offset: u64) -> (f32, f32) {
+        let (sum, weight, _) = self._score_with(data, offset, ScoreAlgo::Weighted);
+
+        (sum, weight)
+    }
+
+    fn _score_with(&self, data: &[NormalizedRating], offset: u64, algo: ScoreAlgo) -> (f32, f32, usize) {
+        let now = self.now().timestamp() as u64;
+
+        Self::_score_at(data, now, offset, algo)
+    }
+
+    fn _score_at(data: &[NormalizedRating], now: u64, offset: u64, algo: ScoreAlgo) -> (f32, f32, usize) {
+        let mut quality_ratings_sum = 0.0;
+        let mut total_weight = 0.0;
+        let mut used = 0;
+
+        let offsetted = now.saturating_sub(offset);
+
+        for r in data {
+            let weight = match algo {
+                ScoreAlgo::Weighted => Self::_rating_weight(r, offsetted, offset),
+                ScoreAlgo::Recency => Self::_time_weight(r, offsetted, offset),
+                ScoreAlgo::Mean => Self::_time_weight(r, offsetted, offset).map(|_| 1.0),
+            };
+
+            let avg_weight = match weight {
+                Some(weight) => weight,
+                None => continue,
+            };
+
+            total_weight += avg_weight;
+            quality_ratings_sum += r.quality() * avg_weight;
+            used += 1;
+        }
+
+        (quality_ratings_sum, total_weight, used)
+    }
+
+    fn _time_weight(r: &NormalizedRating, offsetted: u64, offset: u64) -> Option<f32> {
+        if (r.date.timestamp() as u64) < offsetted {
+            return None;
+        }
+
+        Some(((r.date.timestamp() as u64 - offsetted) as f64 / offset as f64) as f32)
+    }
+
+    fn _rating_weight(r: &NormalizedRating, offsetted: u64, offset: u64) -> Option<f32> {
+        let time_weight = Self::_time_weight(r, offsetted, offset)?;
+        let thumbs_weight = (r.thumbs_up + 1) as f32 / (r.thumbs_up + r.thumbs_down + 1) as f32;
+        let quantity_weight = ((r.thumbs_up + r.thumbs_down) as f32 / 2.0).ln_1p() + 1.0;
+
+        Some(thumbs_weight * time_weight * quantity_weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{self, MockRmp, Reply};
+    use crate::sim::{self, Distribution, RatingSim};
+    use futures::future::join_all;
+    use std::time::Duration;
+
+    const NAMES: &[(&str, &str)] = &[
+        ("Ada", "Lovelace"),
+        ("Alan", "Turing"),
+        ("Grace", "Hopper"),
+        ("Edsger", "Dijkstra"),
+    ];
+
+    fn rating(days_ago: i64, clarity: u32) -> NormalizedRating {
+        sim::rating(chrono::Utc::now() - chrono::Duration::days(days_ago), clarity, clarity, 0, 0)
+    }
+
+    const GOLDEN_SIMS: &[(u64, usize, i64, Distribution, u32)] = &[
+        (1, 20, 5 * 365, Distribution::Uniform, 0),
+        (2, 50, 5 * 365, Distribution::Bimodal, 10),
+        (3, 8, 365, Distribution::Skewed(4), 3),
+        (4, 200, 10 * 365, Distribution::Skewed(2), 25),
+        (5, 30, 90, Distribution::Constant(5), 1),
+    ];
+
+    fn golden_scores(algo: ScoreAlgo) -> Vec<(f32, f32, usize)> {
+        GOLDEN_SIMS.iter()
+            .map(|&(seed, count, span_days, quality, max_thumbs)| {
+                let sim = RatingSim::new(seed).count(count).span_days(span_days).quality(quality).max_thumbs(max_thumbs);
+                Controller::_score_at(&sim.generate(), sim.now_secs(), SCORE_WINDOW, algo)
+            })
+            .collect()
+    }
+
+    fn assert_golden(algo: ScoreAlgo, expected: &[(f32, f32, usize)]) {
+        for (i, ((sum, weight, used), (quality, expected_weight, expected_used))) in golden_scores(algo).into_iter().zip(expected.iter()).enumerate() {
+            assert_eq!(used, *expected_used, "{:?} sim {}", algo, i);
+            assert!((weight - expected_weight).abs() < 1e-3, "{:?} sim {}: weight {} != {}", algo, i, weight, expected_weight);
+            assert!((sum / weight - quality).abs() < 1e-3, "{:?} sim {}: quality {} != {}", algo, i, sum / weight, quality);
+        }
+    }
+
+    #[test]
+    fn weighted_score_is_golden() {
+        assert_golden(ScoreAlgo::Weighted, &[(2.7987, 7.93, 20), (3.0886, 35.2079, 50), (3.6568, 9.0925, 8), (2.0912, 105.8378, 99), (5.0, 32.4561, 30)]);
+    }
+
+    #[test]
+    fn recency_score_is_golden() {
+        assert_golden(ScoreAlgo::Recency, &[(2.7987, 7.93, 20), (3.1185, 23.196, 50), (3.625, 7.0732, 8), (2.0629, 53.1991, 99), (5.0, 29.2302, 30)]);
+    }
+
+    #[test]
+    fn mean_score_is_golden() {
+        assert_golden(ScoreAlgo::Mean, &[(3.0, 20.0, 20), (3.03, 50.0, 50), (3.625, 8.0, 8), (2.0455, 99.0, 99), (5.0, 30.0, 30)]);
+    }
+
+    #[test]
+    fn weighted_score_uses_weighted_algo() {
+        use chrono::TimeZone;
+
+        let now = chrono::Utc.ymd(2024, 1, 1).and_hms(0, 0, 0);
+        let sim = RatingSim::new(7).count(40).max_thumbs(5).now(now);
+        let ratings = sim.generate();
+        let controller = Controller::builder()
+            .clock(mock::MockClock::at(now))
+            .build()
+            .unwrap();
+
+        let (sum, weight) = controller._weighted_score(&ratings, SCORE_WINDOW);
+        let (expected_sum, expected_weight, _) = Controller::_score_at(&ratings, sim.now_secs(), SCORE_WINDOW, ScoreAlgo::Weighted);
+
+        assert_eq!((sum, weight), (expected_sum, expected_weight));
+    }
+
+    #[test]
+    fn simulated_ratings_are_deterministic() {
+        let a = RatingSim::new(42).count(25).max_thumbs(9).generate();
+        let b = RatingSim::new(42).count(25).max_thumbs(9).generate();
+        let c = RatingSim::new(43).count(25).max_thumbs(9).generate();
+
+        let key = |r: &NormalizedRating| (r.date, r.clarity, r.helpful, r.thumbs_up, r.thumbs_down);
+        assert!(a.iter().map(key).eq(b.iter().map(key)));
+        assert!(!a.iter().map(key).eq(c.iter().map(key)));
+    }
+
+    async fn seeded_controller() -> Controller {
+        let controller = Controller::from_config(&AppConfig::default()).unwrap();
+
+        {
+            let mut data = controller.data.lock().await;
+            let now = chrono::Utc::now();
+
+            for (i, (first, last)) in NAMES.iter().enumerate() {
+                let id = i as u32 + 1;
+                let full_name = format!("{} {}", first, last);
+
+                data.name_id_map.insert(name::canonical(full_name.as_str()), NameEntry {
+                    ids: vec![id],
+                    fetched_at: now,
+                });
+                data.ratings_map.insert(id, RatingsEntry {
+                    ratings: (0..20).map(|d| rating(d * 10, 4)).collect(),
+                    fetched_at: now,
+                });
+
+                Controller::_cache_professor(&mut data, Professor {
+                    rmp_id: id,
+                    score: None,
+                    rmp_quality: None,
+                    num_ratings: Some(20),
+                    first_name: first.to_string(),
+                    last_name: last.to_string(),
+                    full_name,
+                    department: "Computer Science".to_owned(),
+                    rmp_department: None,
+                });
+            }
+        }
+
+        controller
+    }
+
+    #[actix_rt::test]
+    async fn concurrent_requests_complete() {
+        let controller = seeded_controller().await;
+
+        let requests = (0..64).map(|i| {
+            let (first, last) = NAMES[i % NAMES.len()];
+            let name = format!("{} {}", first, last);
+            let controller = &controller;
+
+            async move {
+                match i % 3 {
+                    0 => controller.professor_overview(name, Freshness::default()).await.map(|_| ()),
+                    1 => controller.professor_comments(name, Some("CMPSC 130A".to_owned()), Freshness::default()).await.map(|_| ()),
+                    _ => {
+                        controller.autocomplete_professor(last.to_owned(), 10).await;
+                        Ok(())
+                    }
+                }
+            }
+        });
+
+        let results = tokio::time::timeout(Duration::from_secs(5), join_all(requests)).await
+            .expect("requests deadlocked");
+
+        assert!(results.iter().all(|r| r.is_ok()));
+
+        for (first, last) in NAMES {
+            let pr = controller._name_to_professor(format!("{} {}", first, last), Freshness::default()).await.unwrap();
+            assert!(pr.lock().await.score.as_ref().and_then(|s| s.quality).is_some());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn held_professor_lock_does_not_block_others() {
+        let controller = seeded_controller().await;
+
+        let held = controller._name_to_professor("Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        let _guard = held.lock().await;
+
+        let others = NAMES.iter()
+            .skip(1)
+            .map(|(first, last)| controller.professor_overview(format!("{} {}", first, last), Freshness::default()));
+
+        let results = tokio::time::timeout(Duration::from_secs(5), join_all(others)).await
+            .expect("overview blocked on an unrelated professor");
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(controller.school_stats().await.professors_rated, NAMES.len());
+    }
+
+    #[test]
+    fn builder_rejects_invalid_settings() {
+        assert!(Controller::builder().school_id(0).build().is_err());
+        assert!(Controller::builder().timeout(Duration::from_secs(0)).build().is_err());
+        assert!(Controller::builder().refresh_min_interval(-1).build().is_err());
+        assert!(Controller::builder().build().is_ok());
+    }
+
+    #[test]
+    fn solr_url_encodes_tricky_names() {
+        let names = ["O'Brien & Sons", "Smith #2", "Anne-Marie  Dupont", "a+b=c", "100%", "José Núñez", "q=*:*&rows=1"];
+
+        for name in names.iter() {
+            let url = solr_url(DEFAULT_SCHOOL_ID, name, None, &SolrConfig::default()).unwrap();
+
+            assert_eq!(url.fragment(), None, "{}", name);
+
+            let q: Vec<String> = url.query_pairs()
+                .filter(|(k, _)| k == "q")
+                .map(|(_, v)| v.into_owned())
+                .collect();
+            assert_eq!(q, vec![name.to_string()]);
+
+            let rows: Vec<String> = url.query_pairs()
+                .filter(|(k, _)| k == "rows")
+                .map(|(_, v)| v.into_owned())
+                .collect();
+            assert_eq!(rows, vec!["200".to_owned()], "{}", name);
+        }
+    }
+
+    #[test]
+    fn solr_url_uses_configured_rows_and_qf() {
+        let solr = SolrConfig {
+            rows: 25,
+            qf: "teacherlastname_t^10".to_owned(),
+            ..SolrConfig::default()
+        };
+        let url = solr_url(42, "Lovelace", None, &solr).unwrap();
+        let pairs: HashMap<String, String> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(pairs["rows"], "25");
+        assert_eq!(pairs["qf"], "teacherlastname_t^10");
+        assert_eq!(pairs["fq"], "schoolid_s:42");
+        assert_eq!(pairs["q"], "Lovelace");
+    }
+
+    #[test]
+    fn solr_url_scopes_to_department() {
+        let url = solr_url(42, "*:*", Some("Computer \"Science\""), &SolrConfig::default()).unwrap();
+        let filters: Vec<String> = url.query_pairs()
+            .filter(|(k, _)| k == "fq")
+            .map(|(_, v)| v.into_owned())
+            .collect();
+
+        assert_eq!(filters, vec!["schoolid_s:42".to_owned(), "teacherdepartment_s:\"Computer Science\"".to_owned()]);
+    }
+
+    #[test]
+    fn graphql_query_template_declares_variables() {
+        assert!(load_graphql_query(&GraphQLConfig::default()).is_ok());
+
+        let renamed = GraphQLConfig { query: GRAPHQL_QUERY.replace("RatingsList_teacher_4pguUW", "RatingsList_teacher_abc"), ..GraphQLConfig::default() };
+        assert!(load_graphql_query(&renamed).unwrap().contains("RatingsList_teacher_abc"));
+
+        let missing = GraphQLConfig { query: GRAPHQL_QUERY.replace("$cursor: String", ""), ..GraphQLConfig::default() };
+        assert!(load_graphql_query(&missing).is_err());
+
+        let unreadable = GraphQLConfig { query_file: Some("/nonexistent/ratings.graphql".to_owned()), ..GraphQLConfig::default() };
+        assert!(Controller::builder().graphql(unreadable).build().is_err());
+    }
+
+    #[test]
+    fn online_filter_uses_course_type_then_remote_quarters() {
+        use chrono::TimeZone;
+        use crate::filter::CommentFilter;
+
+        let at = |year, month| chrono::Utc.ymd(year, month, 1).and_hms(12, 0, 0);
+        let typed = |date, course_type| NormalizedRating { course_type, ..sim::rating(date, 4, 4, 0, 0) };
+        let ratings = vec![
+            typed(at(2023, 2), Some(1)),
+            typed(at(2023, 5), Some(2)),
+            typed(at(2023, 10), Some(3)),
+            typed(at(2020, 10), None),
+            typed(at(2020, 10), Some(1)),
+            typed(at(2019, 10), None),
+        ];
+
+        let kinds: Vec<(Option<CourseType>, Option<bool>)> = ratings.iter().map(|r| (r.course_type(), r.online())).collect();
+        assert_eq!(kinds, vec![
+            (Some(CourseType::InPerson), Some(false)),
+            (Some(CourseType::Online), Some(true)),
+            (Some(CourseType::Hybrid), Some(false)),
+            (None, Some(true)),
+            (Some(CourseType::InPerson), Some(false)),
+            (None, None),
+        ]);
+
+        let dates = |online| CommentFilter { online: Some(online), ..CommentFilter::default() }
+            .apply(ratings.clone(), at(2024, 1)).unwrap()
+            .iter().map(|r| (r.date, r.course_type)).collect::<Vec<_>>();
+        assert_eq!(dates(true), vec![(at(2023, 5), Some(2)), (at(2020, 10), None)]);
+        assert_eq!(dates(false).len(), 4);
+    }
+
+    #[test]
+    fn deep_links_use_rmp_ids() {
+        let ids = rmp_ids(7);
+        assert_eq!(ids.rmp_node_id, "VGVhY2hlci03");
+        assert_eq!(ids.rmp_url, "https://www.ratemyprofessors.com/professor/7");
+
+        assert_eq!(rmp_course_url(7, "cmpsc 130a"), "https://www.ratemyprofessors.com/professor/7?course=CMPSC130A");
+        assert_eq!(rmp_course_url(7, "CMPSC 190DD"), "https://www.ratemyprofessors.com/professor/7?course=CMPSC190DD");
+    }
+
+    fn mock_controller(mock: &MockRmp, page_size: u32) -> Controller {
+        Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(page_size))
+            .backend(Arc::new(mock.clone()))
+            .build()
+            .unwrap()
+    }
+
+    fn mock_professor(mock: &MockRmp) {
+        mock.solr(vec![mock::solr_teachers(vec![mock::teacher(7, "Ada", "Lovelace", "Computer Science")])]);
+    }
+
+    #[actix_rt::test]
+    async fn mock_comments_follow_pagination() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![
+            mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5), mock::rating(2, "CMPSC130A", 4)], Some("cursor-1")),
+            mock::ratings_page(vec![mock::rating(3, "CMPSC130A", 3)], None),
+        ]);
+
+        let controller = mock_controller(&mock, 2);
+        let (professor, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+
+        assert_eq!(professor.rmp_id, 7);
+        assert_eq!(fetched.source, Source::Rmp);
+        assert_eq!(fetched.value.iter().map(|r| r.legacy_id).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(fetched.value[0].tags, vec!["Clear grading".to_owned(), "Tough grader".to_owned()]);
+
+        let requests = mock.graphql_requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body["variables"]["count"], 2);
+        assert!(requests[0].body["variables"]["cursor"].is_null());
+        assert_eq!(requests[1].body["variables"]["cursor"], "cursor-1");
+        assert!(requests.iter().all(|r| r.authorization.as_deref() == Some("Basic secret")));
+        assert_eq!(mock.token_requests(), 1);
+
+        let (_, cached) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!(cached.source, Source::Cache);
+        assert_eq!(mock.graphql_requests().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn mock_expired_token_is_refreshed_once() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("expired"), mock::token_page("fresh")]);
+        mock.graphql(vec![
+            Reply::raw(401, "{\"errors\":[{\"message\":\"unauthorized\"}]}"),
+            mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None),
+        ]);
+
+        let controller = mock_controller(&mock, 20);
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+
+        assert_eq!(fetched.value.len(), 1);
+        assert_eq!(mock.token_requests(), 2);
+
+        let authorizations: Vec<Option<String>> = mock.graphql_requests().into_iter().map(|r| r.authorization).collect();
+        assert_eq!(authorizations, vec![Some("Basic expired".to_owned()), Some("Basic fresh".to_owned())]);
+    }
+
+    #[actix_rt::test]
+    async fn mock_token_scrape_is_single_flight() {
+        let mock = MockRmp::new();
+        mock.token(vec![Reply::raw(200, "no token here"), mock::token_page("secret")]);
+
+        let controller = mock_controller(&mock, 20);
+        let tokens = join_all((0..8).map(|_| controller.graphql_token())).await;
+        assert!(tokens.iter().all(|t| t.is_err()));
+        assert_eq!(mock.token_requests(), 1);
+
+        let controller = Controller::builder()
+            .graphql(GraphQLConfig { token_retry_secs: 0, ..mock.graphql_config(20) })
+            .backend(Arc::new(mock.clone()))
+            .build()
+            .unwrap();
+        let tokens = join_all((0..8).map(|_| controller.graphql_token())).await;
+        assert!(tokens.iter().all(|t| t.as_deref().ok() == Some("secret")));
+        assert_eq!(mock.token_requests(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn mock_rejected_token_is_an_upstream_error() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("revoked")]);
+        mock.graphql(vec![Reply::raw(403, "")]);
+
+        let controller = mock_controller(&mock, 20);
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await;
+
+        assert!(matches!(result, Err(Error::Rmp)), "{:?}", result.err());
+        assert_eq!(mock.graphql_requests().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn mock_malformed_payloads_are_schema_errors() {
+        let mock = MockRmp::new();
+        mock.solr(vec![Reply::raw(200, "{\"grouped\": null}")]);
+
+        let controller = mock_controller(&mock, 20);
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await;
+        assert!(matches!(result, Err(Error::RmpSchema(_))), "{:?}", result.err());
+
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![Reply::raw(200, "<html>maintenance</html>")]);
+
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness { refresh: true, ..Freshness::default() }).await;
+        assert!(matches!(result, Err(Error::RmpSchema(_))), "{:?}", result.err());
+    }
+
+    #[actix_rt::test]
+    async fn mock_course_filters_only_go_upstream_for_truncated_ratings() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None)]);
+
+        let controller = mock_controller(&mock, 20);
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), Some("MATH 3A".to_owned()), Freshness::default()).await.unwrap();
+        assert!(fetched.value.is_empty());
+        assert_eq!(mock.graphql_requests().len(), 1);
+
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![
+            mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], Some("cursor-1")),
+            mock::ratings_page(vec![mock::rating(2, "MATH3A", 4)], None),
+            mock::ratings_page(vec![], None),
+        ]);
+
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(GraphQLConfig { max_pages: 1, ..mock.graphql_config(1) })
+            .backend(Arc::new(mock.clone()))
+            .build()
+            .unwrap();
+
+        for _ in 0..2 {
+            let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), Some("MATH 3A".to_owned()), Freshness::default()).await.unwrap();
+            assert_eq!(fetched.value.iter().map(|r| r.legacy_id).collect::<Vec<_>>(), vec![Some(2)]);
+        }
+        for _ in 0..2 {
+            let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), Some("PHYS 1".to_owned()), Freshness::default()).await.unwrap();
+            assert!(fetched.value.is_empty());
+        }
+
+        let requests = mock.graphql_requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].body["variables"]["courseFilter"], "MATH 3A");
+        assert_eq!(requests[2].body["variables"]["courseFilter"], "PHYS 1");
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[actix_rt::test]
+    async fn mock_evicted_ratings_are_read_back_from_the_store() {
+        let path = std::env::temp_dir().join(format!("ucsb-courses-hot-tier-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = crate::store::connect(format!("sqlite:{}", path.display()).as_str()).await.unwrap();
+
+        let mock = MockRmp::new();
+        mock.solr(vec![
+            mock::solr_teachers(vec![mock::teacher(7, "Ada", "Lovelace", "Computer Science")]),
+            mock::solr_teachers(vec![mock::teacher(8, "Alan", "Turing", "Computer Science")]),
+        ]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![
+            mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None),
+            mock::ratings_page(vec![mock::rating(2, "CMPSC40", 3)], None),
+        ]);
+
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .store(store)
+            .cache_size(1)
+            .build()
+            .unwrap();
+
+        for (name, rmp_id) in [("Ada Lovelace", 7), ("Alan Turing", 8)] {
+            let (professor, fetched) = controller.professor_comments(name.to_owned(), None, Freshness::default()).await.unwrap();
+            assert_eq!((professor.rmp_id, fetched.source), (rmp_id, Source::Rmp));
+        }
+        {
+            let data = controller.data.lock().await;
+            assert_eq!(data.ratings_map.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![8]);
+            assert!(data.evicted.contains(&7));
+        }
+        assert_eq!(controller._rated_professors(None).await.len(), 2);
+
+        let (professor, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!((professor.rmp_id, fetched.source), (7, Source::Store));
+        assert_eq!(fetched.value.iter().map(|r| r.legacy_id).collect::<Vec<_>>(), vec![Some(1)]);
+        assert_eq!(mock.graphql_requests().len(), 2);
+        assert!(controller.data.lock().await.evicted.contains(&8));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[actix_rt::test]
+    async fn mock_top_comments_survive_a_clock_near_the_epoch() {
+        use chrono::TimeZone;
+
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5), mock::rating(2, "CMPSC130A", 4)], None)]);
+
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .clock(mock::MockClock::at(chrono::Utc.ymd(1970, 1, 2).and_hms(0, 0, 0)))
+            .build()
+            .unwrap();
+
+        let top = controller.top_comments("Ada Lovelace".to_owned(), 1, Freshness::default()).await.unwrap();
+        assert_eq!(top.value.len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn mock_rate_limits_surface_retry_after() {
+        use chrono::TimeZone;
+
+        let mock = MockRmp::new();
+        mock.solr(vec![Reply::rate_limited(17)]);
+
+        let clock = mock::MockClock::at(chrono::Utc.ymd(2024, 1, 1).and_hms(12, 0, 0));
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .clock(clock.clone())
+            .build()
+            .unwrap();
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await;
+        assert!(matches!(result, Err(Error::Overloaded(17))), "{:?}", result.err());
+
+        mock_professor(&mock);
+        clock.advance(10);
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await;
+        assert!(matches!(result, Err(Error::Overloaded(7))), "{:?}", result.err());
+        assert_eq!(mock.solr_requests(), 1);
+
+        clock.advance(7);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![Reply::rate_limited(30)]);
+
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness { refresh: true, ..Freshness::default() }).await;
+        assert!(matches!(result, Err(Error::Overloaded(30))), "{:?}", result.err());
+    }
+
+    #[actix_rt::test]
+    async fn mock_server_errors_are_retried_and_recorded() {
+        let mock = MockRmp::new();
+        mock.solr(vec![Reply::raw(503, ""), mock::solr_teachers(vec![mock::teacher(7, "Ada", "Lovelace", "Computer Science")])]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![Reply::raw(502, ""), Reply::raw(502, ""), Reply::raw(502, "")]);
+
+        let path = std::env::temp_dir().join(format!("ucsb-courses-record-{}.jsonl", std::process::id()));
+        let budget = Arc::new(UpstreamBudget::new(0));
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .budget(budget.clone())
+            .outbound(OutboundConfig { retries: 2, retry_backoff_ms: 0, record_path: Some(path.to_string_lossy().into_owned()) })
+            .build()
+            .unwrap();
+
+        let result = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await;
+        assert!(matches!(result, Err(Error::Rmp)), "{:?}", result.err());
+        assert_eq!(mock.solr_requests(), 2);
+        assert_eq!(mock.graphql_requests().len(), 3);
+        assert_eq!(budget.report(chrono::Utc::now()).since_start.total(), 6);
+
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let statuses: Vec<u64> = recorded.lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["status"].as_u64().unwrap())
+            .collect();
+        assert_eq!(statuses, vec![503, 200, 200, 502, 502, 502]);
+        assert!(!recorded.contains("Basic"), "{}", recorded);
+    }
+
+    #[actix_rt::test]
+    async fn mock_shared_names_list_each_professors_courses() {
+        let mock = MockRmp::new();
+        mock.solr(vec![mock::solr_teachers(vec![
+            mock::teacher(7, "Ada", "Lovelace", "Computer Science"),
+            mock::teacher(8, "Ada", "Lovelace", "Mathematics"),
+        ])]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![
+            mock::rating(1, "CMPSC130A", 5),
+            mock::rating(2, "CMPSC130A", 4),
+            mock::rating(3, "CMPSC8", 3),
+        ], None)]);
+
+        let controller = mock_controller(&mock, 20);
+        controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+
+        let matches = controller.professor_matches("ada  lovelace".to_owned(), 1, Freshness::default()).await.unwrap();
+        let summary: Vec<(u32, &str, Vec<CourseCount>)> = matches.iter()
+            .map(|(p, courses)| (p.rmp_id, p.department.as_str(), courses.clone()))
+            .collect();
+        assert_eq!(summary, vec![
+            (7, "Computer Science", vec![CourseCount { course: "CMPSC 130A".to_owned(), num_ratings: 2 }]),
+            (8, "Mathematics", Vec::new()),
+        ]);
+        assert_eq!(mock.solr_requests(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn mock_unrated_professors_report_insufficient_data() {
+        use chrono::TimeZone;
+
+        let mock = MockRmp::new();
+        let mut teacher = mock::teacher(7, "Ada", "Lovelace", "Computer Science");
+        teacher["averageratingscore_rf"] = serde_json::Value::Null;
+        teacher["total_number_of_ratings_i"] = 0.into();
+        mock.solr(vec![mock::solr_teachers(vec![teacher])]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(Vec::new(), None)]);
+
+        let clock = mock::MockClock::at(chrono::Utc.ymd(2024, 1, 1).and_hms(12, 0, 0));
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .clock(clock.clone())
+            .score_ttl(0)
+            .ttl_jitter(0)
+            .empty_ratings_ttl(600)
+            .build()
+            .unwrap();
+
+        let professor = controller.professor_overview("Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        let score = professor.lock().await.score.clone().unwrap();
+        assert_eq!((score.quality, score.num_ratings, score.insufficient_data), (None, 0, true));
+
+        clock.advance(300);
+        controller.professor_overview("Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        assert_eq!(mock.graphql_requests().len(), 1);
+
+        clock.advance(301);
+        controller.professor_overview("Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        assert_eq!(mock.graphql_requests().len(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn mock_last_name_lookups_answer_from_cache() {
+        let mock = MockRmp::new();
+        mock.solr(vec![mock::solr_teachers(vec![
+            mock::teacher(7, "Ada", "Lovelace", "Computer Science"),
+            mock::teacher(8, "Ada", "Byron", "Mathematics"),
+        ])]);
+
+        let controller = mock_controller(&mock, 20);
+        let first = controller.professors_by_last_name("Lovelace".to_owned(), Freshness::default()).await;
+        assert_eq!(first.source, Source::Rmp);
+        assert_eq!(first.value.iter().map(|p| p.rmp_id).collect::<Vec<u32>>(), vec![7]);
+
+        let second = controller.professors_by_last_name(" LOVELACE ".to_owned(), Freshness::default()).await;
+        assert_eq!(second.source, Source::Cache);
+        assert_eq!(second.value.iter().map(|p| p.rmp_id).collect::<Vec<u32>>(), vec![7]);
+        assert_eq!(mock.solr_requests(), 1);
+
+        let refreshed = controller.professors_by_last_name("Lovelace".to_owned(), Freshness { refresh: true, ..Freshness::default() }).await;
+        assert_eq!(refreshed.source, Source::Rmp);
+        assert_eq!(mock.solr_requests(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn mock_shedding_serves_last_names_without_searching() {
+        use crate::config::SheddingConfig;
+
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+
+        let shedding = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .shedding(SheddingConfig { enabled: true, ..SheddingConfig::default() })
+            .build()
+            .unwrap();
+        let missing = shedding.professors_by_last_name("Lovelace".to_owned(), Freshness { refresh: true, ..Freshness::default() }).await;
+        assert_eq!((missing.source, missing.value.len()), (Source::Cache, 0));
+        assert_eq!(mock.solr_requests(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn mock_hidden_ratings_are_left_out_of_scores_and_stats() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5), mock::rating(2, "CMPSC130A", 1), mock::rating(3, "CMPSC130A", 4)], None)]);
+
+        let hidden = Arc::new(HiddenRatings::new(None));
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .hidden(hidden.clone())
+            .build()
+            .unwrap();
+
+        let professor = controller.professor_overview("Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        assert_eq!(professor.lock().await.score.as_ref().map(|s| s.num_ratings), Some(3));
+        assert_eq!(controller.school_stats().await.total_ratings, 3);
+
+        hidden.hide(2, "abusive".to_owned()).await.unwrap();
+        controller.forget_scores().await;
+
+        let professor = controller.professor_overview("Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        assert_eq!(professor.lock().await.score.as_ref().map(|s| s.num_ratings), Some(2));
+        assert_eq!(controller.school_stats().await.total_ratings, 2);
+        assert_eq!(mock.graphql_requests().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn mock_warming_by_id_respects_access_rules() {
+        use crate::config::AccessConfig;
+
+        let mock = MockRmp::new();
+        mock.solr(vec![mock::solr_teachers(vec![
+            mock::teacher(7, "Ada", "Lovelace", "Computer Science"),
+            mock::teacher(8, "Ada", "Lovelace", "Mathematics"),
+        ])]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None)]);
+
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(20))
+            .backend(Arc::new(mock.clone()))
+            .access(AccessConfig { block_professors: vec![8], ..AccessConfig::default() })
+            .build()
+            .unwrap();
+        controller.autocomplete_professor("Lovelace".to_owned(), 10).await;
+
+        controller.warm("7").await.unwrap();
+        assert!(matches!(controller.warm("8").await, Err(Error::NotFound)));
+        assert!(matches!(controller.warm("9").await, Err(Error::NotFound)));
+        assert_eq!(mock.graphql_requests().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn mock_departments_follow_the_ucsb_subject() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+
+        let controller = mock_controller(&mock, 20);
+        controller.autocomplete_professor("Lovelace".to_owned(), 10).await;
+
+        let subjects: HashMap<String, String> = vec![("LOVELACE A".to_owned(), "MATH".to_owned()), ("NOBODY X".to_owned(), "PHYS".to_owned())]
+            .into_iter()
+            .collect();
+        assert_eq!(controller.correct_departments(&subjects).await, 1);
+        assert_eq!(controller.correct_departments(&subjects).await, 0);
+
+        let professor = controller.autocomplete_professor("Lovelace".to_owned(), 10).await.value.remove(0);
+        assert_eq!((professor.department.as_str(), professor.rmp_department.as_deref()), ("Mathematics", Some("Computer Science")));
+    }
+
+    #[actix_rt::test]
+    async fn mock_merged_profiles_combine_ratings() {
+        let mock = MockRmp::new();
+        mock.solr(vec![mock::solr_teachers(vec![
+            mock::teacher(7, "Ada", "Lovelace", "Computer Science"),
+            mock::teacher(8, "Ada", "Lovelace", "Mathematics"),
+        ])]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![
+            mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5), mock::rating(2, "CMPSC130A", 4)], None),
+            mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5), mock::rating(2, "CMPSC130A", 4)], None),
+            mock::ratings_page(vec![mock::rating(2, "CMPSC130A", 4), mock::rating(3, "MATH3A", 2)], None),
+        ]);
+
+        let controller = mock_controller(&mock, 20);
+        controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+
+        assert!(controller.merge_professors(7, 7).await.is_err());
+        let professor = controller.merge_professors(7, 8).await.unwrap();
+        assert_eq!(professor.rmp_id, 7);
+        assert!(controller.merge_professors(8, 7).await.is_err());
+
+        let (professor, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!(professor.rmp_id, 7);
+        assert_eq!(fetched.source, Source::Cache);
+        assert_eq!(fetched.value.iter().map(|r| r.legacy_id).collect::<Vec<_>>(), vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(mock.graphql_requests().len(), 3);
+
+        let suggestions = controller.autocomplete_professor("Lovelace".to_owned(), 10).await;
+        assert_eq!(suggestions.value.iter().map(|p| p.rmp_id).collect::<Vec<_>>(), vec![7]);
+    }
+
+    #[actix_rt::test]
+    async fn mock_refresh_fetches_only_new_ratings() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![
+            mock::ratings_page(vec![mock::dated(mock::rating(2, "CMPSC130A", 4), "2023-09-01"), mock::dated(mock::rating(1, "CMPSC130A", 5), "2023-06-01")], Some("cursor-1")),
+            mock::ratings_page(vec![mock::dated(mock::rating(0, "CMPSC130A", 3), "2022-01-01")], None),
+            mock::ratings_page(vec![mock::dated(mock::rating(3, "CMPSC130A", 2), "2023-10-01"), mock::dated(mock::rating(2, "CMPSC130A", 1), "2023-09-01")], Some("cursor-2")),
+            mock::ratings_page(vec![mock::dated(mock::rating(1, "CMPSC130A", 5), "2023-06-01"), mock::dated(mock::rating(0, "CMPSC130A", 3), "2022-01-01")], Some("cursor-3")),
+        ]);
+
+        let controller = mock_controller(&mock, 2);
+        controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!(mock.graphql_requests().len(), 2);
+
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness { max_age: Some(-1), ..Freshness::default() }).await.unwrap();
+        assert_eq!(fetched.source, Source::Rmp);
+        assert_eq!(fetched.value.iter().map(|r| r.legacy_id).collect::<Vec<_>>(), vec![Some(3), Some(2), Some(1), Some(0)]);
+        assert_eq!(fetched.value[1].clarity, 1);
+
+        let requests = mock.graphql_requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[2].body["variables"]["cursor"].is_null());
+        assert_eq!(requests[3].body["variables"]["cursor"], "cursor-2");
+    }
+
+    #[actix_rt::test]
+    async fn mock_ratings_expire_on_the_injected_clock() {
+        use chrono::TimeZone;
+
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None)]);
+
+        let clock = mock::MockClock::at(chrono::Utc.ymd(2024, 1, 1).and_hms(0, 0, 0));
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(10))
+            .backend(Arc::new(mock.clone()))
+            .clock(clock.clone())
+            .ratings_ttl(3600)
+            .ttl_jitter(0)
+            .build()
+            .unwrap();
+
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!(fetched.fetched_at, chrono::Utc.ymd(2024, 1, 1).and_hms(0, 0, 0));
+
+        clock.advance(3599);
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!(fetched.source, Source::Cache);
+        assert_eq!(mock.graphql_requests().len(), 1);
+
+        clock.advance(2);
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        assert_eq!(fetched.source, Source::Rmp);
+        assert_eq!(mock.graphql_requests().len(), 2);
+    }
+
+    #[test]
+    fn seeded_samples_are_reproducible_and_spread_across_quality() {
+        use crate::analysis::{representative, Rng};
+
+        let ratings: Vec<NormalizedRating> = (0..30)
+            .map(|i| NormalizedRating { comment: format!("rating {}", i), ..rating(i, 1 + (i % 5) as u32) })
+            .collect();
+
+        let a = representative(&ratings, 5, &mut Rng::new(11));
+        let b = representative(&ratings, 5, &mut Rng::new(11));
+        let c = representative(&ratings, 5, &mut Rng::new(12));
+
+        assert_eq!(a.iter().map(|r| r.comment.clone()).collect::<Vec<_>>(), b.iter().map(|r| r.comment.clone()).collect::<Vec<_>>());
+        assert_ne!(a.iter().map(|r| r.comment.clone()).collect::<Vec<_>>(), c.iter().map(|r| r.comment.clone()).collect::<Vec<_>>());
+        assert_eq!(a.iter().map(|r| r.clarity).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(representative(&ratings[..3], 5, &mut Rng::new(11)).len(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn mock_trivial_names_are_rejected_before_search() {
+        use crate::validate::Fields;
+
+        for trivial in [" ", "%", "-- .", "Dr.", "Prof. X"] {
+            assert!(name::is_trivial(trivial), "{:?}", trivial);
+            assert_eq!(Fields::new().professor("name", trivial).finish().len(), 1);
+        }
+        assert_eq!(name::canonical("Dr. Ada Lovelace, PhD"), "ada lovelace");
+        assert!(Fields::new().professor("name", "Li").finish().is_empty());
+
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None)]);
+        let controller = mock_controller(&mock, 10);
+
+        assert!(matches!(controller.professor_overview("  Prof. ".to_owned(), Freshness::default()).await, Err(Error::BadRequest(_))));
+        let professor = controller.professor_overview("Dr. Ada Lovelace".to_owned(), Freshness::default()).await.unwrap();
+        assert_eq!(professor.lock().await.rmp_id, 7);
+    }
+
+    #[actix_rt::test]
+    async fn mock_name_spellings_share_one_cache_key() {
+        for spelling in ["phill conrad", "Conrad, Phill", "  PHILL   CONRAD ", "Dr. Phill Conrad", "Phíll Conrad"] {
+            assert_eq!(name::canonical(spelling), "phill conrad", "{:?}", spelling);
+        }
+
+        let mock = MockRmp::new();
+        mock.solr(vec![mock::solr_teachers(vec![mock::teacher(9, "Phill", "Conrad", "Computer Science")])]);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC156", 5)], None)]);
+        let controller = mock_controller(&mock, 10);
+
+        for spelling in ["Phill Conrad", "phill conrad", "Conrad, Phill"] {
+            let professor = controller.professor_overview(spelling.to_owned(), Freshness::default()).await.unwrap();
+            assert_eq!(professor.lock().await.rmp_id, 9);
+        }
+        assert_eq!(mock.solr_requests(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn mock_exhausted_budget_serves_cache_only_until_midnight() {
+        use chrono::TimeZone;
+
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None)]);
+
+        let clock = mock::MockClock::at(chrono::Utc.ymd(2024, 1, 1).and_hms(22, 0, 0));
+        let budget = Arc::new(UpstreamBudget::new(3));
+        let controller = Controller::builder()
+            .solr(mock.solr_config())
+            .graphql(mock.graphql_config(10))
+            .backend(Arc::new(mock.clone()))
+            .clock(clock.clone())
+            .budget(budget.clone())
+            .build()
+            .unwrap();
+
+        controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness::default()).await.unwrap();
+        let report = budget.report(clock.now());
+        assert_eq!((report.today.solr, report.today.token, report.today.graphql), (1, 1, 1));
+        assert!(report.exhausted);
+
+        let (_, fetched) = controller.professor_comments("Ada Lovelace".to_owned(), None, Freshness { refresh: true, ..Freshness::default() }).await.unwrap();
+        assert_eq!(fetched.source, Source::Cache);
+        assert!(matches!(controller.professor_overview("Alan Turing".to_owned(), Freshness::default()).await, Err(Error::Shedding(_))));
+        assert_eq!(controller.professors_by_last_name("Turing".to_owned(), Freshness::default()).await.source, Source::Cache);
+        assert_eq!(mock.solr_requests(), 1);
+        assert!(budget.metrics(clock.now()).contains("ucsb_courses_upstream_budget_remaining 0"));
+
+        clock.advance(2 * 3600);
+        assert!(!budget.exhausted(clock.now()));
+        assert_eq!(budget.report(clock.now()).since_start.total(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn mock_self_test_reports_failing_step() {
+        let mock = MockRmp::new();
+        mock_professor(&mock);
+        mock.token(vec![mock::token_page("secret")]);
+        mock.graphql(vec![mock::ratings_page(vec![mock::rating(1, "CMPSC130A", 5)], None)]);
+
+        let controller = mock_controller(&mock, 20);
+        let steps = controller.self_test("Lovelace").await;
+        assert_eq!(steps.iter().map(|s| (s.step, s.ok)).collect::<Vec<_>>(), vec![("token", true), ("search", true), ("ratings", true)]);
 
-        (quality_ratings_sum, total_weight)
+        mock.solr(vec![mock::solr_teachers(vec![])]);
+        let steps = controller.self_test("Lovelace").await;
+        assert_eq!(steps.iter().map(|s| (s.step, s.ok)).collect::<Vec<_>>(), vec![("token", true), ("search", false)]);
     }
 }
@@ -0,0 +1,190 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+
+use crate::course::normalize_course;
+use crate::ucsb::{ClassResponse, ClassSection};
+
+const MAX_COMBINATIONS: usize = 10000;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Constraints {
+    pub earliest_start: Option<String>,
+    pub latest_end: Option<String>,
+    pub days_off: Vec<char>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SuggestRequest {
+    pub courses: Vec<String>,
+    #[serde(default)]
+    pub constraints: Constraints,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Meeting {
+    pub days: Vec<char>,
+    pub begin: u32,
+    pub end: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionChoice {
+    pub course: String,
+    pub enroll_code: String,
+    pub section: String,
+    pub instructor: Option<String>,
+    pub quality: Option<f32>,
+    pub meetings: Vec<Meeting>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub score: f32,
+    pub sections: Vec<SectionChoice>,
+}
+
+pub struct SectionIndex {
+    sections: HashMap<String, Vec<ClassSection>>,
+}
+
+pub fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    Some(hours * 60 + minutes)
+}
+
+pub fn instructor_query(instructor: &str) -> String {
+    let mut tokens: Vec<&str> = instructor.split_whitespace().collect();
+
+    match tokens.len() {
+        0 | 1 => instructor.trim().to_owned(),
+        _ => {
+            let first = tokens.split_off(tokens.len() - 1);
+            format!("{} {}", first.join(" "), tokens.join(" "))
+        }
+    }
+}
+
+impl Constraints {
+    pub fn is_valid(&self) -> bool {
+        self.earliest_start.as_deref().map(|t| parse_time(t).is_some()).unwrap_or(true)
+            && self.latest_end.as_deref().map(|t| parse_time(t).is_some()).unwrap_or(true)
+    }
+
+    fn accepts(&self, meetings: &[Meeting]) -> bool {
+        let earliest = self.earliest_start.as_deref().and_then(parse_time);
+        let latest = self.latest_end.as_deref().and_then(parse_time);
+        let days_off: Vec<char> = self.days_off.iter().map(|d| d.to_ascii_uppercase()).collect();
+
+        meetings.iter().all(|m| {
+            earliest.map(|e| m.begin >= e).unwrap_or(true)
+                && latest.map(|l| m.end <= l).unwrap_or(true)
+                && !m.days.iter().any(|d| days_off.contains(d))
+        })
+    }
+}
+
+impl SectionIndex {
+    pub fn new() -> Self {
+        SectionIndex {
+            sections: HashMap::new(),
+        }
+    }
+
+    pub fn from_classes(classes: &[ClassResponse]) -> Self {
+        let mut index = Self::new();
+
+        for class in classes {
+            if let Some(course) = normalize_course(class.course_id.as_str()) {
+                index.sections.entry(course).or_default().extend(class.class_sections.iter().cloned());
+            }
+        }
+
+        index
+    }
+
+    pub fn options(&self, course: &str) -> Option<Vec<SectionChoice>> {
+        let course = normalize_course(course)?;
+        let sections = self.sections.get(course.as_str())?;
+
+        let lectures: Vec<&ClassSection> = sections.iter().filter(|s| s.section.ends_with("00")).collect();
+        let candidates: Vec<&ClassSection> = if lectures.is_empty() { sections.iter().collect() } else { lectures };
+
+        Some(candidates.into_iter()
+            .map(|s| SectionChoice {
+                course: course.clone(),
+                enroll_code: s.enroll_code.clone(),
+                section: s.section.clone(),
+                instructor: s.instructors.first().map(|i| i.instructor.clone()),
+                quality: None,
+                meetings: s.time_locations.iter()
+                    .filter_map(|t| Some(Meeting {
+                        days: t.days.as_deref()?.chars().filter(|c| !c.is_whitespace()).collect(),
+                        begin: parse_time(t.begin_time.as_deref()?)?,
+                        end: parse_time(t.end_time.as_deref()?)?,
+                    }))
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+fn conflicts(a: &[Meeting], b: &[Meeting]) -> bool {
+    a.iter().any(|m| b.iter().any(|n| {
+        m.days.iter().any(|d| n.days.contains(d)) && m.begin < n.end && n.begin < m.end
+    }))
+}
+
+pub fn suggest(options: Vec<Vec<SectionChoice>>, qualities: &HashMap<String, f32>, constraints: &Constraints, limit: usize) -> Vec<Suggestion> {
+    let options: Vec<Vec<SectionChoice>> = options.into_iter()
+        .map(|choices| choices.into_iter()
+            .filter(|c| constraints.accepts(c.meetings.as_slice()))
+            .map(|c| SectionChoice {
+                quality: c.instructor.as_ref().and_then(|i| qualities.get(i)).cloned(),
+                ..c
+            })
+            .collect())
+        .collect();
+
+    let mut suggestions = Vec::new();
+    let mut picked: Vec<&SectionChoice> = Vec::new();
+    _combine(options.as_slice(), &mut picked, &mut suggestions);
+
+    suggestions.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+fn _combine<'a>(options: &'a [Vec<SectionChoice>], picked: &mut Vec<&'a SectionChoice>, out: &mut Vec<Suggestion>) {
+    if out.len() >= MAX_COMBINATIONS {
+        return;
+    }
+
+    let (choices, rest) = match options.split_first() {
+        Some(split) => split,
+        None => {
+            out.push(Suggestion {
+                score: picked.iter().filter_map(|c| c.quality).sum(),
+                sections: picked.iter().map(|c| (*c).clone()).collect(),
+            });
+            return;
+        }
+    };
+
+    for choice in choices {
+        if picked.iter().any(|p| conflicts(p.meetings.as_slice(), choice.meetings.as_slice())) {
+            continue;
+        }
+
+        picked.push(choice);
+        _combine(rest, picked, out);
+        picked.pop();
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+use crate::error::Error;
+use crate::rmp::Controller;
+use crate::supervisor;
+use crate::watch::Watchlist;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Job {
+    TokenRefresh,
+    CacheRefresh,
+    Crawl,
+    SnapshotExport,
+    SeatWatch,
+}
+
+impl FromStr for Job {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "token_refresh" => Ok(Job::TokenRefresh),
+            "cache_refresh" => Ok(Job::CacheRefresh),
+            "crawl" => Ok(Job::Crawl),
+            "snapshot_export" => Ok(Job::SnapshotExport),
+            "seat_watch" => Ok(Job::SeatWatch),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobConfig {
+    pub job: Job,
+    pub interval_secs: u64,
+    #[serde(default)]
+    pub jitter_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SchedulerConfig {
+    pub jobs: Vec<JobConfig>,
+    pub snapshot_path: String,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            jobs: Vec::new(),
+            snapshot_path: "snapshot.json".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub job: Job,
+    pub interval_secs: u64,
+    pub jitter_secs: u64,
+    pub running: bool,
+    pub runs: u64,
+    pub last_run: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<i64>,
+    pub last_result: Option<String>,
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+pub struct Scheduler {
+    jobs: Vec<JobConfig>,
+    snapshot_path: String,
+    crawl_pace: Duration,
+    watchlist: Arc<Watchlist>,
+    status: Mutex<HashMap<Job, JobStatus>>,
+}
+
+fn jitter(secs: u64) -> Duration {
+    if secs == 0 {
+        return Duration::from_secs(0);
+    }
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+
+    Duration::from_millis(nanos as u64 % (secs * 1000))
+}
+
+impl JobStatus {
+    fn new(job: Job, interval_secs: u64, jitter_secs: u64) -> Self {
+        JobStatus {
+            job,
+            interval_secs,
+            jitter_secs,
+            running: false,
+            runs: 0,
+            last_run: None,
+            last_duration_ms: None,
+            last_result: None,
+            next_run: None,
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: &SchedulerConfig, crawl_pace: Duration, watchlist: Arc<Watchlist>) -> Self {
+        let status = config.jobs.iter()
+            .map(|j| (j.job, JobStatus::new(j.job, j.interval_secs, j.jitter_secs)))
+            .collect();
+
+        Scheduler {
+            jobs: config.jobs.clone(),
+            snapshot_path: config.snapshot_path.clone(),
+            crawl_pace,
+            watchlist,
+            status: Mutex::new(status),
+        }
+    }
+
+    pub fn jobs(&self) -> &[JobConfig] {
+        self.jobs.as_slice()
+    }
+
+    pub async fn status(&self) -> Vec<JobStatus> {
+        let status = self.status.lock().await;
+
+        let mut jobs: Vec<JobStatus> = status.values().cloned().collect();
+        jobs.sort_by_key(|s| format!("{:?}", s.job));
+
+        jobs
+    }
+
+    pub async fn is_running(&self, job: Job) -> bool {
+        let status = self.status.lock().await;
+
+        status.get(&job).map(|s| s.running).unwrap_or(false)
+    }
+
+    pub async fn run_forever(&self, job: JobConfig, controllers: &[&Controller]) {
+        loop {
+            let delay = Duration::from_secs(job.interval_secs) + jitter(job.jitter_secs);
+
+            {
+                let mut status = self.status.lock().await;
+                if let Some(s) = status.get_mut(&job.job) {
+                    s.next_run = chrono::Duration::from_std(delay).ok().map(|d| Utc::now() + d);
+                }
+            }
+
+            tokio::time::delay_for(delay).await;
+
+            if let Err(e) = self.run(job.job, controllers).await {
+                println!("scheduler: {:?} error {}", job.job, e);
+            }
+        }
+    }
+
+    pub async fn run(&self, job: Job, controllers: &[&Controller]) -> Result<String, Error> {
+        {
+            let mut status = self.status.lock().await;
+            let s = status.entry(job).or_insert_with(|| JobStatus::new(job, 0, 0));
+
+            if s.running {
+                return Err(Error::BadRequest(format!("{:?} is already running", job)));
+            }
+            s.running = true;
+        }
+
+        let started = Utc::now();
+        let result = AssertUnwindSafe(self._run(job, controllers)).catch_unwind().await;
+
+        let mut status = self.status.lock().await;
+        if let Some(s) = status.get_mut(&job) {
+            s.running = false;
+            s.runs += 1;
+            s.last_run = Some(started);
+            s.last_duration_ms = Some((Utc::now() - started).num_milliseconds());
+            s.last_result = Some(match &result {
+                Ok(Ok(message)) => message.clone(),
+                Ok(Err(e)) => format!("error: {}", e),
+                Err(payload) => format!("panic: {}", supervisor::panic_message(payload.as_ref())),
+            });
+        }
+        drop(status);
+
+        result.unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    }
+
+    async fn _run(&self, job: Job, controllers: &[&Controller]) -> Result<String, Error> {
+        match job {
+            Job::TokenRefresh => {
+                for controller in controllers {
+                    controller.refresh_graphql_token().await?;
+                }
+                Ok("refreshed graphql token".to_owned())
+            }
+            Job::CacheRefresh => {
+                let mut count = 0;
+                for controller in controllers {
+                    count += controller.refresh_expired(self.crawl_pace).await;
+                }
+                Ok(format!("refreshed {} professors", count))
+            }
+            Job::Crawl => {
+                let mut count = 0;
+                for controller in controllers {
+                    count += controller.crawl(self.crawl_pace).await?;
+                }
+                Ok(format!("crawled {} professors", count))
+            }
+            Job::SnapshotExport => {
+                let mut snapshot = Vec::new();
+                for controller in controllers {
+                    snapshot.extend(controller.snapshot().await);
+                }
+                let content = serde_json::to_vec(&snapshot).map_err(|e| Error::Store(e.to_string()))?;
+
+                tokio::fs::write(self.snapshot_path.as_str(), content).await
+                    .map_err(|e| Error::Store(e.to_string()))?;
+
+                Ok(format!("exported {} professors to {}", snapshot.len(), self.snapshot_path))
+            }
+            Job::SeatWatch => {
+                let sent = self.watchlist.poll().await?;
+                Ok(format!("sent {} seat notifications", sent))
+            }
+        }
+    }
+}
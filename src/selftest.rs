@@ -0,0 +1,27 @@
+use crate::config::AppConfig;
+use crate::rmp::Controller;
+
+const DEFAULT_QUERY: &str = "*:*";
+
+pub async fn run(query: Option<String>) -> std::io::Result<()> {
+    let config = AppConfig::load()?;
+    let controller = Controller::from_config(&config)
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    let query = query.unwrap_or_else(|| DEFAULT_QUERY.to_owned());
+    println!("self-test: school {}, query {}", controller.school_id(), query);
+
+    let steps = controller.self_test(query.as_str()).await;
+    for step in steps.iter() {
+        println!("  {:<8} {:<4} {:>6}ms  {}", step.step, if step.ok { "ok" } else { "FAIL" }, step.elapsed_ms, step.detail);
+    }
+
+    match steps.iter().find(|s| !s.ok) {
+        Some(failed) => Err(std::io::Error::other(format!("self-test failed at {} step", failed.step))),
+        None if steps.len() < 3 => Err(std::io::Error::other("self-test did not complete")),
+        None => {
+            println!("self-test: upstream is compatible");
+            Ok(())
+        }
+    }
+}
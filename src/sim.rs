@@ -0,0 +1,119 @@
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::analysis::Rng;
+use crate::quarter::Quarter;
+use crate::rmp::NormalizedRating;
+
+pub const EPOCH: i64 = 1_700_000_000;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Distribution {
+    Constant(u32),
+    Uniform,
+    Bimodal,
+    Skewed(u32),
+}
+
+pub struct RatingSim {
+    seed: u64,
+    now: DateTime<Utc>,
+    count: usize,
+    span_days: i64,
+    quality: Distribution,
+    max_thumbs: u32,
+}
+
+impl RatingSim {
+    pub fn new(seed: u64) -> Self {
+        RatingSim {
+            seed,
+            now: Utc.timestamp(EPOCH, 0),
+            count: 20,
+            span_days: 5 * 365,
+            quality: Distribution::Uniform,
+            max_thumbs: 0,
+        }
+    }
+
+    pub fn now(mut self, now: DateTime<Utc>) -> Self {
+        self.now = now;
+        self
+    }
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn span_days(mut self, span_days: i64) -> Self {
+        self.span_days = span_days;
+        self
+    }
+
+    pub fn quality(mut self, quality: Distribution) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    pub fn max_thumbs(mut self, max_thumbs: u32) -> Self {
+        self.max_thumbs = max_thumbs;
+        self
+    }
+
+    pub fn now_secs(&self) -> u64 {
+        self.now.timestamp() as u64
+    }
+
+    pub fn generate(&self) -> Vec<NormalizedRating> {
+        let mut rng = Rng::new(self.seed);
+
+        (0..self.count)
+            .map(|_| {
+                let age = rng.below((self.span_days * 86400) as u64) as i64;
+                let clarity = self._score(&mut rng);
+                let helpful = self._score(&mut rng);
+                let thumbs_up = rng.below(self.max_thumbs as u64 + 1) as u32;
+                let thumbs_down = rng.below(self.max_thumbs as u64 + 1) as u32;
+
+                rating(self.now - chrono::Duration::seconds(age), clarity, helpful, thumbs_up, thumbs_down)
+            })
+            .collect()
+    }
+
+    fn _score(&self, rng: &mut Rng) -> u32 {
+        match self.quality {
+            Distribution::Constant(q) => q,
+            Distribution::Uniform => 1 + rng.below(5) as u32,
+            Distribution::Bimodal => if rng.below(2) == 0 { 1 + rng.below(2) as u32 } else { 4 + rng.below(2) as u32 },
+            Distribution::Skewed(mode) => {
+                let spread = rng.below(3) as i64 - 1;
+                (mode as i64 + spread).clamp(1, 5) as u32
+            }
+        }
+    }
+}
+
+pub fn rating(date: DateTime<Utc>, clarity: u32, helpful: u32, thumbs_up: u32, thumbs_down: u32) -> NormalizedRating {
+    NormalizedRating {
+        legacy_id: None,
+        class: "CMPSC130A".to_owned(),
+        course: Some("CMPSC 130A".to_owned()),
+        comment: String::new(),
+        tags: Vec::new(),
+        word_count: 0,
+        reading_seconds: 0,
+        grade: "A".to_owned(),
+        gpa: Some(4.0),
+        attendance_mandatory: None,
+        would_take_again: None,
+        textbook_use: None,
+        course_type: None,
+        clarity,
+        helpful,
+        difficulty: 3,
+        thumbs_up,
+        thumbs_down,
+        date,
+        quarter: Quarter::from_date(date),
+    }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::fmt;
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Config,
+    Store,
+    Snapshots,
+    Tasks,
+    Listen,
+    Ready,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Phase::Config => "config",
+            Phase::Store => "store",
+            Phase::Snapshots => "snapshots",
+            Phase::Tasks => "tasks",
+            Phase::Listen => "listen",
+            Phase::Ready => "ready",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub struct StartupError {
+    pub phase: Phase,
+    pub message: String,
+}
+
+impl fmt::Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "startup failed during {} phase: {}", self.phase, self.message)
+    }
+}
+
+impl std::error::Error for StartupError {}
+
+impl From<StartupError> for std::io::Error {
+    fn from(e: StartupError) -> Self {
+        std::io::Error::other(e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReadyStatus {
+    pub ready: bool,
+    pub phase: Phase,
+    pub degraded: Vec<String>,
+    pub failed_tasks: Vec<String>,
+}
+
+pub struct Readiness {
+    phase: RwLock<Phase>,
+    degraded: RwLock<Vec<String>>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        Readiness {
+            phase: RwLock::new(Phase::Config),
+            degraded: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn enter(&self, phase: Phase) {
+        println!("enter: startup phase {}", phase);
+        *self.phase.write().unwrap() = phase;
+    }
+
+    pub fn fail<E: fmt::Display>(&self, e: E) -> StartupError {
+        StartupError {
+            phase: *self.phase.read().unwrap(),
+            message: e.to_string(),
+        }
+    }
+
+    pub fn degrade<E: fmt::Display>(&self, what: &str, e: E) {
+        println!("degrade: {} during {} phase: {}", what, self.phase.read().unwrap(), e);
+        self.degraded.write().unwrap().push(format!("{}: {}", what, e));
+    }
+
+    pub fn status(&self, failed_tasks: Vec<String>) -> ReadyStatus {
+        let phase = *self.phase.read().unwrap();
+        let degraded = self.degraded.read().unwrap().clone();
+
+        ReadyStatus {
+            ready: phase == Phase::Ready && degraded.is_empty() && failed_tasks.is_empty(),
+            phase,
+            degraded,
+            failed_tasks,
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::name::fold;
+use crate::rmp::NormalizedRating;
+use ucsb_courses::models::CourseCount;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Percentiles {
+    pub p10: f32,
+    pub p25: f32,
+    pub p50: f32,
+    pub p75: f32,
+    pub p90: f32,
+}
+
+impl Percentiles {
+    fn from_sorted(sorted: &[f32]) -> Option<Self> {
+        Some(Percentiles {
+            p10: percentile(sorted, 0.10)?,
+            p25: percentile(sorted, 0.25)?,
+            p50: percentile(sorted, 0.50)?,
+            p75: percentile(sorted, 0.75)?,
+            p90: percentile(sorted, 0.90)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchoolStats {
+    pub professors_tracked: usize,
+    pub professors_rated: usize,
+    pub total_ratings: usize,
+
+    pub avg_quality: Option<f32>,
+    pub avg_difficulty: Option<f32>,
+
+    pub quality_percentiles: Option<Percentiles>,
+    pub difficulty_percentiles: Option<Percentiles>,
+
+    pub computed_at: chrono::DateTime<chrono::Utc>,
+    pub last_crawl: Option<chrono::DateTime<chrono::Utc>>,
+
+    #[serde(skip)]
+    quality_distribution: Vec<f32>,
+    #[serde(skip)]
+    department_distributions: HashMap<String, Vec<f32>>,
+}
+
+pub fn percentile(sorted: &[f32], p: f32) -> Option<f32> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = p * (sorted.len() - 1) as f32;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+
+    Some(sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f32))
+}
+
+pub fn percentile_rank(sorted: &[f32], value: f32) -> Option<f32> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let below = sorted.iter().filter(|v| **v <= value).count();
+
+    Some(below as f32 / sorted.len() as f32 * 100.0)
+}
+
+pub fn mean_quality(ratings: &[NormalizedRating]) -> Option<f32> {
+    mean(ratings.iter().map(|r| r.quality()))
+}
+
+pub fn mean_difficulty(ratings: &[NormalizedRating]) -> Option<f32> {
+    mean(ratings.iter().map(|r| r.difficulty as f32))
+}
+
+pub fn mean_gpa(ratings: &[NormalizedRating]) -> Option<f32> {
+    mean(ratings.iter().filter_map(|r| r.gpa))
+}
+
+pub fn top_tags(ratings: &[NormalizedRating], limit: usize) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in ratings.iter().flat_map(|r| r.tags.iter()) {
+        *counts.entry(tag.trim()).or_insert(0) += 1;
+    }
+
+    let mut ranked: Vec<(&str, usize)> = counts.into_iter().filter(|(tag, _)| !tag.is_empty()).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    ranked.into_iter().take(limit).map(|(tag, _)| tag.to_owned()).collect()
+}
+
+pub fn top_courses(ratings: &[NormalizedRating], limit: usize) -> Vec<CourseCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for r in ratings {
+        *counts.entry(r.course.as_deref().unwrap_or(r.class.as_str())).or_insert(0) += 1;
+    }
+
+    let mut ranked: Vec<(&str, usize)> = counts.into_iter().filter(|(course, _)| !course.is_empty()).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    ranked.into_iter()
+        .take(limit)
+        .map(|(course, num_ratings)| CourseCount { course: course.to_owned(), num_ratings })
+        .collect()
+}
+
+pub fn pearson(points: &[(f32, f32)]) -> Option<f32> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let mean_x = mean(points.iter().map(|(x, _)| *x))?;
+    let mean_y = mean(points.iter().map(|(_, y)| *y))?;
+
+    let (cov, var_x, var_y) = points.iter().fold((0.0, 0.0, 0.0), |(cov, var_x, var_y), (x, y)| {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        (cov + dx * dy, var_x + dx * dx, var_y + dy * dy)
+    });
+
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+fn mean<I: Iterator<Item = f32>>(values: I) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f32)
+    }
+}
+
+fn sorted(mut values: Vec<f32>) -> Vec<f32> {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    values
+}
+
+impl SchoolStats {
+    pub fn compute(professors_tracked: usize, rating_sets: &[(String, &[NormalizedRating])], last_crawl: Option<chrono::DateTime<chrono::Utc>>, now: chrono::DateTime<chrono::Utc>) -> Self {
+        let rated: Vec<&(String, &[NormalizedRating])> = rating_sets.iter().filter(|(_, r)| !r.is_empty()).collect();
+
+        let qualities = sorted(rated.iter().filter_map(|(_, r)| mean_quality(r)).collect());
+        let difficulties = sorted(rated.iter().filter_map(|(_, r)| mean_difficulty(r)).collect());
+
+        let mut department_distributions: HashMap<String, Vec<f32>> = HashMap::new();
+        for (department, ratings) in rated.iter() {
+            if let Some(quality) = mean_quality(ratings) {
+                department_distributions.entry(department.clone()).or_default().push(quality);
+            }
+        }
+        let department_distributions = department_distributions.into_iter()
+            .map(|(k, v)| (k, sorted(v)))
+            .collect();
+
+        let all = rated.iter().flat_map(|(_, r)| r.iter());
+
+        SchoolStats {
+            professors_tracked,
+            professors_rated: rated.len(),
+            total_ratings: rated.iter().map(|(_, r)| r.len()).sum(),
+            avg_quality: mean(all.clone().map(|r| r.quality())),
+            avg_difficulty: mean(all.map(|r| r.difficulty as f32)),
+            quality_percentiles: Percentiles::from_sorted(qualities.as_slice()),
+            difficulty_percentiles: Percentiles::from_sorted(difficulties.as_slice()),
+            computed_at: now,
+            last_crawl,
+            quality_distribution: qualities,
+            department_distributions,
+        }
+    }
+
+    pub fn school_percentile(&self, quality: f32) -> Option<f32> {
+        percentile_rank(self.quality_distribution.as_slice(), quality)
+    }
+
+    pub fn department_percentile(&self, department: &str, quality: f32) -> Option<f32> {
+        self.department_distributions.get(department)
+            .and_then(|d| percentile_rank(d.as_slice(), quality))
+    }
+}
+
+const MIN_GRADED_RATINGS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradePoint {
+    pub rmp_id: u32,
+    pub full_name: String,
+    pub department: String,
+    pub avg_gpa: f32,
+    pub avg_quality: f32,
+    pub graded_ratings: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeQualityReport {
+    pub points: Vec<GradePoint>,
+    pub correlation: Option<f32>,
+    pub computed_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl GradeQualityReport {
+    pub fn compute(professors: Vec<(u32, String, String, &[NormalizedRating])>, now: chrono::DateTime<chrono::Utc>) -> Self {
+        let points: Vec<GradePoint> = professors.into_iter()
+            .filter_map(|(rmp_id, full_name, department, ratings)| {
+                let graded: Vec<&NormalizedRating> = ratings.iter().filter(|r| r.gpa.is_some()).collect();
+
+                if graded.len() < MIN_GRADED_RATINGS {
+                    return None;
+                }
+
+                Some(GradePoint {
+                    rmp_id,
+                    full_name,
+                    department,
+                    avg_gpa: mean_gpa(ratings)?,
+                    avg_quality: mean_quality(ratings)?,
+                    graded_ratings: graded.len(),
+                })
+            })
+            .collect();
+
+        let pairs: Vec<(f32, f32)> = points.iter().map(|p| (p.avg_gpa, p.avg_quality)).collect();
+
+        GradeQualityReport {
+            correlation: pearson(pairs.as_slice()),
+            points,
+            computed_at: now,
+        }
+    }
+}
+
+const MIN_TAG_RATINGS: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagProfessor {
+    pub rmp_id: u32,
+    pub full_name: String,
+    pub department: String,
+    pub tagged_ratings: usize,
+    pub total_ratings: usize,
+    pub share: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TagReport {
+    pub tag: String,
+    pub professors: Vec<TagProfessor>,
+    pub computed_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl TagReport {
+    pub fn compute(tag: &str, professors: Vec<(u32, String, String, &[NormalizedRating])>, limit: usize, now: chrono::DateTime<chrono::Utc>) -> Self {
+        let folded = fold(tag.trim());
+
+        let mut ranked: Vec<TagProfessor> = professors.into_iter()
+            .filter(|(_, _, _, ratings)| ratings.len() >= MIN_TAG_RATINGS)
+            .filter_map(|(rmp_id, full_name, department, ratings)| {
+                let tagged = ratings.iter()
+                    .filter(|r| r.tags.iter().any(|t| fold(t.trim()) == folded))
+                    .count();
+
+                if tagged == 0 {
+                    return None;
+                }
+
+                Some(TagProfessor {
+                    rmp_id,
+                    full_name,
+                    department,
+                    tagged_ratings: tagged,
+                    total_ratings: ratings.len(),
+                    share: tagged as f32 / ratings.len() as f32,
+                })
+            })
+            .collect();
+
+        ranked.sort_by(|a, b| b.share.partial_cmp(&a.share)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.tagged_ratings.cmp(&a.tagged_ratings)));
+        ranked.truncate(limit);
+
+        TagReport {
+            tag: tag.trim().to_owned(),
+            professors: ranked,
+            computed_at: now,
+        }
+    }
+}
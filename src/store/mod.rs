@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::account::Favorites;
+use crate::audit::AuditEntry;
+use crate::envelope::Cursor;
+use crate::error::Error;
+use crate::moderation::HiddenRating;
+use crate::quota::ApiKey;
+use crate::rmp::{GraphQLToken, NormalizedRating, Professor};
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+mod sql;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub struct StoredRatings {
+    pub ratings: Vec<NormalizedRating>,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RatingsSort {
+    #[default]
+    Newest,
+    Oldest,
+    Helpful,
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+pub struct RatingsQuery {
+    pub courses: Vec<String>,
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+    pub grade: Option<String>,
+    pub min_thumb_score: Option<i64>,
+    pub exclude: Vec<u32>,
+    pub sort: RatingsSort,
+    pub after: Option<Cursor>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+pub struct RatingsPage {
+    pub ratings: Vec<NormalizedRating>,
+    pub total: usize,
+}
+
+#[async_trait]
+pub trait Store: Send + Sync {
+    async fn save_professor(&self, school_id: u32, professor: &Professor) -> Result<(), Error>;
+
+    async fn load_professors(&self, school_id: u32) -> Result<Vec<Professor>, Error>;
+
+    async fn save_ratings(&self, rmp_id: u32, ratings: &[NormalizedRating], fetched_at: chrono::DateTime<chrono::Utc>) -> Result<(), Error>;
+
+    async fn load_ratings(&self, rmp_id: u32) -> Result<Option<StoredRatings>, Error>;
+
+    async fn touch_ratings(&self, rmp_id: u32, fetched_at: chrono::DateTime<chrono::Utc>) -> Result<(), Error>;
+
+    async fn query_ratings(&self, rmp_id: u32, query: &RatingsQuery) -> Result<RatingsPage, Error>;
+
+    async fn save_mapping(&self, school_id: u32, name: &str, rmp_id: u32) -> Result<(), Error>;
+
+    async fn load_mappings(&self, school_id: u32) -> Result<Vec<(String, u32)>, Error>;
+
+    async fn delete_mapping(&self, school_id: u32, name: &str) -> Result<(), Error>;
+
+    async fn save_merge(&self, school_id: u32, duplicate_id: u32, rmp_id: u32) -> Result<(), Error>;
+
+    async fn load_merges(&self, school_id: u32) -> Result<Vec<(u32, u32)>, Error>;
+
+    async fn save_token(&self, school_id: u32, token: &GraphQLToken) -> Result<(), Error>;
+
+    async fn load_token(&self, school_id: u32) -> Result<Option<GraphQLToken>, Error>;
+
+    async fn save_favorites(&self, account_id: &str, favorites: &Favorites) -> Result<(), Error>;
+
+    async fn load_favorites(&self, account_id: &str) -> Result<Option<Favorites>, Error>;
+
+    async fn save_api_key(&self, api_key: &ApiKey) -> Result<(), Error>;
+
+    async fn load_api_keys(&self) -> Result<Vec<ApiKey>, Error>;
+
+    async fn save_usage(&self, key_id: &str, period: &str, count: u64) -> Result<(), Error>;
+
+    async fn load_usage(&self, periods: &[String]) -> Result<Vec<(String, String, u64)>, Error>;
+
+    async fn save_audit(&self, entry: &AuditEntry) -> Result<(), Error>;
+
+    async fn load_audit(&self, limit: usize, action: Option<&str>) -> Result<Vec<AuditEntry>, Error>;
+
+    async fn save_hidden(&self, hidden: &HiddenRating) -> Result<(), Error>;
+
+    async fn delete_hidden(&self, rating_id: u32) -> Result<(), Error>;
+
+    async fn load_hidden(&self) -> Result<Vec<HiddenRating>, Error>;
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+fn join_favorites(favorites: &Favorites) -> (String, String) {
+    (
+        favorites.professors.iter().map(|p| p.to_string()).collect::<Vec<String>>().join(","),
+        favorites.sections.join(","),
+    )
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+fn split_favorites(professors: &str, sections: &str, updated_at: i64) -> Favorites {
+    use chrono::TimeZone;
+
+    Favorites {
+        professors: professors.split(',').filter_map(|p| p.parse().ok()).collect(),
+        sections: sections.split(',').filter(|s| !s.is_empty()).map(|s| s.to_owned()).collect(),
+        updated_at: Some(chrono::Utc.timestamp(updated_at, 0)),
+    }
+}
+
+pub async fn connect(url: &str) -> Result<Arc<dyn Store>, Error> {
+    #[cfg(feature = "sqlite")]
+    if url.starts_with("sqlite:") {
+        return Ok(Arc::new(sqlite::SqliteStore::connect(url).await?));
+    }
+
+    #[cfg(feature = "postgres")]
+    if url.starts_with("postgres:") || url.starts_with("postgresql:") {
+        return Ok(Arc::new(postgres::PostgresStore::connect(url).await?));
+    }
+
+    Err(Error::Store(format!("unsupported storage url {}", url)))
+}
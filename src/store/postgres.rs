@@ -0,0 +1,640 @@
+use async_trait::async_trait;
+use chrono::TimeZone;
+use sqlx::postgres::{PgPool, PgRow};
+use sqlx::Row;
+
+use crate::error::Error;
+use crate::grade;
+use crate::quarter::Quarter;
+use crate::rmp::{GraphQLToken, NormalizedRating, Professor};
+use crate::text;
+use crate::account::Favorites;
+use crate::audit::AuditEntry;
+use crate::moderation::HiddenRating;
+use crate::quota::ApiKey;
+use super::sql::{ratings_filter, ratings_order, SqlValue};
+use super::{join_favorites, split_favorites, RatingsPage, RatingsQuery, Store, StoredRatings};
+
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS professors (
+        rmp_id BIGINT PRIMARY KEY,
+        school_id BIGINT NOT NULL,
+        first_name TEXT NOT NULL,
+        last_name TEXT NOT NULL,
+        full_name TEXT NOT NULL,
+        department TEXT NOT NULL,
+        rmp_quality DOUBLE PRECISION,
+        num_ratings BIGINT
+    )",
+    "CREATE TABLE IF NOT EXISTS courses (
+        code TEXT PRIMARY KEY
+    )",
+    "CREATE TABLE IF NOT EXISTS ratings (
+        id BIGSERIAL PRIMARY KEY,
+        professor_id BIGINT NOT NULL,
+        legacy_id BIGINT,
+        class TEXT NOT NULL,
+        course TEXT,
+        comment TEXT NOT NULL,
+        tags TEXT NOT NULL,
+        grade TEXT NOT NULL,
+        gpa DOUBLE PRECISION,
+        attendance_mandatory BOOLEAN,
+        would_take_again BOOLEAN,
+        textbook_use BIGINT,
+        course_type BIGINT,
+        clarity BIGINT NOT NULL,
+        helpful BIGINT NOT NULL,
+        difficulty BIGINT NOT NULL,
+        thumbs_up BIGINT NOT NULL,
+        thumbs_down BIGINT NOT NULL,
+        date BIGINT NOT NULL,
+        quarter TEXT NOT NULL
+    )",
+    "CREATE INDEX IF NOT EXISTS ratings_professor_id ON ratings (professor_id)",
+    "CREATE INDEX IF NOT EXISTS ratings_course ON ratings (course)",
+    "DELETE FROM ratings WHERE legacy_id IS NOT NULL AND id NOT IN
+        (SELECT MAX(id) FROM ratings WHERE legacy_id IS NOT NULL GROUP BY professor_id, legacy_id)",
+    "CREATE UNIQUE INDEX IF NOT EXISTS ratings_legacy_id ON ratings (professor_id, legacy_id)",
+    "CREATE TABLE IF NOT EXISTS rating_fetches (
+        professor_id BIGINT PRIMARY KEY,
+        fetched_at BIGINT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS name_mappings (
+        school_id BIGINT NOT NULL,
+        name TEXT NOT NULL,
+        rmp_id BIGINT NOT NULL,
+        PRIMARY KEY (school_id, name)
+    )",
+    "CREATE TABLE IF NOT EXISTS professor_merges (
+        school_id BIGINT NOT NULL,
+        duplicate_id BIGINT NOT NULL,
+        rmp_id BIGINT NOT NULL,
+        PRIMARY KEY (school_id, duplicate_id)
+    )",
+    "CREATE TABLE IF NOT EXISTS graphql_tokens (
+        school_id BIGINT PRIMARY KEY,
+        token TEXT NOT NULL,
+        scraped_at BIGINT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS favorites (
+        account_id TEXT PRIMARY KEY,
+        professors TEXT NOT NULL,
+        sections TEXT NOT NULL,
+        updated_at BIGINT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS api_keys (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        key_hash TEXT NOT NULL,
+        daily_quota BIGINT NOT NULL,
+        monthly_quota BIGINT NOT NULL,
+        created_at BIGINT NOT NULL,
+        revoked_at BIGINT
+    )",
+    "CREATE TABLE IF NOT EXISTS api_usage (
+        key_id TEXT NOT NULL,
+        period TEXT NOT NULL,
+        count BIGINT NOT NULL,
+        PRIMARY KEY (key_id, period)
+    )",
+    "CREATE TABLE IF NOT EXISTS audit_log (
+        id BIGSERIAL PRIMARY KEY,
+        actor TEXT NOT NULL,
+        action TEXT NOT NULL,
+        target TEXT NOT NULL,
+        detail TEXT,
+        at BIGINT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS hidden_ratings (
+        rating_id BIGINT PRIMARY KEY,
+        reason TEXT NOT NULL,
+        hidden_at BIGINT NOT NULL
+    )",
+];
+
+fn store_error(e: sqlx::Error) -> Error {
+    println!("postgres store: error {}", e);
+    Error::Store(e.to_string())
+}
+
+fn professor_from_row(row: PgRow) -> sqlx::Result<Professor> {
+    Ok(Professor {
+        rmp_id: row.try_get::<i64, _>("rmp_id")? as u32,
+        score: None,
+        rmp_quality: row.try_get::<Option<f64>, _>("rmp_quality")?.map(|q| q as f32),
+        num_ratings: row.try_get::<Option<i64>, _>("num_ratings")?.map(|n| n as u32),
+        first_name: row.try_get("first_name")?,
+        last_name: row.try_get("last_name")?,
+        full_name: row.try_get("full_name")?,
+        department: row.try_get("department")?,
+        rmp_department: None,
+    })
+}
+
+fn rating_from_row(row: PgRow) -> sqlx::Result<NormalizedRating> {
+    let date = chrono::Utc.timestamp(row.try_get::<i64, _>("date")?, 0);
+    let grade: String = row.try_get("grade")?;
+    let tags: String = row.try_get("tags")?;
+    let comment: String = row.try_get("comment")?;
+    let word_count = text::word_count(comment.as_str());
+
+    Ok(NormalizedRating {
+        legacy_id: row.try_get::<Option<i64>, _>("legacy_id")?.map(|i| i as u32),
+        class: row.try_get("class")?,
+        course: row.try_get("course")?,
+        comment,
+        word_count,
+        reading_seconds: text::reading_seconds(word_count),
+        tags: tags.split("--").filter(|t| !t.is_empty()).map(|t| t.to_owned()).collect(),
+        gpa: grade::gpa(grade.as_str()),
+        grade,
+        attendance_mandatory: row.try_get("attendance_mandatory")?,
+        would_take_again: row.try_get("would_take_again")?,
+        textbook_use: row.try_get::<Option<i64>, _>("textbook_use")?.map(|i| i as u32),
+        course_type: row.try_get::<Option<i64>, _>("course_type")?.map(|i| i as u32),
+        clarity: row.try_get::<i64, _>("clarity")? as u32,
+        helpful: row.try_get::<i64, _>("helpful")? as u32,
+        difficulty: row.try_get::<i64, _>("difficulty")? as u32,
+        thumbs_up: row.try_get::<i64, _>("thumbs_up")? as u32,
+        thumbs_down: row.try_get::<i64, _>("thumbs_down")? as u32,
+        date,
+        quarter: Quarter::from_date(date),
+    })
+}
+
+fn mapping_from_row(row: PgRow) -> sqlx::Result<(String, u32)> {
+    Ok((row.try_get("name")?, row.try_get::<i64, _>("rmp_id")? as u32))
+}
+
+fn merge_from_row(row: PgRow) -> sqlx::Result<(u32, u32)> {
+    Ok((row.try_get::<i64, _>("duplicate_id")? as u32, row.try_get::<i64, _>("rmp_id")? as u32))
+}
+
+fn token_from_row(row: PgRow) -> sqlx::Result<GraphQLToken> {
+    Ok(GraphQLToken {
+        token: row.try_get("token")?,
+        scraped_at: chrono::Utc.timestamp(row.try_get("scraped_at")?, 0),
+        uses: 0,
+    })
+}
+
+fn favorites_from_row(row: PgRow) -> sqlx::Result<Favorites> {
+    let professors: String = row.try_get("professors")?;
+    let sections: String = row.try_get("sections")?;
+
+    Ok(split_favorites(professors.as_str(), sections.as_str(), row.try_get("updated_at")?))
+}
+
+fn api_key_from_row(row: PgRow) -> sqlx::Result<ApiKey> {
+    Ok(ApiKey {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        key_hash: row.try_get("key_hash")?,
+        daily_quota: row.try_get::<i64, _>("daily_quota")? as u64,
+        monthly_quota: row.try_get::<i64, _>("monthly_quota")? as u64,
+        created_at: chrono::Utc.timestamp(row.try_get("created_at")?, 0),
+        revoked_at: row.try_get::<Option<i64>, _>("revoked_at")?.map(|t| chrono::Utc.timestamp(t, 0)),
+    })
+}
+
+fn usage_from_row(row: PgRow) -> sqlx::Result<(String, String, u64)> {
+    Ok((row.try_get("key_id")?, row.try_get("period")?, row.try_get::<i64, _>("count")? as u64))
+}
+
+fn audit_from_row(row: PgRow) -> sqlx::Result<AuditEntry> {
+    Ok(AuditEntry {
+        actor: row.try_get("actor")?,
+        action: row.try_get("action")?,
+        target: row.try_get("target")?,
+        detail: row.try_get("detail")?,
+        at: chrono::Utc.timestamp(row.try_get("at")?, 0),
+    })
+}
+
+fn hidden_from_row(row: PgRow) -> sqlx::Result<HiddenRating> {
+    Ok(HiddenRating {
+        rating_id: row.try_get::<i64, _>("rating_id")? as u32,
+        reason: row.try_get("reason")?,
+        hidden_at: chrono::Utc.timestamp(row.try_get("hidden_at")?, 0),
+    })
+}
+
+fn total_from_row(row: PgRow) -> sqlx::Result<i64> {
+    row.try_get("total")
+}
+
+fn bind_values<'q>(query: sqlx::Query<'q, sqlx::Postgres>, values: Vec<SqlValue>) -> sqlx::Query<'q, sqlx::Postgres> {
+    values.into_iter().fold(query, |query, value| match value {
+        SqlValue::Int(i) => query.bind(i),
+        SqlValue::Text(s) => query.bind(s),
+    })
+}
+
+fn fetched_at_from_row(row: PgRow) -> sqlx::Result<i64> {
+    row.try_get("fetched_at")
+}
+
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        let pool = PgPool::new(url).await.map_err(store_error)?;
+
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await.map_err(store_error)?;
+        }
+
+        Ok(PostgresStore { pool })
+    }
+}
+
+#[async_trait]
+impl Store for PostgresStore {
+    async fn save_professor(&self, school_id: u32, professor: &Professor) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO professors
+                (rmp_id, school_id, first_name, last_name, full_name, department, rmp_quality, num_ratings)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (rmp_id) DO UPDATE SET
+                    school_id = EXCLUDED.school_id,
+                    first_name = EXCLUDED.first_name,
+                    last_name = EXCLUDED.last_name,
+                    full_name = EXCLUDED.full_name,
+                    department = EXCLUDED.department,
+                    rmp_quality = EXCLUDED.rmp_quality,
+                    num_ratings = EXCLUDED.num_ratings")
+            .bind(professor.rmp_id as i64)
+            .bind(school_id as i64)
+            .bind(professor.first_name.clone())
+            .bind(professor.last_name.clone())
+            .bind(professor.full_name.clone())
+            .bind(professor.department.clone())
+            .bind(professor.rmp_quality.map(|q| q as f64))
+            .bind(professor.num_ratings.map(|n| n as i64))
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_professors(&self, school_id: u32) -> Result<Vec<Professor>, Error> {
+        sqlx::query("SELECT * FROM professors WHERE school_id = $1")
+            .bind(school_id as i64)
+            .try_map(professor_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_ratings(&self, rmp_id: u32, ratings: &[NormalizedRating], fetched_at: chrono::DateTime<chrono::Utc>) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await.map_err(store_error)?;
+
+        sqlx::query("DELETE FROM ratings WHERE professor_id = $1 AND legacy_id IS NULL")
+            .bind(rmp_id as i64)
+            .execute(&mut tx)
+            .await
+            .map_err(store_error)?;
+
+        for r in ratings {
+            if let Some(code) = &r.course {
+                sqlx::query("INSERT INTO courses (code) VALUES ($1) ON CONFLICT DO NOTHING")
+                    .bind(code.clone())
+                    .execute(&mut tx)
+                    .await
+                    .map_err(store_error)?;
+            }
+
+            sqlx::query(
+                "INSERT INTO ratings
+                    (professor_id, legacy_id, class, course, comment, tags, grade, gpa,
+                     attendance_mandatory, would_take_again, textbook_use, course_type,
+                     clarity, helpful, difficulty, thumbs_up, thumbs_down, date, quarter)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+                    ON CONFLICT (professor_id, legacy_id) DO UPDATE SET
+                        class = EXCLUDED.class,
+                        course = EXCLUDED.course,
+                        comment = EXCLUDED.comment,
+                        tags = EXCLUDED.tags,
+                        grade = EXCLUDED.grade,
+                        gpa = EXCLUDED.gpa,
+                        attendance_mandatory = EXCLUDED.attendance_mandatory,
+                        would_take_again = EXCLUDED.would_take_again,
+                        textbook_use = EXCLUDED.textbook_use,
+                        course_type = EXCLUDED.course_type,
+                        clarity = EXCLUDED.clarity,
+                        helpful = EXCLUDED.helpful,
+                        difficulty = EXCLUDED.difficulty,
+                        thumbs_up = EXCLUDED.thumbs_up,
+                        thumbs_down = EXCLUDED.thumbs_down,
+                        date = EXCLUDED.date,
+                        quarter = EXCLUDED.quarter")
+                .bind(rmp_id as i64)
+                .bind(r.legacy_id.map(|i| i as i64))
+                .bind(r.class.clone())
+                .bind(r.course.clone())
+                .bind(r.comment.clone())
+                .bind(r.tags.join("--"))
+                .bind(r.grade.clone())
+                .bind(r.gpa.map(|g| g as f64))
+                .bind(r.attendance_mandatory)
+                .bind(r.would_take_again)
+                .bind(r.textbook_use.map(|i| i as i64))
+                .bind(r.course_type.map(|i| i as i64))
+                .bind(r.clarity as i64)
+                .bind(r.helpful as i64)
+                .bind(r.difficulty as i64)
+                .bind(r.thumbs_up as i64)
+                .bind(r.thumbs_down as i64)
+                .bind(r.date.timestamp())
+                .bind(r.quarter.code())
+                .execute(&mut tx)
+                .await
+                .map_err(store_error)?;
+        }
+
+        sqlx::query("INSERT INTO rating_fetches (professor_id, fetched_at) VALUES ($1, $2)
+                ON CONFLICT (professor_id) DO UPDATE SET fetched_at = EXCLUDED.fetched_at")
+            .bind(rmp_id as i64)
+            .bind(fetched_at.timestamp())
+            .execute(&mut tx)
+            .await
+            .map_err(store_error)?;
+
+        tx.commit().await.map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_ratings(&self, rmp_id: u32) -> Result<Option<StoredRatings>, Error> {
+        let fetched_at = sqlx::query("SELECT fetched_at FROM rating_fetches WHERE professor_id = $1")
+            .bind(rmp_id as i64)
+            .try_map(fetched_at_from_row)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        let fetched_at = match fetched_at {
+            Some(fetched_at) => chrono::Utc.timestamp(fetched_at, 0),
+            None => return Ok(None),
+        };
+
+        let ratings = sqlx::query("SELECT * FROM ratings WHERE professor_id = $1 ORDER BY date DESC")
+            .bind(rmp_id as i64)
+            .try_map(rating_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(Some(StoredRatings { ratings, fetched_at }))
+    }
+
+    async fn query_ratings(&self, rmp_id: u32, query: &RatingsQuery) -> Result<RatingsPage, Error> {
+        let (filter, values) = ratings_filter(rmp_id, query, false, |i| format!("${}", i));
+        let total = bind_values(sqlx::query(format!("SELECT COUNT(*) AS total FROM ratings WHERE {}", filter).as_str()), values)
+            .try_map(total_from_row)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        let (filter, values) = ratings_filter(rmp_id, query, true, |i| format!("${}", i));
+        let sql = format!("SELECT * FROM ratings WHERE {} ORDER BY {} LIMIT {} OFFSET {}", filter, ratings_order(query.sort), query.limit, query.offset);
+        let ratings = bind_values(sqlx::query(sql.as_str()), values)
+            .try_map(rating_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(RatingsPage { ratings, total: total as usize })
+    }
+
+    async fn touch_ratings(&self, rmp_id: u32, fetched_at: chrono::DateTime<chrono::Utc>) -> Result<(), Error> {
+        sqlx::query("INSERT INTO rating_fetches (professor_id, fetched_at) VALUES ($1, $2)
+                ON CONFLICT (professor_id) DO UPDATE SET fetched_at = EXCLUDED.fetched_at")
+            .bind(rmp_id as i64)
+            .bind(fetched_at.timestamp())
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn save_mapping(&self, school_id: u32, name: &str, rmp_id: u32) -> Result<(), Error> {
+        sqlx::query("INSERT INTO name_mappings (school_id, name, rmp_id) VALUES ($1, $2, $3)
+                ON CONFLICT (school_id, name) DO UPDATE SET rmp_id = EXCLUDED.rmp_id")
+            .bind(school_id as i64)
+            .bind(name.to_owned())
+            .bind(rmp_id as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_mappings(&self, school_id: u32) -> Result<Vec<(String, u32)>, Error> {
+        sqlx::query("SELECT name, rmp_id FROM name_mappings WHERE school_id = $1")
+            .bind(school_id as i64)
+            .try_map(mapping_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn delete_mapping(&self, school_id: u32, name: &str) -> Result<(), Error> {
+        sqlx::query("DELETE FROM name_mappings WHERE school_id = $1 AND name = $2")
+            .bind(school_id as i64)
+            .bind(name.to_owned())
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn save_merge(&self, school_id: u32, duplicate_id: u32, rmp_id: u32) -> Result<(), Error> {
+        sqlx::query("INSERT INTO professor_merges (school_id, duplicate_id, rmp_id) VALUES ($1, $2, $3)
+                ON CONFLICT (school_id, duplicate_id) DO UPDATE SET rmp_id = EXCLUDED.rmp_id")
+            .bind(school_id as i64)
+            .bind(duplicate_id as i64)
+            .bind(rmp_id as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_merges(&self, school_id: u32) -> Result<Vec<(u32, u32)>, Error> {
+        sqlx::query("SELECT duplicate_id, rmp_id FROM professor_merges WHERE school_id = $1")
+            .bind(school_id as i64)
+            .try_map(merge_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_token(&self, school_id: u32, token: &GraphQLToken) -> Result<(), Error> {
+        sqlx::query("INSERT INTO graphql_tokens (school_id, token, scraped_at) VALUES ($1, $2, $3)
+                ON CONFLICT (school_id) DO UPDATE SET token = EXCLUDED.token, scraped_at = EXCLUDED.scraped_at")
+            .bind(school_id as i64)
+            .bind(token.token.clone())
+            .bind(token.scraped_at.timestamp())
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_token(&self, school_id: u32) -> Result<Option<GraphQLToken>, Error> {
+        sqlx::query("SELECT token, scraped_at FROM graphql_tokens WHERE school_id = $1")
+            .bind(school_id as i64)
+            .try_map(token_from_row)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_favorites(&self, account_id: &str, favorites: &Favorites) -> Result<(), Error> {
+        let (professors, sections) = join_favorites(favorites);
+
+        sqlx::query("INSERT INTO favorites (account_id, professors, sections, updated_at) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (account_id) DO UPDATE SET professors = EXCLUDED.professors, sections = EXCLUDED.sections, updated_at = EXCLUDED.updated_at")
+            .bind(account_id.to_owned())
+            .bind(professors)
+            .bind(sections)
+            .bind(favorites.updated_at.map(|t| t.timestamp()).unwrap_or(0))
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_favorites(&self, account_id: &str) -> Result<Option<Favorites>, Error> {
+        sqlx::query("SELECT * FROM favorites WHERE account_id = $1")
+            .bind(account_id.to_owned())
+            .try_map(favorites_from_row)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_api_key(&self, api_key: &ApiKey) -> Result<(), Error> {
+        sqlx::query("INSERT INTO api_keys (id, name, key_hash, daily_quota, monthly_quota, created_at, revoked_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, daily_quota = EXCLUDED.daily_quota,
+                    monthly_quota = EXCLUDED.monthly_quota, revoked_at = EXCLUDED.revoked_at")
+            .bind(api_key.id.clone())
+            .bind(api_key.name.clone())
+            .bind(api_key.key_hash.clone())
+            .bind(api_key.daily_quota as i64)
+            .bind(api_key.monthly_quota as i64)
+            .bind(api_key.created_at.timestamp())
+            .bind(api_key.revoked_at.map(|t| t.timestamp()))
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_api_keys(&self) -> Result<Vec<ApiKey>, Error> {
+        sqlx::query("SELECT * FROM api_keys")
+            .try_map(api_key_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_usage(&self, key_id: &str, period: &str, count: u64) -> Result<(), Error> {
+        sqlx::query("INSERT INTO api_usage (key_id, period, count) VALUES ($1, $2, $3)
+                ON CONFLICT (key_id, period) DO UPDATE SET count = GREATEST(api_usage.count, EXCLUDED.count)")
+            .bind(key_id.to_owned())
+            .bind(period.to_owned())
+            .bind(count as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_usage(&self, periods: &[String]) -> Result<Vec<(String, String, u64)>, Error> {
+        let placeholders = (1..=periods.len()).map(|i| format!("${}", i)).collect::<Vec<String>>().join(", ");
+        let sql = format!("SELECT * FROM api_usage WHERE period IN ({})", placeholders);
+
+        let mut query = sqlx::query(sql.as_str());
+        for period in periods {
+            query = query.bind(period.clone());
+        }
+
+        query.try_map(usage_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_audit(&self, entry: &AuditEntry) -> Result<(), Error> {
+        sqlx::query("INSERT INTO audit_log (actor, action, target, detail, at) VALUES ($1, $2, $3, $4, $5)")
+            .bind(entry.actor.clone())
+            .bind(entry.action.clone())
+            .bind(entry.target.clone())
+            .bind(entry.detail.clone())
+            .bind(entry.at.timestamp())
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_audit(&self, limit: usize, action: Option<&str>) -> Result<Vec<AuditEntry>, Error> {
+        let query = match action {
+            Some(action) => sqlx::query("SELECT * FROM audit_log WHERE action = $1 ORDER BY id DESC LIMIT $2").bind(action.to_owned()),
+            None => sqlx::query("SELECT * FROM audit_log ORDER BY id DESC LIMIT $1"),
+        };
+
+        query.bind(limit as i64)
+            .try_map(audit_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn save_hidden(&self, hidden: &HiddenRating) -> Result<(), Error> {
+        sqlx::query("INSERT INTO hidden_ratings (rating_id, reason, hidden_at) VALUES ($1, $2, $3)
+                ON CONFLICT (rating_id) DO UPDATE SET reason = EXCLUDED.reason, hidden_at = EXCLUDED.hidden_at")
+            .bind(hidden.rating_id as i64)
+            .bind(hidden.reason.clone())
+            .bind(hidden.hidden_at.timestamp())
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn delete_hidden(&self, rating_id: u32) -> Result<(), Error> {
+        sqlx::query("DELETE FROM hidden_ratings WHERE rating_id = $1")
+            .bind(rating_id as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(store_error)?;
+
+        Ok(())
+    }
+
+    async fn load_hidden(&self) -> Result<Vec<HiddenRating>, Error> {
+        sqlx::query("SELECT * FROM hidden_ratings")
+            .try_map(hidden_from_row)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(store_error)
+    }
+}
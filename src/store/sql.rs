@@ -0,0 +1,78 @@
+use super::{RatingsQuery, RatingsSort};
+
+pub enum SqlValue {
+    Int(i64),
+    Text(String),
+}
+
+struct SqlFilter<P> {
+    clauses: Vec<String>,
+    values: Vec<SqlValue>,
+    placeholder: P,
+}
+
+impl<P: Fn(usize) -> String> SqlFilter<P> {
+    fn bind(&mut self, value: SqlValue) -> String {
+        self.values.push(value);
+        (self.placeholder)(self.values.len())
+    }
+}
+
+pub fn ratings_filter<P: Fn(usize) -> String>(rmp_id: u32, query: &RatingsQuery, keyset: bool, placeholder: P) -> (String, Vec<SqlValue>) {
+    let mut f = SqlFilter { clauses: Vec::new(), values: Vec::new(), placeholder };
+
+    let id = f.bind(SqlValue::Int(rmp_id as i64));
+    f.clauses.push(format!("professor_id = {}", id));
+
+    if !query.courses.is_empty() {
+        let courses: Vec<String> = query.courses.iter()
+            .filter_map(|c| crate::course::normalize_course(c.as_str()))
+            .collect();
+        let courses: Vec<String> = courses.into_iter().map(|c| f.bind(SqlValue::Text(c))).collect();
+        let classes: Vec<String> = query.courses.iter()
+            .map(|c| f.bind(SqlValue::Text(crate::course::compact_course(c.as_str()))))
+            .collect();
+
+        let class_match = format!("REPLACE(REPLACE(UPPER(class), ' ', ''), '-', '') IN ({})", classes.join(", "));
+        f.clauses.push(match courses.is_empty() {
+            true => class_match,
+            false => format!("(course IN ({}) OR {})", courses.join(", "), class_match),
+        });
+    }
+    if let Some(since) = query.since {
+        let since = f.bind(SqlValue::Int(since.timestamp()));
+        f.clauses.push(format!("date >= {}", since));
+    }
+    if let Some(until) = query.until {
+        let until = f.bind(SqlValue::Int(until.timestamp()));
+        f.clauses.push(format!("date <= {}", until));
+    }
+    if let Some(grade) = &query.grade {
+        let grade = f.bind(SqlValue::Text(grade.to_uppercase()));
+        f.clauses.push(format!("UPPER(grade) = {}", grade));
+    }
+    if let Some(min) = query.min_thumb_score {
+        let min = f.bind(SqlValue::Int(min));
+        f.clauses.push(format!("thumbs_up - thumbs_down >= {}", min));
+    }
+    if !query.exclude.is_empty() {
+        let ids: Vec<String> = query.exclude.iter().map(|id| f.bind(SqlValue::Int(*id as i64))).collect();
+        f.clauses.push(format!("(legacy_id IS NULL OR legacy_id NOT IN ({}))", ids.join(", ")));
+    }
+    if let (true, Some(after)) = (keyset, query.after) {
+        let date = f.bind(SqlValue::Int(after.date));
+        let same_date = f.bind(SqlValue::Int(after.date));
+        let id = f.bind(SqlValue::Int(after.id as i64));
+        f.clauses.push(format!("(date < {} OR (date = {} AND COALESCE(legacy_id, 0) < {}))", date, same_date, id));
+    }
+
+    (f.clauses.join(" AND "), f.values)
+}
+
+pub fn ratings_order(sort: RatingsSort) -> &'static str {
+    match sort {
+        RatingsSort::Newest => "date DESC, COALESCE(legacy_id, 0) DESC",
+        RatingsSort::Oldest => "date ASC, COALESCE(legacy_id, 0) ASC",
+        RatingsSort::Helpful => "thumbs_up - thumbs_down DESC, date DESC",
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const BACKOFF_BASE_SECS: u64 = 1;
+const BACKOFF_MAX_SECS: u64 = 300;
+const MAX_RESTARTS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    Restarting,
+    Finished,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskHealth {
+    pub name: String,
+    pub state: TaskState,
+    pub started_at: DateTime<Utc>,
+    pub restarts: u32,
+    pub last_panic: Option<String>,
+    pub last_panic_at: Option<DateTime<Utc>>,
+}
+
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
+
+fn backoff(failures: u32) -> Duration {
+    let secs = BACKOFF_BASE_SECS.saturating_mul(1 << failures.min(16)).min(BACKOFF_MAX_SECS);
+
+    Duration::from_secs(secs)
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    tasks: Mutex<BTreeMap<String, TaskHealth>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor::default()
+    }
+
+    pub fn spawn<F, Fut>(self: &Arc<Self>, name: &str, task: F)
+        where F: Fn() -> Fut + 'static,
+              Fut: Future<Output = ()> + 'static
+    {
+        let supervisor = self.clone();
+        let name = name.to_owned();
+
+        actix_rt::spawn(async move {
+            supervisor.supervise(name, task).await
+        });
+    }
+
+    async fn supervise<F, Fut>(&self, name: String, task: F)
+        where F: Fn() -> Fut,
+              Fut: Future<Output = ()>
+    {
+        let mut failures = 0;
+
+        loop {
+            let started_at = Utc::now();
+            self._update(name.as_str(), |h| {
+                h.state = TaskState::Running;
+                h.started_at = started_at;
+            });
+
+            let payload = match AssertUnwindSafe(task()).catch_unwind().await {
+                Ok(()) => {
+                    self._update(name.as_str(), |h| h.state = TaskState::Finished);
+                    return;
+                }
+                Err(payload) => payload,
+            };
+
+            let message = panic_message(payload.as_ref());
+            println!("supervise: {} panicked after {}s: {}", name, (Utc::now() - started_at).num_seconds(), message);
+
+            if (Utc::now() - started_at).num_seconds() as u64 > BACKOFF_MAX_SECS {
+                failures = 0;
+            }
+            failures += 1;
+
+            let state = if failures > MAX_RESTARTS { TaskState::Failed } else { TaskState::Restarting };
+            self._update(name.as_str(), |h| {
+                h.state = state;
+                h.restarts += 1;
+                h.last_panic = Some(message.clone());
+                h.last_panic_at = Some(Utc::now());
+            });
+
+            if state == TaskState::Failed {
+                println!("supervise: {} failed {} times, giving up", name, failures);
+                return;
+            }
+
+            let delay = backoff(failures - 1);
+            println!("supervise: restarting {} in {}s", name, delay.as_secs());
+            tokio::time::delay_for(delay).await;
+        }
+    }
+
+    fn _update<U: FnOnce(&mut TaskHealth)>(&self, name: &str, update: U) {
+        let mut tasks = self.tasks.lock().unwrap();
+
+        let health = tasks.entry(name.to_owned()).or_insert_with(|| TaskHealth {
+            name: name.to_owned(),
+            state: TaskState::Running,
+            started_at: Utc::now(),
+            restarts: 0,
+            last_panic: None,
+            last_panic_at: None,
+        });
+        update(health);
+    }
+
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.tasks.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn unhealthy(&self) -> Vec<String> {
+        self.tasks.lock().unwrap().values()
+            .filter(|h| h.state == TaskState::Failed || h.state == TaskState::Restarting)
+            .map(|h| h.name.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,132 @@
+use ucsb_courses::models::ProfessorCard;
+
+const WIDTH: u32 = 600;
+const HEIGHT: u32 = 314;
+const MAX_TITLE: usize = 32;
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            '\'' => "&apos;".to_owned(),
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => String::new(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+
+    format!("{}…", text.chars().take(max - 1).collect::<String>())
+}
+
+fn score_color(score: Option<f32>) -> &'static str {
+    match score {
+        Some(q) if q >= 4.0 => "#2e7d32",
+        Some(q) if q >= 3.0 => "#f9a825",
+        Some(q) if q >= 2.0 => "#ef6c00",
+        Some(_) => "#c62828",
+        None => "#9e9e9e",
+    }
+}
+
+pub fn render_card(card: &ProfessorCard) -> String {
+    let rounded = card.score.filter(|q| q.is_finite()).map(|q| (q * 10.0).round() / 10.0);
+    let score = rounded.map(|q| format!("{:.1}", q)).unwrap_or_else(|| "–".to_owned());
+    let difficulty = card.difficulty
+        .map(|d| format!("Difficulty {:.1} / 5", d))
+        .unwrap_or_default();
+
+    let tags: String = card.top_tags.iter().enumerate()
+        .map(|(i, tag)| format!(
+            "<text x=\"32\" y=\"{}\" font-size=\"18\" fill=\"#444\">• {}</text>",
+            206 + i as u32 * 28, escape(truncate(tag, 40).as_str()),
+        ))
+        .collect();
+
+    format!(concat!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"Helvetica, Arial, sans-serif\">",
+        "<rect width=\"{w}\" height=\"{h}\" rx=\"16\" fill=\"#fafafa\" stroke=\"#ddd\"/>",
+        "<text x=\"32\" y=\"64\" font-size=\"32\" font-weight=\"bold\" fill=\"#111\">{title}</text>",
+        "<text x=\"32\" y=\"100\" font-size=\"20\" fill=\"#666\">{subtitle}</text>",
+        "<text x=\"32\" y=\"142\" font-size=\"20\" fill=\"#666\">{label}</text>",
+        "<text x=\"32\" y=\"172\" font-size=\"18\" fill=\"#666\">{difficulty}</text>",
+        "{tags}",
+        "<circle cx=\"500\" cy=\"150\" r=\"64\" fill=\"{color}\"/>",
+        "<text x=\"500\" y=\"166\" font-size=\"44\" font-weight=\"bold\" fill=\"#fff\" text-anchor=\"middle\">{score}</text>",
+        "</svg>",
+    ),
+        w = WIDTH,
+        h = HEIGHT,
+        title = escape(truncate(card.title.as_str(), MAX_TITLE).as_str()),
+        subtitle = escape(truncate(card.subtitle.as_str(), 48).as_str()),
+        label = escape(card.score_label.as_str()),
+        difficulty = difficulty,
+        tags = tags,
+        color = score_color(rounded),
+        score = score,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(title: &str, score: Option<f32>) -> ProfessorCard {
+        ProfessorCard {
+            title: title.to_owned(),
+            subtitle: "Computer Science".to_owned(),
+            score,
+            score_label: "4.5 / 5 from 20 ratings".to_owned(),
+            difficulty: Some(3.0),
+            num_ratings: 20,
+            top_tags: vec!["Clear grading".to_owned()],
+            url: "https://www.ratemyprofessors.com/professor/1".to_owned(),
+            fetched_at: None,
+        }
+    }
+
+    #[test]
+    fn svg_cards_escape_professor_text() {
+        let svg = render_card(&card("Ada <Lovelace> & Co", Some(4.5)));
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("Ada &lt;Lovelace&gt; &amp; Co"));
+        assert!(svg.contains("Difficulty 3.0 / 5"));
+        assert!(svg.contains("• Clear grading"));
+        assert!(svg.contains("#2e7d32"));
+    }
+
+    #[test]
+    fn long_titles_are_cut_before_escaping() {
+        let title = format!("{}&&&", "a".repeat(MAX_TITLE - 2));
+        let svg = render_card(&card(title.as_str(), None));
+
+        assert!(svg.contains(format!("{}&amp;…</text>", "a".repeat(MAX_TITLE - 2)).as_str()));
+        assert!(!svg.contains("&am…"));
+        assert_eq!(truncate("é".repeat(MAX_TITLE).as_str(), MAX_TITLE), "é".repeat(MAX_TITLE));
+    }
+
+    #[test]
+    fn control_characters_never_reach_the_markup() {
+        let svg = render_card(&card("Ada\u{0}\u{1b}[31m Lovelace\"'", None));
+
+        assert!(svg.contains("Ada[31m Lovelace&quot;&apos;"));
+        assert!(!svg.chars().any(|c| c == '\u{0}' || c == '\u{1b}'));
+    }
+
+    #[test]
+    fn score_colors_follow_the_displayed_score() {
+        let svg = render_card(&card("Ada", Some(3.96)));
+        assert!(svg.contains(">4.0</text>") && svg.contains("#2e7d32"));
+
+        let svg = render_card(&card("Ada", Some(f32::NAN)));
+        assert!(svg.contains(">–</text>") && svg.contains("#9e9e9e"));
+    }
+}
@@ -0,0 +1,205 @@
+use serde::Deserialize;
+use unicode_normalization::UnicodeNormalization;
+
+const ENTITIES: &[(&str, &str)] = &[
+    ("&quot;", "\""),
+    ("&#34;", "\""),
+    ("&apos;", "'"),
+    ("&#39;", "'"),
+    ("&lt;", "<"),
+    ("&gt;", ">"),
+    ("&nbsp;", " "),
+    ("&amp;", "&"),
+];
+
+pub fn decode_entities(text: &str) -> String {
+    ENTITIES.iter().fold(text.to_owned(), |acc, (entity, replacement)| acc.replace(entity, replacement))
+}
+
+const ZERO_WIDTH: &[char] = &['\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}', '\u{feff}', '\u{00ad}'];
+
+fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '<' if !in_tag && chars.peek().map(|n| n.is_ascii_alphabetic() || *n == '/' || *n == '!').unwrap_or(false) => {
+                in_tag = true;
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                out.push(' ');
+            }
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+
+    out
+}
+
+fn normalize_char(c: char) -> Option<char> {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{2032}' => Some('\''),
+        '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{2033}' => Some('"'),
+        '\u{2013}' | '\u{2014}' => Some('-'),
+        c if ZERO_WIDTH.contains(&c) => None,
+        c => Some(c),
+    }
+}
+
+pub fn sanitize(text: &str) -> String {
+    let decoded = strip_tags(decode_entities(text).as_str());
+
+    let normalized: String = decoded.nfc()
+        .filter_map(normalize_char)
+        .collect();
+
+    normalized.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextProfile {
+    #[default]
+    Raw,
+    Plain,
+    Ascii,
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1f000..=0x1faff | 0x2600..=0x27bf | 0x2b00..=0x2bff)
+}
+
+fn is_emoji_modifier(c: char) -> bool {
+    matches!(c as u32, 0x1f3fb..=0x1f3ff | 0xfe00..=0xfe0f | 0xe0020..=0xe007f | 0x20e3 | 0x200d)
+}
+
+fn plain_char(c: char, out: &mut String) {
+    match c {
+        '\u{2026}' => out.push_str("..."),
+        '\u{00a0}' | '\u{2002}'..='\u{200a}' | '\u{202f}' => out.push(' '),
+        '\u{00ab}' | '\u{00bb}' => out.push('"'),
+        '\u{2039}' | '\u{203a}' => out.push('\''),
+        '\u{2022}' | '\u{2212}' => out.push('-'),
+        c if is_emoji_modifier(c) => {}
+        c if is_emoji(c) => out.push(' '),
+        c => out.push(c),
+    }
+}
+
+fn ascii_char(c: char, out: &mut String) {
+    match c {
+        'ß' => out.push_str("ss"),
+        'æ' => out.push_str("ae"),
+        'Æ' => out.push_str("AE"),
+        'œ' => out.push_str("oe"),
+        'Œ' => out.push_str("OE"),
+        'ø' => out.push('o'),
+        'Ø' => out.push('O'),
+        'ł' => out.push('l'),
+        'Ł' => out.push('L'),
+        'đ' => out.push('d'),
+        'Đ' => out.push('D'),
+        '\u{2044}' => out.push('/'),
+        c if c.is_ascii() => out.push(c),
+        _ => {}
+    }
+}
+
+pub fn normalize(text: &str, profile: TextProfile) -> String {
+    let plain = match profile {
+        TextProfile::Raw => return text.to_owned(),
+        TextProfile::Plain | TextProfile::Ascii => {
+            let mut out = String::with_capacity(text.len());
+            text.chars().for_each(|c| plain_char(c, &mut out));
+            out
+        }
+    };
+
+    let normalized: String = match profile {
+        TextProfile::Ascii => {
+            let mut out = String::with_capacity(plain.len());
+            plain.nfkd().for_each(|c| ascii_char(c, &mut out));
+            out
+        }
+        _ => plain,
+    };
+
+    normalized.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+const WORDS_PER_MINUTE: u32 = 238;
+
+pub fn word_count(text: &str) -> u32 {
+    text.split_whitespace()
+        .filter(|w| w.chars().any(|c| c.is_alphanumeric()))
+        .count() as u32
+}
+
+pub fn reading_seconds(words: u32) -> u32 {
+    (words * 60).div_ceil(WORDS_PER_MINUTE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_tags_are_stripped_after_decoding() {
+        assert_eq!(sanitize("&lt;script&gt;alert(1)&lt;/script&gt; nice"), "alert(1) nice");
+        assert_eq!(sanitize("&lt;img src=x onerror=alert(1)&gt;"), "");
+        assert_eq!(sanitize("Tom &amp; Jerry &lt;3"), "Tom & Jerry <3");
+        assert_eq!(sanitize("&amp;lt;b&amp;gt;"), "&lt;b&gt;");
+    }
+
+    #[test]
+    fn nested_and_broken_tags_leave_no_markup() {
+        assert_eq!(sanitize("<div><p>Great <b>class</b></p></div>"), "Great class");
+        assert!(!sanitize("<scr<b>ipt>alert(1)</scr</b>ipt>").contains('<'));
+        assert!(!sanitize("<<b>script>alert(1)").contains("<script"));
+        assert_eq!(sanitize("2 < 3 and 5 > 4"), "2 < 3 and 5 > 4");
+        assert_eq!(sanitize("fine <!-- hidden --> text"), "fine text");
+    }
+
+    #[test]
+    fn zero_width_characters_and_smart_quotes_normalize() {
+        assert_eq!(sanitize("ex\u{200b}am\u{feff}ple"), "example");
+        assert_eq!(sanitize("soft\u{00ad}ware"), "software");
+        assert_eq!(sanitize("\u{201c}best\u{201d} prof\u{2019}s class \u{2014} really"), "\"best\" prof's class - really");
+        assert_eq!(sanitize("cafe\u{0301}"), "caf\u{00e9}");
+        assert_eq!(sanitize("  spaced \n\t out&nbsp;&nbsp;here "), "spaced out here");
+    }
+
+    #[test]
+    fn profiles_strip_emoji_and_accents() {
+        let comment = "Great prof \u{1f44d}\u{1f3fd} \u{2014} caf\u{00e9}\u{00a0}chats\u{2026}";
+
+        assert_eq!(normalize(comment, TextProfile::Raw), comment);
+        assert_eq!(normalize(comment, TextProfile::Plain), "Great prof \u{2014} caf\u{00e9} chats...");
+        assert_eq!(normalize(comment, TextProfile::Ascii), "Great prof cafe chats...");
+    }
+    #[test]
+    fn emoji_sequences_leave_no_joiners_behind() {
+        assert_eq!(normalize("\u{1f469}\u{200d}\u{1f4bb} great TA", TextProfile::Plain), "great TA");
+        assert_eq!(normalize("\u{1f1fa}\u{1f1f8} flag \u{1f44d}\u{1f3fd}", TextProfile::Plain), "flag");
+        assert_eq!(normalize("gets a 1\u{fe0f}\u{20e3}0", TextProfile::Ascii), "gets a 10");
+    }
+
+    #[test]
+    fn ascii_folds_letters_without_decompositions() {
+        assert_eq!(normalize("Stra\u{00df}e \u{00c6}sop \u{0141}\u{00f3}d\u{017a} \u{00d8}rsted", TextProfile::Ascii), "Strasse AEsop Lodz Orsted");
+        assert_eq!(normalize("\u{fb01}nal \u{00bd} \u{2116}1", TextProfile::Ascii), "final 1/2 No1");
+        assert_eq!(normalize("\u{4e2d}\u{6587} only", TextProfile::Ascii), "only");
+    }
+
+    #[test]
+    fn word_counts_skip_punctuation() {
+        assert_eq!(word_count("Great - really great !!"), 3);
+        assert_eq!(reading_seconds(0), 0);
+        assert_eq!(reading_seconds(word_count("one")), 1);
+        assert_eq!(reading_seconds(238), 60);
+    }
+}
@@ -0,0 +1,178 @@
+use serde::{Serialize, Deserialize};
+use futures::TryFutureExt;
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::quarter::Term;
+
+const CLASSES_SEARCH_URL: &str =
+    "https://api.ucsb.edu/academics/curriculums/v3/classes/search";
+
+#[cfg(feature = "scheduler")]
+const CLASS_SECTION_URL: &str =
+    "https://api.ucsb.edu/academics/curriculums/v3/classsection";
+
+const PAGE_SIZE: u32 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassResponse {
+    #[serde(rename = "courseId")]
+    pub course_id: String,
+    pub title: String,
+    #[serde(rename = "deptCode")]
+    pub dept_code: String,
+    #[serde(rename = "subjectArea")]
+    pub subject_area: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "classSections", default)]
+    pub class_sections: Vec<ClassSection>,
+    #[serde(rename = "generalEducation", default)]
+    pub general_education: Vec<GeneralEducation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneralEducation {
+    #[serde(rename = "geCode")]
+    pub ge_code: String,
+    #[serde(rename = "geCollege", default)]
+    pub ge_college: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassSection {
+    #[serde(rename = "enrollCode")]
+    pub enroll_code: String,
+    pub section: String,
+    #[serde(rename = "timeLocations", default)]
+    pub time_locations: Vec<TimeLocation>,
+    #[serde(default)]
+    pub instructors: Vec<Instructor>,
+    #[serde(rename = "enrolledTotal", default)]
+    pub enrolled_total: Option<u32>,
+    #[serde(rename = "maxEnroll", default)]
+    pub max_enroll: Option<u32>,
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeLocation {
+    pub days: Option<String>,
+    #[serde(rename = "beginTime")]
+    pub begin_time: Option<String>,
+    #[serde(rename = "endTime")]
+    pub end_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instructor {
+    pub instructor: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClassesSearchResponse {
+    pub total: u32,
+    pub classes: Vec<ClassResponse>,
+}
+
+pub fn instructor_subjects(classes: &[ClassResponse]) -> HashMap<String, String> {
+    let mut counts: HashMap<String, HashMap<String, usize>> = HashMap::new();
+
+    for class in classes {
+        for section in class.class_sections.iter() {
+            for instructor in section.instructors.iter() {
+                *counts.entry(instructor.instructor.trim().to_owned()).or_default()
+                    .entry(class.subject_area.trim().to_owned()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    counts.into_iter()
+        .filter_map(|(instructor, subjects)| {
+            subjects.into_iter()
+                .max_by(|(a, ca), (b, cb)| ca.cmp(cb).then(b.cmp(a)))
+                .map(|(subject, _)| (instructor, subject))
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct Catalog {
+    api_key: String,
+
+    client: reqwest::Client,
+}
+
+impl Catalog {
+    pub fn new(api_key: String) -> Self {
+        Catalog {
+            api_key,
+            client: reqwest::Client::default(),
+        }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        std::env::var("UCSB_API_KEY").ok().map(Self::new)
+    }
+
+    pub async fn classes(&self, quarter: &str) -> Result<Vec<ClassResponse>, Error> {
+        let term = Term::parse(quarter);
+        let quarter = term.map(|t| t.ucsb_code()).unwrap_or_else(|| quarter.to_owned());
+
+        let mut classes = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let resp = self.client.get(CLASSES_SEARCH_URL)
+                .query(&[
+                    ("quarter", quarter.clone()),
+                    ("pageNumber", page.to_string()),
+                    ("pageSize", PAGE_SIZE.to_string()),
+                    ("includeClassSections", "true".to_owned()),
+                ])
+                .header("ucsb-api-key", self.api_key.as_str())
+                .send()
+                .and_then(|r| async move { r.json::<ClassesSearchResponse>().await })
+                .map_err(|e| {
+                    println!("ucsb classes: error {}", e);
+                    Error::Ucsb
+                }).await?;
+
+            let received = resp.classes.len();
+            classes.extend(resp.classes);
+
+            if received == 0 || classes.len() as u32 >= resp.total {
+                break;
+            }
+            page += 1;
+        }
+
+        if let Some(session) = term.and_then(|t| t.session) {
+            for class in classes.iter_mut() {
+                class.class_sections.retain(|s| session.matches(s.session.as_deref().unwrap_or("")));
+            }
+            classes.retain(|c| !c.class_sections.is_empty());
+        }
+
+        Ok(classes)
+    }
+
+    #[cfg(feature = "scheduler")]
+    pub async fn section(&self, quarter: &str, enroll_code: &str) -> Result<ClassSection, Error> {
+        let quarter = Term::parse(quarter).map(|t| t.ucsb_code()).unwrap_or_else(|| quarter.to_owned());
+
+        let class = self.client.get(format!("{}/{}/{}", CLASS_SECTION_URL, quarter, enroll_code).as_str())
+            .header("ucsb-api-key", self.api_key.as_str())
+            .send()
+            .and_then(|r| async move { r.json::<ClassResponse>().await })
+            .map_err(|e| {
+                println!("ucsb section: error {}", e);
+                Error::Ucsb
+            }).await?;
+
+        class.class_sections.into_iter()
+            .find(|s| s.enroll_code == enroll_code)
+            .ok_or(Error::NotFound)
+    }
+}
@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::course;
+use crate::error::Error;
+use crate::name;
+
+const MAX_NAME_LEN: usize = 100;
+const MAX_SLUG_LEN: usize = 32;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: &'static str,
+}
+
+pub trait Validate {
+    fn errors(&self) -> Vec<FieldError>;
+
+    fn validate(&self) -> Result<(), Error> {
+        let errors = self.errors();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Validation(errors))
+        }
+    }
+}
+
+impl<T: Validate> Validate for [T] {
+    fn errors(&self) -> Vec<FieldError> {
+        self.iter().flat_map(|v| v.errors()).collect()
+    }
+}
+
+pub struct Fields(Vec<FieldError>);
+
+impl Fields {
+    pub fn new() -> Self {
+        Fields(Vec::new())
+    }
+
+    pub fn name(mut self, field: &'static str, value: &str) -> Self {
+        if value.trim().is_empty() {
+            self.0.push(FieldError { field, message: "must not be empty" });
+        } else if value.chars().count() > MAX_NAME_LEN {
+            self.0.push(FieldError { field, message: "must be at most 100 characters" });
+        } else if value.chars().any(|c| c.is_control()) {
+            self.0.push(FieldError { field, message: "must contain only printable characters" });
+        }
+
+        self
+    }
+
+    pub fn professor(self, field: &'static str, value: &str) -> Self {
+        let before = self.0.len();
+        let mut fields = self.name(field, value);
+        if fields.0.len() == before && name::is_trivial(value) {
+            fields.0.push(FieldError { field, message: "must contain at least two letters besides titles" });
+        }
+
+        fields
+    }
+
+    pub fn course(mut self, field: &'static str, value: &str) -> Self {
+        if value.chars().count() > MAX_NAME_LEN || course::normalize_course(value).is_none() {
+            self.0.push(FieldError { field, message: "must look like a course code such as CMPSC 130A" });
+        }
+
+        self
+    }
+
+    pub fn school(mut self, field: &'static str, value: Option<&str>) -> Self {
+        if let Some(value) = value {
+            if value.is_empty() || value.len() > MAX_SLUG_LEN
+                || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                self.0.push(FieldError { field, message: "must be a school slug" });
+            }
+        }
+
+        self
+    }
+
+    pub fn range(mut self, field: &'static str, value: Option<f64>, min: f64, max: f64) -> Self {
+        if let Some(value) = value {
+            if !(min..=max).contains(&value) {
+                self.0.push(FieldError { field, message: "out of range" });
+            }
+        }
+
+        self
+    }
+
+    pub fn check(mut self, field: &'static str, ok: bool, message: &'static str) -> Self {
+        if !ok {
+            self.0.push(FieldError { field, message });
+        }
+
+        self
+    }
+
+    pub fn finish(self) -> Vec<FieldError> {
+        self.0
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    R0,
+    R1,
+}
+
+impl ApiVersion {
+    pub fn from_path(path: &str) -> Option<Self> {
+        match path.trim_start_matches('/').split('/').next()? {
+            "r0" => Some(ApiVersion::R0),
+            "r1" => Some(ApiVersion::R1),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VersionPolicy {
+    pub deprecated_at: Option<DateTime<Utc>>,
+    pub sunset_at: Option<DateTime<Utc>>,
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VersionsConfig {
+    pub r0: VersionPolicy,
+    pub r1: VersionPolicy,
+}
+
+impl VersionsConfig {
+    pub fn policy(&self, version: ApiVersion) -> &VersionPolicy {
+        match version {
+            ApiVersion::R0 => &self.r0,
+            ApiVersion::R1 => &self.r1,
+        }
+    }
+
+    pub fn headers(&self, path: &str) -> Vec<(&'static str, String)> {
+        let policy = match ApiVersion::from_path(path) {
+            Some(version) => self.policy(version),
+            None => return Vec::new(),
+        };
+
+        let mut headers = Vec::new();
+        if let Some(at) = policy.deprecated_at {
+            headers.push(("deprecation", format!("@{}", at.timestamp())));
+        }
+        if let Some(at) = policy.sunset_at {
+            headers.push(("sunset", at.format(HTTP_DATE).to_string()));
+        }
+        if let Some(link) = &policy.link {
+            headers.push(("link", format!("<{}>; rel=\"deprecation\"", link)));
+        }
+
+        headers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn deprecated_versions_get_headers() {
+        let versions = VersionsConfig {
+            r0: VersionPolicy {
+                deprecated_at: Some(Utc.timestamp(1_700_000_000, 0)),
+                sunset_at: Some(Utc.ymd(2027, 1, 1).and_hms(0, 0, 0)),
+                link: Some("https://example.com/migrating-to-r1".to_owned()),
+            },
+            ..VersionsConfig::default()
+        };
+
+        assert_eq!(versions.headers("/r0/ucsb/professor/ada/overview"), vec![
+            ("deprecation", "@1700000000".to_owned()),
+            ("sunset", "Fri, 01 Jan 2027 00:00:00 GMT".to_owned()),
+            ("link", "<https://example.com/migrating-to-r1>; rel=\"deprecation\"".to_owned()),
+        ]);
+        assert!(versions.headers("/r1/professor/ada/comments").is_empty());
+        assert!(versions.headers("/admin/audit").is_empty());
+    }
+
+    #[test]
+    fn versions_match_whole_path_segments() {
+        assert_eq!(ApiVersion::from_path("r0"), Some(ApiVersion::R0));
+        assert_eq!(ApiVersion::from_path("//r1/ucsb"), Some(ApiVersion::R1));
+        assert_eq!(ApiVersion::from_path("/r01/ucsb"), None);
+        assert_eq!(ApiVersion::from_path("/R0/ucsb"), None);
+        assert_eq!(ApiVersion::from_path("/"), None);
+        assert_eq!(ApiVersion::from_path(""), None);
+    }
+
+    #[test]
+    fn partial_policies_only_emit_what_is_set() {
+        let versions = VersionsConfig {
+            r1: VersionPolicy { sunset_at: Some(Utc.ymd(2030, 6, 15).and_hms(8, 5, 9)), ..VersionPolicy::default() },
+            ..VersionsConfig::default()
+        };
+
+        assert_eq!(versions.headers("/r1/ucsb"), vec![("sunset", "Sat, 15 Jun 2030 08:05:09 GMT".to_owned())]);
+        assert!(versions.headers("/r0/ucsb").is_empty());
+    }
+}
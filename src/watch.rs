@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::Mutex;
+
+use crate::error::Error;
+use crate::mail::Mailer;
+use crate::quarter::Term;
+use crate::ucsb::Catalog;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchRequest {
+    pub enroll_code: String,
+    pub quarter: Option<String>,
+    pub webhook: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Watch {
+    pub id: u64,
+    pub enroll_code: String,
+    pub quarter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub seats_open: Option<bool>,
+    pub notified_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeatNotification {
+    pub watch_id: u64,
+    pub enroll_code: String,
+    pub quarter: String,
+    pub enrolled: u32,
+    pub capacity: u32,
+}
+
+impl SeatNotification {
+    fn template_values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("watch_id", self.watch_id.to_string()),
+            ("enroll_code", self.enroll_code.clone()),
+            ("quarter", self.quarter.clone()),
+            ("enrolled", self.enrolled.to_string()),
+            ("capacity", self.capacity.to_string()),
+        ]
+    }
+}
+
+struct Watches {
+    next_id: u64,
+    watches: BTreeMap<u64, Watch>,
+}
+
+pub struct Watchlist {
+    catalog: Option<Catalog>,
+    default_quarter: String,
+    client: reqwest::Client,
+    mailer: Option<Mailer>,
+    watches: Mutex<Watches>,
+}
+
+impl Watchlist {
+    pub fn new(catalog: Option<Catalog>, default_quarter: String, mailer: Option<Mailer>) -> Self {
+        Watchlist {
+            catalog,
+            default_quarter,
+            client: reqwest::Client::default(),
+            mailer,
+            watches: Mutex::new(Watches {
+                next_id: 1,
+                watches: BTreeMap::new(),
+            }),
+        }
+    }
+
+    pub async fn register(&self, request: WatchRequest) -> Result<Watch, Error> {
+        if self.catalog.is_none() {
+            return Err(Error::Config("no UCSB API key configured".to_owned()));
+        }
+        if request.email.is_some() && self.mailer.is_none() {
+            return Err(Error::Config("no SMTP server configured".to_owned()));
+        }
+
+        let mut watches = self.watches.lock().await;
+
+        let watch = Watch {
+            id: watches.next_id,
+            enroll_code: request.enroll_code,
+            quarter: request.quarter.as_deref()
+                .and_then(Term::parse)
+                .map(|t| t.code())
+                .unwrap_or_else(|| self.default_quarter.clone()),
+            webhook: request.webhook,
+            email: request.email,
+            created_at: Utc::now(),
+            seats_open: None,
+            notified_at: None,
+        };
+
+        watches.next_id += 1;
+        watches.watches.insert(watch.id, watch.clone());
+
+        Ok(watch)
+    }
+
+    pub async fn poll(&self) -> Result<usize, Error> {
+        let catalog = self.catalog.as_ref()
+            .ok_or_else(|| Error::Config("no UCSB API key configured".to_owned()))?;
+
+        let sections: Vec<(String, String)> = {
+            let watches = self.watches.lock().await;
+
+            let mut sections: Vec<(String, String)> = watches.watches.values()
+                .map(|w| (w.quarter.clone(), w.enroll_code.clone()))
+                .collect();
+            sections.sort();
+            sections.dedup();
+
+            sections
+        };
+
+        let mut counts: HashMap<(String, String), (u32, u32)> = HashMap::new();
+        for (quarter, enroll_code) in sections {
+            match catalog.section(quarter.as_str(), enroll_code.as_str()).await {
+                Ok(section) => {
+                    if let (Some(enrolled), Some(capacity)) = (section.enrolled_total, section.max_enroll) {
+                        counts.insert((quarter, enroll_code), (enrolled, capacity));
+                    }
+                }
+                Err(e) => println!("poll: section {} {} error {}", quarter, enroll_code, e),
+            }
+        }
+
+        let notifications: Vec<(Watch, SeatNotification)> = {
+            let mut watches = self.watches.lock().await;
+            let mut notifications = Vec::new();
+
+            for watch in watches.watches.values_mut() {
+                let (enrolled, capacity) = match counts.get(&(watch.quarter.clone(), watch.enroll_code.clone())) {
+                    Some(count) => *count,
+                    None => continue,
+                };
+
+                let open = enrolled < capacity;
+                if open && watch.seats_open != Some(true) {
+                    watch.notified_at = Some(Utc::now());
+                    notifications.push((watch.clone(), SeatNotification {
+                        watch_id: watch.id,
+                        enroll_code: watch.enroll_code.clone(),
+                        quarter: watch.quarter.clone(),
+                        enrolled,
+                        capacity,
+                    }));
+                }
+                watch.seats_open = Some(open);
+            }
+
+            notifications
+        };
+
+        let mut sent = 0;
+        for (watch, notification) in notifications {
+            if let Some(webhook) = &watch.webhook {
+                match self.client.post(webhook.as_str()).json(&notification).send().await {
+                    Ok(_) => sent += 1,
+                    Err(e) => println!("poll: webhook {} error {}", webhook, e),
+                }
+            }
+
+            if let (Some(email), Some(mailer)) = (&watch.email, &self.mailer) {
+                match mailer.notify(email.as_str(), notification.template_values().as_slice()).await {
+                    Ok(true) => sent += 1,
+                    Ok(false) => {}
+                    Err(e) => println!("poll: email {} error {}", email, e),
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+}